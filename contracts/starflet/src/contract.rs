@@ -163,6 +163,9 @@ pub fn try_add_planet(
                         info: planet.asset_info,
                         amount: Uint128::from(VALIDATION_AMOUNT),
                     },
+                    proof: None,
+                    recipient: None,
+                    min_shares_out: None,
                 })?,
             }),
             MSG_REPLY_ID_BOND,
@@ -193,7 +196,10 @@ pub fn reply(deps: DepsMut, env: Env, msg: Reply) -> Result<Response, ContractEr
                     msg: to_binary(&cw20::Cw20ExecuteMsg::Send {
                         contract: tmp_add_planet.planet_addr.to_string(),
                         amount: balance,
-                        msg: to_binary(&PlanetCw20HookMsg::Unbond {})?,
+                        msg: to_binary(&PlanetCw20HookMsg::Unbond {
+                            recipient: None,
+                            min_assets_out: None,
+                        })?,
                     })?,
                 }),
                 MSG_REPLY_ID_UNBOND,
@@ -1,9 +1,8 @@
 #[cfg(not(feature = "library"))]
 use cosmwasm_std::entry_point;
 use cosmwasm_std::{
-    from_binary, to_binary, Addr, Attribute, BankMsg, Binary, Coin, ContractResult, CosmosMsg,
-    Deps, DepsMut, Env, MessageInfo, Reply, ReplyOn, Response, StdError, StdResult, SubMsg,
-    Uint128, WasmMsg,
+    to_binary, Addr, Attribute, Binary, Coin, ContractResult, CosmosMsg, Deps, DepsMut, Env,
+    MessageInfo, Reply, ReplyOn, Response, StdError, StdResult, SubMsg, Uint128, WasmMsg,
 };
 use cw2::set_contract_version;
 use terra_cosmwasm::TerraMsgWrapper;
@@ -15,25 +14,39 @@ use crate::{
     },
     querier::query_epoch_state,
     state::{
-        get_anchor_info, get_deposit_asset_info, get_router, get_tmp_bonder, get_tmp_swap,
-        remove_tmp_swap, set_deposit_asset_info, set_router, set_tmp_bonder, set_tmp_swap,
+        add_deposited, add_withdrawn, get_anchor_info, get_deposit_asset_info, get_deposit_basis,
+        get_pending_router, get_router, get_tmp_bonder, get_tmp_swap, remove_pending_router,
+        remove_tmp_swap, set_deposit_asset_info, set_pending_router, set_router, set_tmp_bonder,
+        set_tmp_swap, PendingRouter,
     },
+    strategy::SwapArbitrage,
 };
 use starflet_protocol::planet::{
-    CommissionResponse, ConfigResponse as PlanetConfigResponse, Cw20HookMsg,
-    InstantiateMsg as PlanetInstantiateMsg, QueryMsg, StakerInfoResponse,
+    Action, CommissionResponse, ConfigResponse as PlanetConfigResponse, ConvertToAssetsResponse,
+    ConvertToSharesResponse, ExecuteMsg as PlanetExecuteMsg,
+    InstantiateMsg as PlanetInstantiateMsg, MaxBondResponse, PreviewBondResponse,
+    PreviewUnbondResponse, QueryMsg, StakerInfoResponse, TotalAssetsResponse,
 };
 
 use cosmwasm_bignumber::{Decimal256, Uint256};
 use planet::{
     contract::{
-        compute_share_rate, instantiate as planet_instantiate, query as planet_query,
-        query_config as query_planet_config, query_stake_info as planet_query_stake_info,
-        reply as planet_reply, try_bond as planet_bond,
-        try_update_config as try_planet_update_config,
+        assert_executor, assert_min_out, assert_not_paused, compute_mint_amount,
+        compute_redeem_amount, compute_total_assets, execute_with,
+        instantiate as planet_instantiate, load_config, query_config as query_planet_config,
+        query_max_bond as planet_query_max_bond, query_with, reply_with, staker_info, to_uint128,
+        try_apply_config as planet_apply_config, try_bond as planet_bond,
+        try_cancel_config as planet_cancel_config, try_set_pause, try_shutdown as planet_shutdown,
+        try_sync, try_update_config as try_planet_update_config, try_update_executors,
+        vaults_token,
     },
     error::ContractError as PlanetContractError,
-    state::{get_commission, get_config, set_vaults, sub_all_commission, sub_vaults, Config},
+    state::{
+        add_commission_claimed, add_staker_withdrawal, add_unbonded, get_commission,
+        get_pending_config, get_total_unbonding, migrate_state, set_vaults, sub_all_commission,
+        sub_vaults, Config,
+    },
+    vault_math,
 };
 use terraswap::{
     asset::{Asset, AssetInfo},
@@ -41,7 +54,7 @@ use terraswap::{
     router::{ExecuteMsg as TerraswapRouterExecute, SwapOperation},
 };
 
-use cw20::{Cw20ExecuteMsg, Cw20ReceiveMsg};
+use cw20::Cw20ExecuteMsg;
 use moneymarket::market::{
     Cw20HookMsg as MoneyMarketCw20HookMsg, ExecuteMsg as MoneyMarketExecuteMsg,
 };
@@ -59,16 +72,26 @@ pub fn instantiate(
 ) -> Result<Response, PlanetContractError> {
     set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
 
-    let router_addr = deps.api.addr_validate(&msg.router_addr).unwrap();
-    set_router(deps.branch(), router_addr).unwrap();
+    let router_addr = deps.api.addr_validate(&msg.router_addr)?;
+    set_router(deps.branch(), router_addr)?;
 
-    set_deposit_asset_info(deps.branch(), msg.deposit_asset_info).unwrap();
+    set_deposit_asset_info(deps.branch(), msg.deposit_asset_info)?;
 
     let planet_msg = PlanetInstantiateMsg {
         commission_rate: msg.commission_rate,
         asset_info: msg.asset_info,
         symbol: msg.symbol,
         token_code_id: msg.token_code_id,
+        unbonding_period: None,
+        max_total_deposit: None,
+        max_deposit_per_user: None,
+        access_mode: None,
+        guardian: msg.guardian,
+        max_rate_change: None,
+        config_delay: msg.config_delay,
+        marketing: msg.marketing,
+        token_admin: msg.token_admin,
+        vaults_token: msg.vaults_token,
     };
 
     planet_instantiate(deps, env, info, planet_msg)
@@ -82,91 +105,133 @@ pub fn execute(
     msg: ExecuteMsg,
 ) -> Result<Response<TerraMsgWrapper>, PlanetContractError> {
     match msg {
-        ExecuteMsg::Receive(msg) => receive_cw20(deps, env, info, msg),
+        ExecuteMsg::Receive(msg) => execute_with(
+            &SwapArbitrage,
+            deps,
+            env,
+            info,
+            PlanetExecuteMsg::Receive(msg),
+        ),
         ExecuteMsg::UpdateConfig {
             owner,
             commission_rate,
             code_id,
             router_addr,
-        } => try_update_config(deps, info, owner, commission_rate, code_id, router_addr),
-        ExecuteMsg::Bond { asset } => try_bond(deps, env, info, asset),
-        ExecuteMsg::Swap { path, amount } => try_swap(deps, env, path, amount),
-        ExecuteMsg::Claim {} => try_claim(deps, info),
+            guardian,
+            config_delay,
+        } => try_update_config(
+            deps,
+            env,
+            info,
+            owner,
+            commission_rate,
+            code_id,
+            router_addr,
+            guardian,
+            config_delay,
+        ),
+        ExecuteMsg::ApplyConfig {} => try_apply_config(deps, env, info),
+        ExecuteMsg::CancelConfig {} => try_cancel_config(deps, info),
+        ExecuteMsg::Bond {
+            asset,
+            recipient,
+            min_shares_out,
+        } => execute_with(
+            &SwapArbitrage,
+            deps,
+            env,
+            info,
+            PlanetExecuteMsg::Bond {
+                asset,
+                proof: None,
+                recipient,
+                min_shares_out,
+            },
+        ),
+        ExecuteMsg::Swap { path, amount } => {
+            assert_not_paused(deps.as_ref(), Action::Execute)?;
+            assert_executor(deps.as_ref(), &info.sender)?;
+            try_swap(deps, env, path, amount)
+        }
+        ExecuteMsg::Claim {} => {
+            assert_not_paused(deps.as_ref(), Action::Claim)?;
+            try_claim(deps, env, info)
+        }
+        ExecuteMsg::SetPause {
+            bond,
+            unbond,
+            execute,
+            claim,
+        } => try_set_pause(deps, info, bond, unbond, execute, claim),
+        ExecuteMsg::UpdateExecutors { add, remove } => {
+            try_update_executors(deps, info, add, remove)
+        }
+        ExecuteMsg::Sync { absorb_shortfall } => {
+            try_sync(&SwapArbitrage, deps, env, info, absorb_shortfall)
+        }
+        ExecuteMsg::BookBacking { amount } => execute_with(
+            &SwapArbitrage,
+            deps,
+            env,
+            info,
+            PlanetExecuteMsg::BookBacking { amount },
+        ),
+        ExecuteMsg::Shutdown { unwind } => try_shutdown(deps, env, info, unwind.unwrap_or(false)),
     }
 }
 
-pub fn receive_cw20(
+pub fn try_shutdown(
     mut deps: DepsMut,
     env: Env,
     info: MessageInfo,
-    cw20_msg: Cw20ReceiveMsg,
+    unwind: bool,
 ) -> Result<Response<TerraMsgWrapper>, PlanetContractError> {
-    let contract_addr = info.sender;
-    match from_binary(&cw20_msg.msg) {
-        Ok(Cw20HookMsg::Unbond {}) => {
-            // only asset contract can execute this message
-            let config: Config = get_config(deps.as_ref()).unwrap();
-            if contract_addr != config.clone().token_address.unwrap() {
-                return Err(PlanetContractError::Unauthorized {});
-            }
-
-            let share_rate =
-                compute_share_rate(deps.as_ref(), config.token_address.unwrap()).unwrap();
-            let unbond_amount = Uint256::from(cw20_msg.amount) * share_rate;
-
-            let anchor_info = get_anchor_info(deps.as_ref()).unwrap();
-
-            sub_vaults(deps.branch(), Decimal256::from_uint256(unbond_amount)).unwrap();
-
-            let deposit_asset_info = get_deposit_asset_info(deps.as_ref()).unwrap();
-            let balance = query_balance(
-                &deps.querier,
-                env.contract.address,
-                deposit_asset_info.to_string(),
-            )
-            .unwrap();
-            set_tmp_bonder(
-                deps.branch(),
-                Addr::unchecked(cw20_msg.sender.to_string()),
-                balance,
-            )
-            .unwrap();
+    let res = planet_shutdown(deps.branch(), info)?;
+    if !unwind {
+        return Ok(res);
+    }
 
-            Ok(Response::new()
-                .add_message(CosmosMsg::Wasm(WasmMsg::Execute {
-                    contract_addr: contract_addr.to_string(),
-                    funds: vec![],
-                    msg: to_binary(&Cw20ExecuteMsg::Burn {
-                        amount: cw20_msg.amount,
-                    })?,
-                }))
-                .add_submessage(SubMsg::reply_on_success(
-                    CosmosMsg::Wasm(WasmMsg::Execute {
-                        contract_addr: anchor_info.aust.to_string(),
-                        funds: vec![],
-                        msg: to_binary(&Cw20ExecuteMsg::Send {
-                            amount: unbond_amount.into(),
-                            contract: anchor_info.market_money.to_string(),
-                            msg: to_binary(&MoneyMarketCw20HookMsg::RedeemStable {}).unwrap(),
-                        })
-                        .unwrap(),
-                    }),
-                    MSG_REPLY_UNBOND,
-                )))
-        }
-        _ => Err(PlanetContractError::InvalidHookMsg {}),
+    // redeem the aUST of the vaults, the commission and the pending unbonds stay in aUST for the
+    // owner to claim and the unbonders to withdraw
+    let anchor_info = get_anchor_info(deps.as_ref())?;
+    let aust_addr = Addr::unchecked(anchor_info.aust.to_string());
+    let balance = query_token_balance(&deps.querier, aust_addr.clone(), env.contract.address)
+        .map_err(PlanetContractError::query_failed(&aust_addr))?;
+    let reserved = vault_math::checked_add(
+        get_total_unbonding(deps.as_ref())?,
+        get_commission(deps.as_ref())?,
+    )?;
+    let amount = balance.saturating_sub(to_uint128(Uint256::one() * reserved)?);
+    if amount.is_zero() {
+        return Ok(res);
     }
+
+    Ok(res
+        .add_message(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: aust_addr.to_string(),
+            funds: vec![],
+            msg: to_binary(&Cw20ExecuteMsg::Send {
+                amount,
+                contract: anchor_info.market_money.to_string(),
+                msg: to_binary(&MoneyMarketCw20HookMsg::RedeemStable {})?,
+            })?,
+        }))
+        .add_attribute("unwind", amount))
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn try_update_config(
     mut deps: DepsMut,
+    env: Env,
     info: MessageInfo,
     owner: Option<String>,
     commission_rate: Option<Decimal256>,
     code_id: Option<u64>,
     router_addr: Option<String>,
+    guardian: Option<Option<String>>,
+    config_delay: Option<u64>,
 ) -> Result<Response<TerraMsgWrapper>, PlanetContractError> {
-    let config: Config = get_config(deps.as_ref()).unwrap();
+    let config: Config = load_config(deps.as_ref())?;
     let mut attrs: Vec<Attribute> = vec![];
 
     // permission check
@@ -177,43 +242,206 @@ pub fn try_update_config(
     if let Some(router_addr) = router_addr {
         attrs.push(Attribute::new("action", "router_addr"));
 
-        let router_addr = deps.api.addr_validate(&router_addr).unwrap();
-        set_router(deps.branch(), router_addr.clone()).unwrap();
-        attrs.push(Attribute::new("router_addr", router_addr));
+        // the router receives the vault assets on every swap, so it waits like a new owner
+        let router_addr = deps.api.addr_validate(&router_addr)?;
+        if config.config_delay == 0 {
+            set_router(deps.branch(), router_addr.clone())?;
+            attrs.push(Attribute::new("router_addr", router_addr));
+        } else {
+            let release_at = env.block.time.seconds() + config.config_delay;
+            set_pending_router(
+                deps.branch(),
+                PendingRouter {
+                    router: router_addr.clone(),
+                    release_at,
+                },
+            )?;
+            attrs.push(Attribute::new("pending_router_addr", router_addr));
+            attrs.push(Attribute::new(
+                "pending_router_release_at",
+                release_at.to_string(),
+            ));
+        }
     }
 
-    match try_planet_update_config(deps, info, owner, commission_rate, code_id) {
+    match try_planet_update_config(
+        deps,
+        env,
+        info,
+        owner,
+        commission_rate,
+        code_id,
+        None,
+        None,
+        None,
+        guardian,
+        None,
+        config_delay,
+    ) {
         Ok(res) => Ok(res.add_attributes(attrs)),
         Err(e) => Err(e),
     }
 }
 
+/// Sets the scheduled router once its delay has passed, together with the scheduled planet config
+pub fn try_apply_config(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+) -> Result<Response<TerraMsgWrapper>, PlanetContractError> {
+    let pending_router = match get_pending_router(deps.as_ref())? {
+        Some(pending_router) => pending_router,
+        None => return planet_apply_config(deps, env, info),
+    };
+    let config: Config = load_config(deps.as_ref())?;
+
+    // permission check
+    if info.sender != config.owner {
+        return Err(PlanetContractError::Unauthorized {});
+    }
+
+    if env.block.time.seconds() < pending_router.release_at {
+        return Err(PlanetContractError::ConfigTimelocked {
+            release_at: pending_router.release_at,
+        });
+    }
+
+    set_router(deps.branch(), pending_router.router.clone())?;
+    remove_pending_router(deps.branch());
+
+    let res = if get_pending_config(deps.as_ref())?.is_some() {
+        planet_apply_config(deps, env, info)?
+    } else {
+        Response::new().add_attribute("action", Action::ApplyConfig.to_string())
+    };
+
+    Ok(res.add_attribute("router_addr", pending_router.router))
+}
+
+/// Drops the scheduled router together with the scheduled planet config
+pub fn try_cancel_config(
+    mut deps: DepsMut,
+    info: MessageInfo,
+) -> Result<Response<TerraMsgWrapper>, PlanetContractError> {
+    if get_pending_router(deps.as_ref())?.is_none() {
+        return planet_cancel_config(deps, info);
+    }
+    let config: Config = load_config(deps.as_ref())?;
+
+    // permission check
+    if info.sender != config.owner {
+        return Err(PlanetContractError::Unauthorized {});
+    }
+
+    remove_pending_router(deps.branch());
+
+    if get_pending_config(deps.as_ref())?.is_some() {
+        planet_cancel_config(deps, info)
+    } else {
+        Ok(Response::new().add_attribute("action", Action::CancelConfig.to_string()))
+    }
+}
+
+/// Deposits the bonded UST into Anchor, the shares are minted for the aUST it returns
 pub fn try_bond(
     deps: DepsMut,
     env: Env,
-    info: MessageInfo,
+    sender: Addr,
+    recipient: Addr,
     asset: Asset,
+    min_shares_out: Option<Uint128>,
 ) -> Result<Response<TerraMsgWrapper>, PlanetContractError> {
-    let anchor_info = get_anchor_info(deps.as_ref()).unwrap();
-    let coin = asset.deduct_tax(&deps.querier).unwrap();
+    let deposit_asset_info = get_deposit_asset_info(deps.as_ref())?;
+    if asset.info != deposit_asset_info {
+        return Err(PlanetContractError::Std(StdError::generic_err(format!(
+            "{} is not the bond asset",
+            asset.info
+        ))));
+    }
+
+    let anchor_info = get_anchor_info(deps.as_ref())?;
+    let coin = asset.deduct_tax(&deps.querier)?;
 
     let aust_addr = Addr::unchecked(anchor_info.aust.to_string());
 
     let current_amount =
-        query_token_balance(&deps.querier, aust_addr, env.contract.address).unwrap();
-
-    set_tmp_bonder(deps, info.sender, current_amount).unwrap();
+        query_token_balance(&deps.querier, aust_addr.clone(), env.contract.address)
+            .map_err(PlanetContractError::query_failed(&aust_addr))?;
+
+    set_tmp_bonder(
+        deps,
+        sender,
+        recipient,
+        current_amount,
+        asset.amount,
+        min_shares_out,
+    )?;
 
     Ok(Response::new().add_submessage(SubMsg::reply_on_success(
         CosmosMsg::Wasm(WasmMsg::Execute {
             contract_addr: anchor_info.market_money.to_string(),
             funds: vec![coin],
-            msg: to_binary(&MoneyMarketExecuteMsg::DepositStable {}).unwrap(),
+            msg: to_binary(&MoneyMarketExecuteMsg::DepositStable {})?,
         }),
         MSG_REPLY_BOND,
     )))
 }
 
+/// Redeems the aUST of the burnt shares, the UST is paid out from the reply
+#[allow(clippy::too_many_arguments)]
+pub fn try_unbond(
+    mut deps: DepsMut,
+    env: Env,
+    vaults_contract: Addr,
+    sender: Addr,
+    recipient: Addr,
+    shares: Uint128,
+    min_assets_out: Option<Uint128>,
+) -> Result<Response<TerraMsgWrapper>, PlanetContractError> {
+    let unbond_amount = compute_redeem_amount(deps.as_ref(), vaults_contract.clone(), shares)?;
+
+    let anchor_info = get_anchor_info(deps.as_ref())?;
+
+    sub_vaults(deps.branch(), Decimal256::from_uint256(unbond_amount))?;
+    add_staker_withdrawal(deps.branch(), &sender, to_uint128(unbond_amount)?, shares)?;
+    add_unbonded(deps.branch(), to_uint128(unbond_amount)?)?;
+
+    let deposit_asset_info = get_deposit_asset_info(deps.as_ref())?;
+    let balance = query_balance(
+        &deps.querier,
+        env.contract.address,
+        deposit_asset_info.to_string(),
+    )
+    .map_err(PlanetContractError::query_failed(&deposit_asset_info))?;
+    set_tmp_bonder(
+        deps.branch(),
+        sender,
+        recipient,
+        balance,
+        shares,
+        min_assets_out,
+    )?;
+
+    Ok(Response::new()
+        .add_message(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: vaults_contract.to_string(),
+            funds: vec![],
+            msg: to_binary(&Cw20ExecuteMsg::Burn { amount: shares })?,
+        }))
+        .add_submessage(SubMsg::reply_on_success(
+            CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: anchor_info.aust.to_string(),
+                funds: vec![],
+                msg: to_binary(&Cw20ExecuteMsg::Send {
+                    amount: to_uint128(unbond_amount)?,
+                    contract: anchor_info.market_money.to_string(),
+                    msg: to_binary(&MoneyMarketCw20HookMsg::RedeemStable {})?,
+                })?,
+            }),
+            MSG_REPLY_UNBOND,
+        )))
+}
+
 const NATIVESWAP: &str = "native_swap";
 const TERRASWAP: &str = "terra_swap";
 const ASTROPORT: &str = "astroport";
@@ -225,16 +453,16 @@ pub fn try_swap(
     path: String,
     amount: Uint128,
 ) -> Result<Response<TerraMsgWrapper>, PlanetContractError> {
-    set_tmp_swap(deps.branch(), path, amount).unwrap();
+    set_tmp_swap(deps.branch(), path, amount)?;
 
-    let anchor_info = get_anchor_info(deps.as_ref()).unwrap();
+    let anchor_info = get_anchor_info(deps.as_ref())?;
     let epoch_state = query_epoch_state(
         deps.as_ref(),
         anchor_info.market_money.clone(),
         env.block.height,
         None,
     )
-    .unwrap();
+    .map_err(PlanetContractError::query_failed(&anchor_info.market_money))?;
 
     let aust = Uint256::from(amount) / epoch_state.exchange_rate;
 
@@ -243,11 +471,10 @@ pub fn try_swap(
             contract_addr: anchor_info.aust.to_string(),
             funds: vec![],
             msg: to_binary(&Cw20ExecuteMsg::Send {
-                amount: aust.into(),
+                amount: to_uint128(aust)?,
                 contract: anchor_info.market_money.to_string(),
-                msg: to_binary(&MoneyMarketCw20HookMsg::RedeemStable {}).unwrap(),
-            })
-            .unwrap(),
+                msg: to_binary(&MoneyMarketCw20HookMsg::RedeemStable {})?,
+            })?,
         }),
         id: MSG_REPLY_PREPARE_SWAP,
         gas_limit: None,
@@ -259,12 +486,15 @@ fn generate_route_msg(
     deps: DepsMut,
     route_path: String,
     amount: Uint128,
-) -> CosmosMsg<TerraMsgWrapper> {
+) -> Result<CosmosMsg<TerraMsgWrapper>, PlanetContractError> {
     let dex = route_path.split("_to_").collect::<Vec<&str>>();
+    if dex.len() != 2 {
+        return Err(PlanetContractError::FailedToParse(route_path));
+    }
 
-    let router_contract = get_router(deps.as_ref()).unwrap();
+    let router_contract = get_router(deps.as_ref())?;
 
-    let asset_info: AssetInfo = get_deposit_asset_info(deps.as_ref()).unwrap();
+    let asset_info: AssetInfo = get_deposit_asset_info(deps.as_ref())?;
 
     let mut funds: Vec<Coin> = vec![];
 
@@ -278,7 +508,7 @@ fn generate_route_msg(
             denom: format!("{}", asset_info),
             amount: asset.amount,
         },
-        _ => asset.deduct_tax(&deps.querier).unwrap(),
+        _ => asset.deduct_tax(&deps.querier)?,
     };
     funds.push(coin);
 
@@ -289,16 +519,14 @@ fn generate_route_msg(
             AssetInfo::NativeToken {
                 denom: "uluna".to_string(),
             },
-        )
-        .unwrap(),
+        )?,
         get_swap_oprations_path(
             dex[1],
             AssetInfo::NativeToken {
                 denom: "uluna".to_string(),
             },
             asset_info,
-        )
-        .unwrap(),
+        )?,
     ];
 
     let execute_msg = TerraswapRouterExecute::ExecuteSwapOperations {
@@ -307,11 +535,11 @@ fn generate_route_msg(
         to: None,
     };
 
-    CosmosMsg::Wasm(WasmMsg::Execute {
+    Ok(CosmosMsg::Wasm(WasmMsg::Execute {
         contract_addr: router_contract.to_string(),
         funds,
-        msg: to_binary(&execute_msg).unwrap(),
-    })
+        msg: to_binary(&execute_msg)?,
+    }))
 }
 
 fn get_swap_oprations_path(
@@ -343,29 +571,53 @@ fn get_swap_oprations_path(
 }
 
 pub fn try_claim(
-    deps: DepsMut,
+    mut deps: DepsMut,
+    env: Env,
     info: MessageInfo,
 ) -> Result<Response<TerraMsgWrapper>, PlanetContractError> {
-    let config = get_config(deps.as_ref()).unwrap();
+    let config = load_config(deps.as_ref())?;
     if config.owner != info.sender {
         return Err(PlanetContractError::Unauthorized {});
     }
 
-    let anchor_info = get_anchor_info(deps.as_ref()).unwrap();
+    let anchor_info = get_anchor_info(deps.as_ref())?;
+
+    let dec_amount = sub_all_commission(deps.branch())?;
+    let amount = to_uint128(Uint256::one() * dec_amount)?;
+    add_commission_claimed(deps.branch(), amount)?;
+
+    // the aUST of the commission leaves the vaults with the redeem
+    sub_vaults(
+        deps.branch(),
+        Decimal256::from_uint256(Uint256::from(amount)),
+    )?;
 
-    let dec_amount = sub_all_commission(deps).unwrap();
-    let amount = Uint256::one() * dec_amount;
+    // only the redeemed commission is paid, the planet may hold other UST after a shutdown
+    let deposit_asset_info = get_deposit_asset_info(deps.as_ref())?;
+    let balance = query_balance(
+        &deps.querier,
+        env.contract.address,
+        deposit_asset_info.to_string(),
+    )
+    .map_err(PlanetContractError::query_failed(&deposit_asset_info))?;
+    set_tmp_bonder(
+        deps,
+        config.owner.clone(),
+        config.owner,
+        balance,
+        Uint128::zero(),
+        None,
+    )?;
 
     Ok(Response::new().add_submessage(SubMsg::reply_on_success(
         CosmosMsg::Wasm(WasmMsg::Execute {
             contract_addr: anchor_info.aust.to_string(),
             funds: vec![],
             msg: to_binary(&Cw20ExecuteMsg::Send {
-                amount: amount.into(),
+                amount,
                 contract: anchor_info.market_money.to_string(),
-                msg: to_binary(&MoneyMarketCw20HookMsg::RedeemStable {}).unwrap(),
-            })
-            .unwrap(),
+                msg: to_binary(&MoneyMarketCw20HookMsg::RedeemStable {})?,
+            })?,
         }),
         MSG_REPLY_CLAIM,
     )))
@@ -373,24 +625,18 @@ pub fn try_claim(
 
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
-    match msg {
-        QueryMsg::Config {} => to_binary(&query_config(deps)),
-        QueryMsg::StakerInfo { staker_addr } => {
-            to_binary(&query_stake_info(deps, env, staker_addr))
-        }
-        QueryMsg::Commission {} => to_binary(&query_commission(deps, env)),
-        _ => planet_query(deps, env, msg),
-    }
+    query_with(&SwapArbitrage, deps, env, msg)
 }
 
-pub fn query_config(deps: Deps) -> ConfigResponse {
-    let config: PlanetConfigResponse = query_planet_config(deps);
+pub fn query_config(deps: Deps) -> StdResult<ConfigResponse> {
+    let config: PlanetConfigResponse = query_planet_config(deps)?;
 
-    let router = get_router(deps).unwrap();
-    let anchor_info = get_anchor_info(deps).unwrap();
-    let deposit_asset_info = get_deposit_asset_info(deps).unwrap();
+    let router = get_router(deps)?;
+    let pending_router = get_pending_router(deps)?;
+    let anchor_info = get_anchor_info(deps)?;
+    let deposit_asset_info = get_deposit_asset_info(deps)?;
 
-    ConfigResponse {
+    Ok(ConfigResponse {
         owner: config.owner,
         commission_rate: config.commission_rate,
         asset_info: config.asset_info,
@@ -399,62 +645,216 @@ pub fn query_config(deps: Deps) -> ConfigResponse {
         router_addr: router.to_string(),
         deposit_asset_info,
         money_market_addr: anchor_info.market_money.to_string(),
-    }
+        executors: config.executors,
+        config_delay: config.config_delay,
+        pending_router_addr: pending_router
+            .as_ref()
+            .map(|pending_router| pending_router.router.to_string()),
+        pending_router_release_at: pending_router.map(|pending_router| pending_router.release_at),
+    })
+}
+
+/// aUST to UST rate of the Anchor market
+fn query_exchange_rate(deps: Deps, env: &Env) -> StdResult<Decimal256> {
+    let anchor_info = get_anchor_info(deps)?;
+
+    let epoch_state = query_epoch_state(
+        deps,
+        anchor_info.market_money.clone(),
+        env.block.height,
+        None,
+    )
+    .map_err(PlanetContractError::query_failed(&anchor_info.market_money))?;
+
+    Ok(epoch_state.exchange_rate)
 }
 
-pub fn query_stake_info(deps: Deps, env: Env, staker_addr: String) -> StakerInfoResponse {
-    let res = planet_query_stake_info(deps, staker_addr);
-    let deposit_asset_info = get_deposit_asset_info(deps).unwrap();
+/// Valued in the deposit asset against the basis booked in it, so the pnl includes the Anchor
+/// yield
+pub fn query_stake_info(
+    deps: Deps,
+    env: Env,
+    staker_addr: String,
+) -> StdResult<StakerInfoResponse> {
+    let config = load_config(deps)?;
+    let staker = Addr::unchecked(staker_addr);
+    let vaults_token_address = vaults_token(&config)?;
+
+    let shares = query_token_balance(&deps.querier, vaults_token_address.clone(), staker.clone())
+        .unwrap_or_else(|_| Uint128::zero());
+    let aust = compute_redeem_amount(deps, vaults_token_address, shares)?;
+
+    staker_info(
+        Asset {
+            info: get_deposit_asset_info(deps)?,
+            amount: to_uint128(aust * query_exchange_rate(deps, &env)?)?,
+        },
+        shares,
+        &get_deposit_basis(deps, &staker)?,
+    )
+}
 
-    let anchor_info = get_anchor_info(deps).unwrap();
+pub fn query_commission(deps: Deps, env: Env) -> StdResult<CommissionResponse> {
+    let deposit_asset_info = get_deposit_asset_info(deps)?;
 
-    let epoch_state =
-        query_epoch_state(deps, anchor_info.market_money, env.block.height, None).unwrap();
+    let exchange_rate = query_exchange_rate(deps, &env)?;
+    let commission = get_commission(deps)?;
 
-    let balance = Uint256::from(res.asset.amount) * epoch_state.exchange_rate;
+    let balance = commission * exchange_rate;
 
-    StakerInfoResponse {
+    Ok(CommissionResponse {
         asset: Asset {
             info: deposit_asset_info,
-            amount: balance.into(),
+            amount: to_uint128(Uint256::one() * balance)?,
         },
+    })
+}
+
+/// The bond pays tax when it is deposited into Anchor, and the vaults hold the minted aUST
+pub fn query_preview_bond(deps: Deps, env: Env, asset: Asset) -> StdResult<PreviewBondResponse> {
+    let deposit_asset_info = get_deposit_asset_info(deps)?;
+    if asset.info != deposit_asset_info {
+        return Err(StdError::generic_err(format!(
+            "{} is not the bond asset",
+            asset.info
+        )));
+    }
+
+    let tax = asset.compute_tax(&deps.querier)?;
+    let aust = Uint256::from(asset.amount.checked_sub(tax)?) / query_exchange_rate(deps, &env)?;
+
+    let config = load_config(deps)?;
+    let shares = compute_mint_amount(deps, vaults_token(&config)?, to_uint128(aust)?)?;
+
+    Ok(PreviewBondResponse {
+        shares: to_uint128(shares)?,
+        tax,
+    })
+}
+
+/// Anchor deducts tax when it redeems the aUST, and the planet again when it pays the bonder
+pub fn query_preview_unbond(
+    deps: Deps,
+    env: Env,
+    shares: Uint128,
+) -> StdResult<PreviewUnbondResponse> {
+    let deposit_asset_info = get_deposit_asset_info(deps)?;
+    let config = load_config(deps)?;
+
+    let aust = compute_redeem_amount(deps, vaults_token(&config)?, shares)?;
+    let redeemed = to_uint128(aust * query_exchange_rate(deps, &env)?)?;
+
+    let redeem_tax = Asset {
+        info: deposit_asset_info.clone(),
+        amount: redeemed,
     }
+    .compute_tax(&deps.querier)?;
+    let received = redeemed.checked_sub(redeem_tax)?;
+
+    let payout_tax = Asset {
+        info: deposit_asset_info.clone(),
+        amount: received,
+    }
+    .compute_tax(&deps.querier)?;
+
+    Ok(PreviewUnbondResponse {
+        asset: Asset {
+            info: deposit_asset_info,
+            amount: received.checked_sub(payout_tax)?,
+        },
+        tax: redeem_tax + payout_tax,
+    })
 }
 
-fn query_commission(deps: Deps, env: Env) -> CommissionResponse {
-    let deposit_asset_info = get_deposit_asset_info(deps).unwrap();
+pub fn query_convert_to_shares(
+    deps: Deps,
+    env: Env,
+    amount: Uint128,
+) -> StdResult<ConvertToSharesResponse> {
+    let config = load_config(deps)?;
 
-    let anchor_info = get_anchor_info(deps).unwrap();
+    let aust = Uint256::from(amount) / query_exchange_rate(deps, &env)?;
+    let shares = compute_mint_amount(deps, vaults_token(&config)?, to_uint128(aust)?)?;
 
-    let epoch_state =
-        query_epoch_state(deps, anchor_info.market_money, env.block.height, None).unwrap();
-    let commission = get_commission(deps).unwrap();
+    Ok(ConvertToSharesResponse {
+        shares: to_uint128(shares)?,
+    })
+}
+
+pub fn query_convert_to_assets(
+    deps: Deps,
+    env: Env,
+    shares: Uint128,
+) -> StdResult<ConvertToAssetsResponse> {
+    let deposit_asset_info = get_deposit_asset_info(deps)?;
+    let config = load_config(deps)?;
 
-    let balance = commission * epoch_state.exchange_rate;
+    let aust = compute_redeem_amount(deps, vaults_token(&config)?, shares)?;
 
-    CommissionResponse {
+    Ok(ConvertToAssetsResponse {
         asset: Asset {
             info: deposit_asset_info,
-            amount: (Uint256::one() * balance).into(),
+            amount: to_uint128(aust * query_exchange_rate(deps, &env)?)?,
         },
-    }
+    })
+}
+
+pub fn query_total_assets(deps: Deps, env: Env) -> StdResult<TotalAssetsResponse> {
+    let deposit_asset_info = get_deposit_asset_info(deps)?;
+
+    let total_assets = compute_total_assets(deps)? * query_exchange_rate(deps, &env)?;
+
+    Ok(TotalAssetsResponse {
+        asset: Asset {
+            info: deposit_asset_info,
+            amount: to_uint128(Uint256::one() * total_assets)?,
+        },
+    })
+}
+
+/// The planet caps are counted in aUST
+pub fn query_max_bond(
+    deps: Deps,
+    env: Env,
+    address: String,
+    proof: Option<Vec<String>>,
+) -> StdResult<MaxBondResponse> {
+    let res = planet_query_max_bond(deps, address, proof)?;
+
+    Ok(MaxBondResponse {
+        amount: match res.amount {
+            Some(amount) => Some(to_uint128(
+                Uint256::from(amount) * query_exchange_rate(deps, &env)?,
+            )?),
+            None => None,
+        },
+    })
 }
 
 const LIMIT_MINIMUM: Uint128 = Uint128::new(10_000_000_000u128);
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn reply(
+    deps: DepsMut,
+    env: Env,
+    reply: Reply,
+) -> Result<Response<TerraMsgWrapper>, PlanetContractError> {
+    reply_with(&SwapArbitrage, deps, env, reply)
+}
+
+/// Replies of the Anchor deposits and redeems and of the swap route
+pub fn strategy_reply(
     mut deps: DepsMut,
     env: Env,
     reply: Reply,
 ) -> Result<Response<TerraMsgWrapper>, PlanetContractError> {
     match reply.id {
         MSG_REPLY_PREPARE_SWAP => {
-            let tmp_swap = get_tmp_swap(deps.as_ref()).unwrap();
+            let tmp_swap = get_tmp_swap(deps.as_ref())?;
 
-            let deposit_asset_info = get_deposit_asset_info(deps.as_ref()).unwrap();
+            let deposit_asset_info = get_deposit_asset_info(deps.as_ref())?;
             let amount = deposit_asset_info
                 .query_pool(&deps.querier, deps.api, env.contract.address)
-                .unwrap();
+                .map_err(PlanetContractError::query_failed(&deposit_asset_info))?;
 
             // discarding
             if amount + Uint128::from(10u64) < tmp_swap.minimum_receive {
@@ -464,9 +864,9 @@ pub fn reply(
                 ))));
             }
 
-            set_tmp_swap(deps.branch(), tmp_swap.route_path.clone(), amount).unwrap();
+            set_tmp_swap(deps.branch(), tmp_swap.route_path.clone(), amount)?;
 
-            let msg = generate_route_msg(deps, tmp_swap.route_path, amount);
+            let msg = generate_route_msg(deps, tmp_swap.route_path, amount)?;
 
             Ok(Response::new().add_submessage(SubMsg {
                 msg,
@@ -476,31 +876,31 @@ pub fn reply(
             }))
         }
         MSG_REPLY_SWAP => {
-            let tmp_swap = get_tmp_swap(deps.as_ref()).unwrap();
+            let tmp_swap = get_tmp_swap(deps.as_ref())?;
 
             match reply.result {
                 ContractResult::Ok(_) => {
                     remove_tmp_swap(deps.branch());
 
-                    let deposit_asset_info = get_deposit_asset_info(deps.as_ref()).unwrap();
+                    let deposit_asset_info = get_deposit_asset_info(deps.as_ref())?;
                     let coin = deps
                         .querier
                         .query_balance(env.contract.address, deposit_asset_info.to_string())
-                        .unwrap();
+                        .map_err(PlanetContractError::query_failed(&deposit_asset_info))?;
 
                     let asset = Asset {
                         info: deposit_asset_info,
                         amount: coin.amount,
                     };
-                    let coin = asset.deduct_tax(&deps.querier).unwrap();
+                    let coin = asset.deduct_tax(&deps.querier)?;
 
-                    let anchor_info = get_anchor_info(deps.as_ref()).unwrap();
+                    let anchor_info = get_anchor_info(deps.as_ref())?;
 
                     Ok(Response::new().add_submessage(SubMsg::reply_on_success(
                         CosmosMsg::Wasm(WasmMsg::Execute {
                             contract_addr: anchor_info.market_money.to_string(),
                             funds: vec![coin],
-                            msg: to_binary(&MoneyMarketExecuteMsg::DepositStable {}).unwrap(),
+                            msg: to_binary(&MoneyMarketExecuteMsg::DepositStable {})?,
                         }),
                         planet::contract::MSG_REPLY_ID_EXECUTE,
                     )))
@@ -509,7 +909,7 @@ pub fn reply(
                     let minimum_receive = tmp_swap
                         .minimum_receive
                         .checked_div(Uint128::from(2u128))
-                        .unwrap();
+                        .map_err(StdError::divide_by_zero)?;
                     if tmp_swap.minimum_receive < LIMIT_MINIMUM {
                         remove_tmp_swap(deps.branch());
 
@@ -517,9 +917,8 @@ pub fn reply(
                             "limit minimum received",
                         )));
                     }
-                    set_tmp_swap(deps.branch(), tmp_swap.route_path.clone(), minimum_receive)
-                        .unwrap();
-                    let msg = generate_route_msg(deps, tmp_swap.route_path, minimum_receive);
+                    set_tmp_swap(deps.branch(), tmp_swap.route_path.clone(), minimum_receive)?;
+                    let msg = generate_route_msg(deps, tmp_swap.route_path, minimum_receive)?;
                     Ok(Response::new().add_submessage(SubMsg {
                         msg,
                         id: MSG_REPLY_SWAP,
@@ -530,90 +929,952 @@ pub fn reply(
             }
         }
         MSG_REPLY_BOND => {
-            let tmp_bonder = get_tmp_bonder(deps.as_ref()).unwrap();
-            let anchor_info = get_anchor_info(deps.as_ref()).unwrap();
-            let config = get_config(deps.as_ref()).unwrap();
+            let tmp_bonder = get_tmp_bonder(deps.as_ref())?;
+            let anchor_info = get_anchor_info(deps.as_ref())?;
+            let config = load_config(deps.as_ref())?;
 
             let current_balance = config
                 .asset_info
                 .query_pool(&deps.querier, deps.api, env.contract.address)
-                .unwrap();
+                .map_err(PlanetContractError::query_failed(&config.asset_info))?;
+            let amount = current_balance
+                .checked_sub(tmp_bonder.prev_amount)
+                .map_err(|err| PlanetContractError::Overflow(err.to_string()))?;
+
+            // the basis is booked at what was paid in, not at the aUST it bought
+            let shares = compute_mint_amount(deps.as_ref(), vaults_token(&config)?, amount)?;
+            add_deposited(
+                deps.branch(),
+                &tmp_bonder.recipient,
+                tmp_bonder.amount,
+                to_uint128(shares)?,
+            )?;
 
             planet_bond(
                 deps,
                 tmp_bonder.bonder,
+                tmp_bonder.recipient,
                 Asset {
                     info: anchor_info.aust,
-                    amount: current_balance - tmp_bonder.prev_amount,
+                    amount,
                 },
+                tmp_bonder.min_out,
             )
         }
         MSG_REPLY_MIGRATE => {
-            let anchor_info = get_anchor_info(deps.as_ref()).unwrap();
+            let anchor_info = get_anchor_info(deps.as_ref())?;
 
             let aust_addr = Addr::unchecked(anchor_info.aust.to_string());
             let balance =
-                query_token_balance(&deps.querier, aust_addr, env.contract.address).unwrap();
+                query_token_balance(&deps.querier, aust_addr.clone(), env.contract.address)
+                    .map_err(PlanetContractError::query_failed(&aust_addr))?;
             set_vaults(
                 deps.branch(),
                 Decimal256::from_uint256(Uint256::from(balance)),
-            )
-            .unwrap();
+            )?;
             Ok(Response::new().add_attribute("migrate", "success"))
         }
         MSG_REPLY_UNBOND => {
-            let tmp_bonder = get_tmp_bonder(deps.as_ref()).unwrap();
+            let tmp_bonder = get_tmp_bonder(deps.as_ref())?;
 
-            let deposit_asset_info = get_deposit_asset_info(deps.as_ref()).unwrap();
-            let mut balance = query_balance(
+            let deposit_asset_info = get_deposit_asset_info(deps.as_ref())?;
+            let balance = query_balance(
                 &deps.querier,
                 env.contract.address,
                 deposit_asset_info.to_string(),
             )
-            .unwrap();
-
-            balance = balance.checked_sub(tmp_bonder.prev_amount).unwrap();
+            .map_err(PlanetContractError::query_failed(&deposit_asset_info))?
+            .checked_sub(tmp_bonder.prev_amount)
+            .map_err(|err| PlanetContractError::Overflow(err.to_string()))?;
             let asset = Asset {
                 amount: balance,
                 info: deposit_asset_info,
             };
+            add_withdrawn(
+                deps.branch(),
+                &tmp_bonder.bonder,
+                balance,
+                tmp_bonder.amount,
+            )?;
 
-            Ok(Response::new().add_message(CosmosMsg::Bank(BankMsg::Send {
-                to_address: tmp_bonder.bonder.to_string(),
-                amount: vec![asset.deduct_tax(&deps.querier)?],
-            })))
+            // the redeemed amount is only known now, checked after the payout tax
+            let tax = asset.compute_tax(&deps.querier)?;
+            assert_min_out(
+                tmp_bonder.min_out,
+                balance.checked_sub(tax).map_err(StdError::from)?,
+            )?;
+
+            Ok(Response::new().add_message(asset.into_msg(&deps.querier, tmp_bonder.recipient)?))
         }
         MSG_REPLY_CLAIM => {
-            let config = get_config(deps.as_ref()).unwrap();
+            let tmp_bonder = get_tmp_bonder(deps.as_ref())?;
 
-            let deposit_asset_info = get_deposit_asset_info(deps.as_ref()).unwrap();
+            let deposit_asset_info = get_deposit_asset_info(deps.as_ref())?;
             let balance = query_balance(
                 &deps.querier,
                 env.contract.address,
                 deposit_asset_info.to_string(),
             )
-            .unwrap();
+            .map_err(PlanetContractError::query_failed(&deposit_asset_info))?
+            .checked_sub(tmp_bonder.prev_amount)
+            .map_err(|err| PlanetContractError::Overflow(err.to_string()))?;
 
             let asset = Asset {
                 info: deposit_asset_info,
                 amount: balance,
             };
 
-            Ok(Response::new().add_message(CosmosMsg::Bank(BankMsg::Send {
-                to_address: config.owner.to_string(),
-                amount: vec![asset.deduct_tax(&deps.querier)?],
-            })))
+            Ok(Response::new().add_message(asset.into_msg(&deps.querier, tmp_bonder.recipient)?))
+        }
+        _ => Err(PlanetContractError::InvalidReplyId {}),
+    }
+}
+
+#[cfg(test)]
+mod testing {
+    use super::*;
+    use crate::mock_querier::WasmMockQuerier;
+    use crate::state::set_anchor_info;
+    use cosmwasm_std::testing::{mock_env, mock_info, MockApi, MockStorage, MOCK_CONTRACT_ADDR};
+    use cosmwasm_std::{coins, OwnedDeps, SubMsgExecutionResponse};
+    use planet::contract::MSG_REPLY_ID_TOKEN_INSTANT;
+    use planet::response::MsgInstantiateContractResponse;
+    use protobuf::Message;
+
+    pub static OWNER: &str = "owner0000";
+    pub static VAULTS_TOKEN: &str = "vaults0000";
+    pub static AUST: &str = "aust0000";
+    pub static MARKET: &str = "market0000";
+
+    /// UST planet over Anchor aUST with a 10% commission, override the fields a test needs
+    pub fn mock_instantiate_msg() -> InstantiateMsg {
+        InstantiateMsg {
+            commission_rate: Decimal256::percent(10),
+            deposit_asset_info: AssetInfo::NativeToken {
+                denom: "uusd".to_string(),
+            },
+            asset_info: AssetInfo::Token {
+                contract_addr: AUST.to_string(),
+            },
+            symbol: "TTN".to_string(),
+            token_code_id: 123u64,
+            router_addr: "router0000".to_string(),
+            money_market_addr: MARKET.to_string(),
+            guardian: None,
+            marketing: None,
+            token_admin: None,
+            vaults_token: None,
+            config_delay: None,
+        }
+    }
+
+    pub fn init(mut deps: DepsMut) {
+        let info = mock_info(OWNER, &[]);
+        instantiate(deps.branch(), mock_env(), info, mock_instantiate_msg()).unwrap();
+
+        let mut res = MsgInstantiateContractResponse::new();
+        res.set_contract_address(VAULTS_TOKEN.to_string());
+
+        let reply_msg = Reply {
+            id: MSG_REPLY_ID_TOKEN_INSTANT,
+            result: ContractResult::Ok(SubMsgExecutionResponse {
+                events: vec![],
+                data: Some(res.write_to_bytes().unwrap().into()),
+            }),
+        };
+        reply(deps.branch(), mock_env(), reply_msg).unwrap();
+
+        set_anchor_info(
+            deps,
+            Addr::unchecked(MARKET),
+            AssetInfo::Token {
+                contract_addr: AUST.to_string(),
+            },
+        )
+        .unwrap();
+    }
+
+    pub static BONDER: &str = "bonder0000";
+
+    pub fn uusd(amount: u128) -> Asset {
+        Asset {
+            info: AssetInfo::NativeToken {
+                denom: "uusd".to_string(),
+            },
+            amount: Uint128::from(amount),
+        }
+    }
+
+    /// Bonds 1000 UST that Anchor turns into 800 aUST
+    pub fn bond(deps: &mut OwnedDeps<MockStorage, MockApi, WasmMockQuerier>) {
+        let msg = ExecuteMsg::Bond {
+            asset: uusd(1000),
+            recipient: None,
+            min_shares_out: None,
+        };
+        let info = mock_info(BONDER, &coins(1000, "uusd"));
+        execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        deps.querier.with_token_balances(&[
+            (
+                &AUST.to_string(),
+                &[(&MOCK_CONTRACT_ADDR.to_string(), &Uint128::from(800u128))],
+            ),
+            (&VAULTS_TOKEN.to_string(), &[]),
+        ]);
+        reply(deps.as_mut(), mock_env(), reply_ok(MSG_REPLY_BOND)).unwrap();
+    }
+
+    pub fn reply_ok(id: u64) -> Reply {
+        Reply {
+            id,
+            result: ContractResult::Ok(SubMsgExecutionResponse {
+                events: vec![],
+                data: None,
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod reply {
+    use super::*;
+    use crate::contract::testing::{
+        bond, init, reply_ok, uusd, AUST, BONDER, MARKET, OWNER, VAULTS_TOKEN,
+    };
+    use crate::mock_querier::mock_dependencies;
+    use cosmwasm_std::testing::{mock_env, mock_info, MOCK_CONTRACT_ADDR};
+    use cosmwasm_std::{attr, coins, BankMsg};
+    use cw20::Cw20ReceiveMsg;
+    use planet::contract::{MSG_REPLY_ID_EXECUTE, MSG_REPLY_ID_EXECUTE_SKIP};
+    use planet::state::get_vaults;
+    use starflet_protocol::planet::Cw20HookMsg;
+
+    use std::str::FromStr;
+
+    #[test]
+    fn bond_and_unbond() {
+        let mut deps = mock_dependencies(&[]);
+        deps.querier
+            .with_exchange_rate(Decimal256::from_str("1.25").unwrap());
+        deps.querier.with_token_balances(&[
+            (
+                &AUST.to_string(),
+                &[(&MOCK_CONTRACT_ADDR.to_string(), &Uint128::zero())],
+            ),
+            (&VAULTS_TOKEN.to_string(), &[]),
+        ]);
+        init(deps.as_mut());
+
+        let msg = ExecuteMsg::Bond {
+            asset: uusd(1000),
+            recipient: None,
+            min_shares_out: None,
+        };
+        let info = mock_info(BONDER, &coins(1000, "uusd"));
+        let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        assert_eq!(
+            res.messages,
+            vec![SubMsg::reply_on_success(
+                CosmosMsg::Wasm(WasmMsg::Execute {
+                    contract_addr: MARKET.to_string(),
+                    funds: coins(1000, "uusd"),
+                    msg: to_binary(&MoneyMarketExecuteMsg::DepositStable {}).unwrap(),
+                }),
+                MSG_REPLY_BOND,
+            )]
+        );
+
+        // the shares are minted for the aUST the deposit returned
+        deps.querier.with_token_balances(&[
+            (
+                &AUST.to_string(),
+                &[(&MOCK_CONTRACT_ADDR.to_string(), &Uint128::from(800u128))],
+            ),
+            (&VAULTS_TOKEN.to_string(), &[]),
+        ]);
+        let res = reply(deps.as_mut(), mock_env(), reply_ok(MSG_REPLY_BOND)).unwrap();
+        assert_eq!(
+            res.messages,
+            vec![SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: VAULTS_TOKEN.to_string(),
+                funds: vec![],
+                msg: to_binary(&Cw20ExecuteMsg::Mint {
+                    recipient: BONDER.to_string(),
+                    amount: Uint128::from(800u128),
+                })
+                .unwrap(),
+            }))]
+        );
+        assert_eq!(
+            get_vaults(deps.as_ref()).unwrap(),
+            Decimal256::from_uint256(800u64)
+        );
+
+        deps.querier.with_token_balances(&[
+            (
+                &AUST.to_string(),
+                &[(&MOCK_CONTRACT_ADDR.to_string(), &Uint128::from(800u128))],
+            ),
+            (
+                &VAULTS_TOKEN.to_string(),
+                &[(&BONDER.to_string(), &Uint128::from(800u128))],
+            ),
+        ]);
+        let res = query_stake_info(deps.as_ref(), mock_env(), BONDER.to_string()).unwrap();
+        assert_eq!(res.asset, uusd(1000));
+        assert_eq!(res.deposited, Uint128::from(1000u128));
+
+        let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+            sender: BONDER.to_string(),
+            amount: Uint128::from(400u128),
+            msg: to_binary(&Cw20HookMsg::Unbond {
+                recipient: None,
+                min_assets_out: None,
+            })
+            .unwrap(),
+        });
+        let res = execute(deps.as_mut(), mock_env(), mock_info(VAULTS_TOKEN, &[]), msg).unwrap();
+        assert_eq!(
+            res.messages,
+            vec![
+                SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+                    contract_addr: VAULTS_TOKEN.to_string(),
+                    funds: vec![],
+                    msg: to_binary(&Cw20ExecuteMsg::Burn {
+                        amount: Uint128::from(400u128),
+                    })
+                    .unwrap(),
+                })),
+                SubMsg::reply_on_success(
+                    CosmosMsg::Wasm(WasmMsg::Execute {
+                        contract_addr: AUST.to_string(),
+                        funds: vec![],
+                        msg: to_binary(&Cw20ExecuteMsg::Send {
+                            amount: Uint128::from(400u128),
+                            contract: MARKET.to_string(),
+                            msg: to_binary(&MoneyMarketCw20HookMsg::RedeemStable {}).unwrap(),
+                        })
+                        .unwrap(),
+                    }),
+                    MSG_REPLY_UNBOND,
+                ),
+            ]
+        );
+        assert_eq!(
+            get_vaults(deps.as_ref()).unwrap(),
+            Decimal256::from_uint256(400u64)
+        );
+
+        // the redeem paid 500 UST, all of it goes to the bonder
+        deps.querier
+            .with_balance(&[(&MOCK_CONTRACT_ADDR.to_string(), coins(500, "uusd"))]);
+        let res = reply(deps.as_mut(), mock_env(), reply_ok(MSG_REPLY_UNBOND)).unwrap();
+        assert_eq!(
+            res.messages,
+            vec![SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+                to_address: BONDER.to_string(),
+                amount: coins(500, "uusd"),
+            }))]
+        );
+        assert_eq!(
+            get_deposit_basis(deps.as_ref(), &Addr::unchecked(BONDER))
+                .unwrap()
+                .withdrawn,
+            Uint128::from(500u128)
+        );
+    }
+
+    #[test]
+    fn strategy_replies() {
+        let mut deps = mock_dependencies(&[]);
+        deps.querier.with_token_balances(&[
+            (
+                &AUST.to_string(),
+                &[(&MOCK_CONTRACT_ADDR.to_string(), &Uint128::zero())],
+            ),
+            (&VAULTS_TOKEN.to_string(), &[]),
+        ]);
+        init(deps.as_mut());
+
+        // ids inside STRATEGY_REPLY_IDS go to the strategy, which knows only its own
+        let res = reply(deps.as_mut(), mock_env(), reply_ok(MSG_REPLY_BOND + 1));
+        assert_eq!(res.unwrap_err(), PlanetContractError::InvalidReplyId {});
+
+        // the migrate reply books the aUST held by the planet
+        deps.querier.with_token_balances(&[(
+            &AUST.to_string(),
+            &[(&MOCK_CONTRACT_ADDR.to_string(), &Uint128::from(300u128))],
+        )]);
+        let res = reply(deps.as_mut(), mock_env(), reply_ok(MSG_REPLY_MIGRATE)).unwrap();
+        assert_eq!(res.attributes, vec![attr("migrate", "success")]);
+        assert_eq!(
+            get_vaults(deps.as_ref()).unwrap(),
+            Decimal256::from_uint256(300u64)
+        );
+
+        // the planet ids stay with the planet
+        let res = reply(
+            deps.as_mut(),
+            mock_env(),
+            reply_ok(MSG_REPLY_ID_EXECUTE_SKIP),
+        )
+        .unwrap();
+        assert_eq!(res.attributes, vec![attr("reply", "execute_skip")]);
+    }
+
+    #[test]
+    fn swap_settles_as_execute() {
+        let mut deps = mock_dependencies(&[]);
+        deps.querier
+            .with_exchange_rate(Decimal256::from_str("1.25").unwrap());
+        deps.querier.with_token_balances(&[
+            (
+                &AUST.to_string(),
+                &[(&MOCK_CONTRACT_ADDR.to_string(), &Uint128::zero())],
+            ),
+            (&VAULTS_TOKEN.to_string(), &[]),
+        ]);
+        init(deps.as_mut());
+
+        bond(&mut deps);
+        deps.querier.with_token_balances(&[
+            (
+                &AUST.to_string(),
+                &[(&MOCK_CONTRACT_ADDR.to_string(), &Uint128::from(800u128))],
+            ),
+            (
+                &VAULTS_TOKEN.to_string(),
+                &[(&BONDER.to_string(), &Uint128::from(800u128))],
+            ),
+        ]);
+
+        let msg = ExecuteMsg::Swap {
+            path: "terra_swap_to_astroport".to_string(),
+            amount: Uint128::from(1000u128),
+        };
+        let res = execute(deps.as_mut(), mock_env(), mock_info(OWNER, &[]), msg).unwrap();
+        assert_eq!(res.messages.len(), 1);
+        assert_eq!(res.messages[0].id, MSG_REPLY_PREPARE_SWAP);
+
+        // the redeemed UST goes through the route
+        deps.querier.with_token_balances(&[
+            (&AUST.to_string(), &[]),
+            (
+                &VAULTS_TOKEN.to_string(),
+                &[(&BONDER.to_string(), &Uint128::from(800u128))],
+            ),
+        ]);
+        deps.querier
+            .with_balance(&[(&MOCK_CONTRACT_ADDR.to_string(), coins(1000, "uusd"))]);
+        let res = reply(deps.as_mut(), mock_env(), reply_ok(MSG_REPLY_PREPARE_SWAP)).unwrap();
+        assert_eq!(res.messages.len(), 1);
+        assert_eq!(res.messages[0].id, MSG_REPLY_SWAP);
+
+        // and back into Anchor, settled by the planet as an execute
+        deps.querier
+            .with_balance(&[(&MOCK_CONTRACT_ADDR.to_string(), coins(1010, "uusd"))]);
+        let res = reply(deps.as_mut(), mock_env(), reply_ok(MSG_REPLY_SWAP)).unwrap();
+        assert_eq!(
+            res.messages,
+            vec![SubMsg::reply_on_success(
+                CosmosMsg::Wasm(WasmMsg::Execute {
+                    contract_addr: MARKET.to_string(),
+                    funds: coins(1010, "uusd"),
+                    msg: to_binary(&MoneyMarketExecuteMsg::DepositStable {}).unwrap(),
+                }),
+                MSG_REPLY_ID_EXECUTE,
+            )]
+        );
+
+        deps.querier
+            .with_balance(&[(&MOCK_CONTRACT_ADDR.to_string(), vec![])]);
+        deps.querier.with_token_balances(&[
+            (
+                &AUST.to_string(),
+                &[(&MOCK_CONTRACT_ADDR.to_string(), &Uint128::from(808u128))],
+            ),
+            (
+                &VAULTS_TOKEN.to_string(),
+                &[(&BONDER.to_string(), &Uint128::from(800u128))],
+            ),
+        ]);
+        let res = reply(deps.as_mut(), mock_env(), reply_ok(MSG_REPLY_ID_EXECUTE)).unwrap();
+        assert_eq!(
+            res.attributes,
+            vec![
+                attr("reply", "execute"),
+                attr("result", "success"),
+                attr("revenue", "8"),
+                attr("add_commission", "0.8"),
+                attr("execution_id", "1"),
+            ]
+        );
+        assert_eq!(
+            get_vaults(deps.as_ref()).unwrap(),
+            Decimal256::from_uint256(808u64)
+        );
+    }
+}
+
+#[cfg(test)]
+mod shutdown {
+    use super::*;
+    use crate::contract::testing::{bond, init, AUST, BONDER, MARKET, OWNER, VAULTS_TOKEN};
+    use crate::mock_querier::mock_dependencies;
+    use cosmwasm_std::testing::{mock_env, mock_info, MOCK_CONTRACT_ADDR};
+    use cosmwasm_std::{attr, coins, BankMsg};
+    use cw20::Cw20ReceiveMsg;
+    use planet::state::{add_commission, add_unbonding, add_vaults, Unbonding};
+    use starflet_protocol::planet::Cw20HookMsg;
+
+    static UNBONDER: &str = "unbonder0000";
+
+    #[test]
+    fn unwind_and_emergency_withdraw() {
+        let mut deps = mock_dependencies(&[]);
+        deps.querier.with_token_balances(&[
+            (
+                &AUST.to_string(),
+                &[(&MOCK_CONTRACT_ADDR.to_string(), &Uint128::zero())],
+            ),
+            (&VAULTS_TOKEN.to_string(), &[]),
+        ]);
+        init(deps.as_mut());
+        bond(&mut deps);
+
+        // 20 aUST of commission and 30 aUST waiting for an unbonder
+        add_vaults(deps.as_mut(), Decimal256::from_uint256(20u64)).unwrap();
+        add_commission(deps.as_mut(), Decimal256::from_uint256(20u64)).unwrap();
+        add_unbonding(
+            deps.as_mut(),
+            &Addr::unchecked(UNBONDER),
+            Unbonding {
+                amount: Uint128::from(30u128),
+                release_at: mock_env().block.time.seconds(),
+            },
+        )
+        .unwrap();
+        deps.querier.with_token_balances(&[
+            (
+                &AUST.to_string(),
+                &[(&MOCK_CONTRACT_ADDR.to_string(), &Uint128::from(850u128))],
+            ),
+            (
+                &VAULTS_TOKEN.to_string(),
+                &[(&BONDER.to_string(), &Uint128::from(800u128))],
+            ),
+        ]);
+
+        let msg = ExecuteMsg::Shutdown { unwind: Some(true) };
+        let res = execute(deps.as_mut(), mock_env(), mock_info(OWNER, &[]), msg).unwrap();
+        assert_eq!(
+            res.messages,
+            vec![SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: AUST.to_string(),
+                funds: vec![],
+                msg: to_binary(&Cw20ExecuteMsg::Send {
+                    amount: Uint128::from(800u128),
+                    contract: MARKET.to_string(),
+                    msg: to_binary(&MoneyMarketCw20HookMsg::RedeemStable {}).unwrap(),
+                })
+                .unwrap(),
+            }))]
+        );
+        assert!(res.attributes.contains(&attr("unwind", "800")));
+
+        // the redeem paid 1000 UST, the reserved aUST is left to its owners
+        deps.querier.with_token_balances(&[
+            (
+                &AUST.to_string(),
+                &[(&MOCK_CONTRACT_ADDR.to_string(), &Uint128::from(50u128))],
+            ),
+            (
+                &VAULTS_TOKEN.to_string(),
+                &[(&BONDER.to_string(), &Uint128::from(800u128))],
+            ),
+        ]);
+        deps.querier
+            .with_balance(&[(&MOCK_CONTRACT_ADDR.to_string(), coins(1000, "uusd"))]);
+
+        let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+            sender: BONDER.to_string(),
+            amount: Uint128::from(300u128),
+            msg: to_binary(&Cw20HookMsg::EmergencyWithdraw {}).unwrap(),
+        });
+        let res = execute(deps.as_mut(), mock_env(), mock_info(VAULTS_TOKEN, &[]), msg).unwrap();
+        assert_eq!(
+            res.messages,
+            vec![
+                SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+                    contract_addr: VAULTS_TOKEN.to_string(),
+                    funds: vec![],
+                    msg: to_binary(&Cw20ExecuteMsg::Burn {
+                        amount: Uint128::from(300u128),
+                    })
+                    .unwrap(),
+                })),
+                // 1000 * 300 / 800 = 375
+                SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+                    to_address: BONDER.to_string(),
+                    amount: coins(375, "uusd"),
+                })),
+            ]
+        );
+    }
+}
+
+#[cfg(test)]
+mod slippage {
+    use super::*;
+    use crate::contract::testing::{bond, init, reply_ok, uusd, AUST, BONDER, VAULTS_TOKEN};
+    use crate::mock_querier::mock_dependencies;
+    use cosmwasm_std::testing::{mock_env, mock_info, MOCK_CONTRACT_ADDR};
+    use cosmwasm_std::{coins, Decimal};
+    use cw20::Cw20ReceiveMsg;
+    use starflet_protocol::planet::Cw20HookMsg;
+
+    #[test]
+    fn bond_reply_below_min_shares_out() {
+        let mut deps = mock_dependencies(&[]);
+        deps.querier.with_token_balances(&[
+            (
+                &AUST.to_string(),
+                &[(&MOCK_CONTRACT_ADDR.to_string(), &Uint128::zero())],
+            ),
+            (&VAULTS_TOKEN.to_string(), &[]),
+        ]);
+        init(deps.as_mut());
+
+        let msg = ExecuteMsg::Bond {
+            asset: uusd(1000),
+            recipient: None,
+            min_shares_out: Some(Uint128::from(900u128)),
+        };
+        let info = mock_info(BONDER, &coins(1000, "uusd"));
+        execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        // the deposit only returned 800 aUST
+        deps.querier.with_token_balances(&[
+            (
+                &AUST.to_string(),
+                &[(&MOCK_CONTRACT_ADDR.to_string(), &Uint128::from(800u128))],
+            ),
+            (&VAULTS_TOKEN.to_string(), &[]),
+        ]);
+        let res = reply(deps.as_mut(), mock_env(), reply_ok(MSG_REPLY_BOND));
+        assert_eq!(
+            res.unwrap_err(),
+            PlanetContractError::SlippageExceeded {
+                minimum: Uint128::from(900u128),
+                actual: Uint128::from(800u128),
+            }
+        );
+    }
+
+    #[test]
+    fn unbond_reply_below_min_assets_out() {
+        let mut deps = mock_dependencies(&[]);
+        deps.querier.with_token_balances(&[
+            (
+                &AUST.to_string(),
+                &[(&MOCK_CONTRACT_ADDR.to_string(), &Uint128::zero())],
+            ),
+            (&VAULTS_TOKEN.to_string(), &[]),
+        ]);
+        init(deps.as_mut());
+        bond(&mut deps);
+        deps.querier.with_token_balances(&[
+            (
+                &AUST.to_string(),
+                &[(&MOCK_CONTRACT_ADDR.to_string(), &Uint128::from(800u128))],
+            ),
+            (
+                &VAULTS_TOKEN.to_string(),
+                &[(&BONDER.to_string(), &Uint128::from(800u128))],
+            ),
+        ]);
+
+        let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+            sender: BONDER.to_string(),
+            amount: Uint128::from(400u128),
+            msg: to_binary(&Cw20HookMsg::Unbond {
+                recipient: None,
+                min_assets_out: Some(Uint128::from(490u128)),
+            })
+            .unwrap(),
+        });
+        execute(deps.as_mut(), mock_env(), mock_info(VAULTS_TOKEN, &[]), msg).unwrap();
+
+        // the redeem only paid 480 UST, 475 after the payout tax
+        deps.querier.with_tax(
+            Decimal::percent(1),
+            &[(&"uusd".to_string(), &Uint128::from(1_000_000u128))],
+        );
+        deps.querier
+            .with_balance(&[(&MOCK_CONTRACT_ADDR.to_string(), coins(480, "uusd"))]);
+        let res = reply(deps.as_mut(), mock_env(), reply_ok(MSG_REPLY_UNBOND));
+        assert_eq!(
+            res.unwrap_err(),
+            PlanetContractError::SlippageExceeded {
+                minimum: Uint128::from(490u128),
+                actual: Uint128::from(475u128),
+            }
+        );
+    }
+}
+
+#[cfg(test)]
+mod preview {
+    use super::*;
+    use crate::contract::testing::{bond, init, uusd, AUST, BONDER, VAULTS_TOKEN};
+    use crate::mock_querier::{mock_dependencies, WasmMockQuerier};
+    use cosmwasm_std::testing::{mock_env, MockApi, MockStorage, MOCK_CONTRACT_ADDR};
+    use cosmwasm_std::{from_binary, Decimal, OwnedDeps};
+
+    use std::str::FromStr;
+
+    /// 800 aUST and shares at 1.25 UST per aUST, with a 1% tax
+    fn init_bonded() -> OwnedDeps<MockStorage, MockApi, WasmMockQuerier> {
+        let mut deps = mock_dependencies(&[]);
+        deps.querier.with_token_balances(&[
+            (
+                &AUST.to_string(),
+                &[(&MOCK_CONTRACT_ADDR.to_string(), &Uint128::zero())],
+            ),
+            (&VAULTS_TOKEN.to_string(), &[]),
+        ]);
+        init(deps.as_mut());
+        bond(&mut deps);
+
+        deps.querier
+            .with_exchange_rate(Decimal256::from_str("1.25").unwrap());
+        deps.querier.with_tax(
+            Decimal::percent(1),
+            &[(&"uusd".to_string(), &Uint128::from(1_000_000u128))],
+        );
+        deps.querier.with_token_balances(&[
+            (
+                &AUST.to_string(),
+                &[(&MOCK_CONTRACT_ADDR.to_string(), &Uint128::from(800u128))],
+            ),
+            (
+                &VAULTS_TOKEN.to_string(),
+                &[(&BONDER.to_string(), &Uint128::from(800u128))],
+            ),
+        ]);
+
+        deps
+    }
+
+    #[test]
+    fn preview_bond_and_unbond() {
+        let deps = init_bonded();
+
+        // 1000 - 10 tax buys 990 / 1.25 = 792 aUST
+        let res: PreviewBondResponse = from_binary(
+            &query(
+                deps.as_ref(),
+                mock_env(),
+                QueryMsg::PreviewBond { asset: uusd(1000) },
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(
+            res,
+            PreviewBondResponse {
+                shares: Uint128::from(792u128),
+                tax: Uint128::from(10u128),
+            }
+        );
+
+        // 400 aUST redeem 500 UST, taxed by Anchor and again on the payout
+        let res: PreviewUnbondResponse = from_binary(
+            &query(
+                deps.as_ref(),
+                mock_env(),
+                QueryMsg::PreviewUnbond {
+                    shares: Uint128::from(400u128),
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(
+            res,
+            PreviewUnbondResponse {
+                asset: uusd(490),
+                tax: Uint128::from(10u128),
+            }
+        );
+
+        let res = query_preview_bond(
+            deps.as_ref(),
+            mock_env(),
+            Asset {
+                info: AssetInfo::Token {
+                    contract_addr: AUST.to_string(),
+                },
+                amount: Uint128::from(1000u128),
+            },
+        );
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn conversions() {
+        let deps = init_bonded();
+
+        let res = query_convert_to_shares(deps.as_ref(), mock_env(), Uint128::from(1000u128));
+        assert_eq!(res.unwrap().shares, Uint128::from(800u128));
+
+        let res = query_convert_to_assets(deps.as_ref(), mock_env(), Uint128::from(400u128));
+        assert_eq!(res.unwrap().asset, uusd(500));
+
+        let res = query_total_assets(deps.as_ref(), mock_env());
+        assert_eq!(res.unwrap().asset, uusd(1000));
+    }
+}
+
+#[cfg(test)]
+mod config {
+    use super::*;
+    use crate::contract::testing::{init, AUST, OWNER, VAULTS_TOKEN};
+    use crate::mock_querier::{mock_dependencies, WasmMockQuerier};
+    use cosmwasm_std::testing::{mock_env, mock_info, MockApi, MockStorage, MOCK_CONTRACT_ADDR};
+    use cosmwasm_std::{attr, OwnedDeps};
+    use planet::state::set_config;
+
+    static ROUTER: &str = "router0001";
+
+    fn init_with_delay(config_delay: u64) -> OwnedDeps<MockStorage, MockApi, WasmMockQuerier> {
+        let mut deps = mock_dependencies(&[]);
+        deps.querier.with_token_balances(&[
+            (
+                &AUST.to_string(),
+                &[(&MOCK_CONTRACT_ADDR.to_string(), &Uint128::zero())],
+            ),
+            (&VAULTS_TOKEN.to_string(), &[]),
+        ]);
+        init(deps.as_mut());
+
+        let mut config = load_config(deps.as_ref()).unwrap();
+        config.config_delay = config_delay;
+        set_config(deps.as_mut(), config).unwrap();
+        deps
+    }
+
+    fn update_router() -> ExecuteMsg {
+        ExecuteMsg::UpdateConfig {
+            owner: None,
+            commission_rate: None,
+            code_id: None,
+            router_addr: Some(ROUTER.to_string()),
+            guardian: None,
+            config_delay: None,
         }
-        _ => planet_reply(deps, env, reply),
+    }
+
+    #[test]
+    fn timelocked_router() {
+        let mut deps = init_with_delay(100);
+
+        let env = mock_env();
+        let info = mock_info(OWNER, &[]);
+        let res = execute(deps.as_mut(), env.clone(), info.clone(), update_router()).unwrap();
+        let release_at = env.block.time.seconds() + 100;
+        assert_eq!(
+            res.attributes,
+            vec![
+                attr("action", "update_config"),
+                attr("action", "router_addr"),
+                attr("pending_router_addr", ROUTER),
+                attr("pending_router_release_at", release_at.to_string()),
+            ]
+        );
+
+        let config = query_config(deps.as_ref()).unwrap();
+        assert_eq!(config.router_addr, "router0000");
+        assert_eq!(config.pending_router_addr, Some(ROUTER.to_string()));
+        assert_eq!(config.pending_router_release_at, Some(release_at));
+
+        let res = execute(
+            deps.as_mut(),
+            env.clone(),
+            info.clone(),
+            ExecuteMsg::ApplyConfig {},
+        );
+        assert_eq!(
+            res.unwrap_err(),
+            PlanetContractError::ConfigTimelocked { release_at }
+        );
+
+        let mut env = env;
+        env.block.time = env.block.time.plus_seconds(100);
+        let res = execute(
+            deps.as_mut(),
+            env.clone(),
+            info.clone(),
+            ExecuteMsg::ApplyConfig {},
+        )
+        .unwrap();
+        assert_eq!(
+            res.attributes,
+            vec![attr("action", "apply_config"), attr("router_addr", ROUTER)]
+        );
+
+        let config = query_config(deps.as_ref()).unwrap();
+        assert_eq!(config.router_addr, ROUTER);
+        assert_eq!(config.pending_router_addr, None);
+
+        let res = execute(deps.as_mut(), env, info, ExecuteMsg::ApplyConfig {});
+        assert_eq!(res.unwrap_err(), PlanetContractError::NoPendingConfig {});
+    }
+
+    #[test]
+    fn cancel_router() {
+        let mut deps = init_with_delay(100);
+
+        let info = mock_info(OWNER, &[]);
+        execute(deps.as_mut(), mock_env(), info.clone(), update_router()).unwrap();
+
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(ROUTER, &[]),
+            ExecuteMsg::CancelConfig {},
+        );
+        assert_eq!(res.unwrap_err(), PlanetContractError::Unauthorized {});
+
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            info.clone(),
+            ExecuteMsg::CancelConfig {},
+        )
+        .unwrap();
+        let config = query_config(deps.as_ref()).unwrap();
+        assert_eq!(config.router_addr, "router0000");
+        assert_eq!(config.pending_router_addr, None);
+
+        // without a delay the router is set at once
+        let mut config = load_config(deps.as_ref()).unwrap();
+        config.config_delay = 0;
+        set_config(deps.as_mut(), config).unwrap();
+        execute(deps.as_mut(), mock_env(), info, update_router()).unwrap();
+        assert_eq!(query_config(deps.as_ref()).unwrap().router_addr, ROUTER);
     }
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
-pub fn migrate(deps: DepsMut, env: Env, _msg: MigrateMsg) -> StdResult<Response> {
-    let config = get_config(deps.as_ref()).unwrap();
-    let vaults_address = config.token_address.unwrap();
-    let amount =
-        query_token_balance(&deps.querier, vaults_address.clone(), env.contract.address).unwrap();
+pub fn migrate(mut deps: DepsMut, env: Env, _msg: MigrateMsg) -> StdResult<Response> {
+    migrate_state(deps.branch())?;
+
+    let config = load_config(deps.as_ref())?;
+    let vaults_address = vaults_token(&config)?;
+    let amount = query_token_balance(&deps.querier, vaults_address.clone(), env.contract.address)
+        .map_err(PlanetContractError::query_failed(&vaults_address))?;
 
     Ok(
         Response::new().add_message(CosmosMsg::Wasm(WasmMsg::Execute {
@@ -2,3 +2,7 @@ pub mod contract;
 pub mod msg;
 pub mod querier;
 pub mod state;
+pub mod strategy;
+
+#[cfg(not(target_arch = "wasm32"))]
+pub mod mock_querier;
@@ -0,0 +1,88 @@
+use std::ops::{Deref, DerefMut};
+
+use cosmwasm_bignumber::{Decimal256, Uint256};
+use cosmwasm_std::testing::{MockApi, MockQuerier, MockStorage, MOCK_CONTRACT_ADDR};
+use cosmwasm_std::{
+    from_binary, from_slice, to_binary, Coin, ContractResult, OwnedDeps, Querier, QuerierResult,
+    QueryRequest, SystemError, SystemResult, WasmQuery,
+};
+use moneymarket::market::{EpochStateResponse, QueryMsg as MarketQueryMsg};
+use starflet_protocol::mock_querier::WasmMockQuerier as PlanetMockQuerier;
+use terra_cosmwasm::TerraQueryWrapper;
+
+/// mock_dependencies is a drop-in replacement for cosmwasm_std::testing::mock_dependencies
+/// this uses the planet mock querier with an Anchor market on top.
+pub fn mock_dependencies(
+    contract_balance: &[Coin],
+) -> OwnedDeps<MockStorage, MockApi, WasmMockQuerier> {
+    let custom_querier = WasmMockQuerier::new(PlanetMockQuerier::new(MockQuerier::new(&[(
+        MOCK_CONTRACT_ADDR,
+        contract_balance,
+    )])));
+
+    OwnedDeps {
+        storage: MockStorage::default(),
+        api: MockApi::default(),
+        querier: custom_querier,
+    }
+}
+
+/// Answers the Anchor `epoch_state` of any contract, everything else goes to the planet querier
+pub struct WasmMockQuerier {
+    base: PlanetMockQuerier,
+    exchange_rate: Decimal256,
+}
+
+impl Querier for WasmMockQuerier {
+    fn raw_query(&self, bin_request: &[u8]) -> QuerierResult {
+        let request: QueryRequest<TerraQueryWrapper> = match from_slice(bin_request) {
+            Ok(v) => v,
+            Err(e) => {
+                return SystemResult::Err(SystemError::InvalidRequest {
+                    error: format!("Parsing query request: {}", e),
+                    request: bin_request.into(),
+                })
+            }
+        };
+
+        if let QueryRequest::Wasm(WasmQuery::Smart { msg, .. }) = &request {
+            if let Ok(MarketQueryMsg::EpochState { .. }) = from_binary(msg) {
+                let res = EpochStateResponse {
+                    exchange_rate: self.exchange_rate,
+                    aterra_supply: Uint256::zero(),
+                };
+                return SystemResult::Ok(ContractResult::Ok(to_binary(&res).unwrap()));
+            }
+        }
+
+        self.base.handle_query(&request)
+    }
+}
+
+impl WasmMockQuerier {
+    pub fn new(base: PlanetMockQuerier) -> Self {
+        WasmMockQuerier {
+            base,
+            exchange_rate: Decimal256::one(),
+        }
+    }
+
+    // configure the aUST to UST rate of the market
+    pub fn with_exchange_rate(&mut self, exchange_rate: Decimal256) {
+        self.exchange_rate = exchange_rate;
+    }
+}
+
+impl Deref for WasmMockQuerier {
+    type Target = PlanetMockQuerier;
+
+    fn deref(&self) -> &Self::Target {
+        &self.base
+    }
+}
+
+impl DerefMut for WasmMockQuerier {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.base
+    }
+}
@@ -3,8 +3,10 @@ use cosmwasm_std::Uint128;
 use cw20::Cw20ReceiveMsg;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use starflet_protocol::planet::{clearable, TokenMarketing};
 use terraswap::asset::{Asset, AssetInfo};
 
+// inside the planet STRATEGY_REPLY_IDS
 pub const MSG_REPLY_PREPARE_SWAP: u64 = 11;
 pub const MSG_REPLY_SWAP: u64 = 12;
 pub const MSG_REPLY_BOND: u64 = 21;
@@ -20,6 +22,11 @@ pub struct InstantiateMsg {
     pub token_code_id: u64,
     pub router_addr: String,
     pub money_market_addr: String,
+    pub guardian: Option<String>,
+    pub marketing: Option<TokenMarketing>,
+    pub token_admin: Option<String>,
+    pub vaults_token: Option<String>,
+    pub config_delay: Option<u64>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -31,15 +38,52 @@ pub enum ExecuteMsg {
         commission_rate: Option<Decimal256>,
         code_id: Option<u64>,
         router_addr: Option<String>,
+        /// `null` removes the guardian
+        #[serde(
+            default,
+            deserialize_with = "clearable",
+            skip_serializing_if = "Option::is_none"
+        )]
+        guardian: Option<Option<String>>,
+        config_delay: Option<u64>,
     },
+    ApplyConfig {},
+    CancelConfig {},
     Bond {
         asset: Asset,
+        /// Receives the minted shares, the sender when not set
+        recipient: Option<String>,
+        /// Fail if fewer shares are minted once the deposit into Anchor settles
+        min_shares_out: Option<Uint128>,
     },
     Swap {
         path: String,
         amount: Uint128,
     },
     Claim {},
+    SetPause {
+        bond: Option<bool>,
+        unbond: Option<bool>,
+        execute: Option<bool>,
+        claim: Option<bool>,
+    },
+    UpdateExecutors {
+        add: Vec<String>,
+        remove: Vec<String>,
+    },
+    /// Reconciles the vaults with the aUST held by the planet
+    Sync {
+        absorb_shortfall: Option<bool>,
+    },
+    /// Books aUST moved in behind an adopted vaults token without commission
+    BookBacking {
+        amount: Uint128,
+    },
+    /// Winds the planet down, owner or guardian only
+    Shutdown {
+        /// Redeem the aUST of the vaults so holders withdraw UST
+        unwind: Option<bool>,
+    },
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -63,6 +107,11 @@ pub struct ConfigResponse {
     pub deposit_asset_info: AssetInfo,
     pub router_addr: String,
     pub money_market_addr: String,
+    pub executors: Vec<String>,
+    pub config_delay: u64,
+    /// Router scheduled by `update_config`, set by `apply_config` from `pending_router_release_at`
+    pub pending_router_addr: Option<String>,
+    pub pending_router_release_at: Option<u64>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
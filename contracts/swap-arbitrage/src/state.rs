@@ -1,5 +1,6 @@
 use cosmwasm_std::{Addr, Deps, DepsMut, StdResult, Uint128};
-use cw_storage_plus::Item;
+use cw_storage_plus::{Item, Map};
+use planet::state::StakerBasis;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use terraswap::asset::AssetInfo;
@@ -14,6 +15,27 @@ pub fn set_router(deps: DepsMut, router: Addr) -> StdResult<()> {
     ROUTER.save(deps.storage, &router)
 }
 
+/// Router change waiting for the config delay, applied and dropped with the planet config
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PendingRouter {
+    pub router: Addr,
+    pub release_at: u64,
+}
+
+pub const PENDING_ROUTER: Item<PendingRouter> = Item::new("pending_router");
+
+pub fn get_pending_router(deps: Deps) -> StdResult<Option<PendingRouter>> {
+    PENDING_ROUTER.may_load(deps.storage)
+}
+
+pub fn set_pending_router(deps: DepsMut, pending_router: PendingRouter) -> StdResult<()> {
+    PENDING_ROUTER.save(deps.storage, &pending_router)
+}
+
+pub fn remove_pending_router(deps: DepsMut) {
+    PENDING_ROUTER.remove(deps.storage)
+}
+
 pub const DEPOSIT_ASSET_INFO: Item<AssetInfo> = Item::new("bond_asset");
 
 pub fn get_deposit_asset_info(deps: Deps) -> StdResult<AssetInfo> {
@@ -68,7 +90,13 @@ pub fn remove_tmp_swap(deps: DepsMut) {
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct TmpBonder {
     pub bonder: Addr,
+    /// Receives the shares on bond, or the deposit asset on unbond
+    pub recipient: Addr,
     pub prev_amount: Uint128,
+    /// Deposit asset paid in on bond, or shares burned on unbond
+    pub amount: Uint128,
+    /// Minimum shares minted on bond, or deposit asset paid out on unbond
+    pub min_out: Option<Uint128>,
 }
 
 pub const TMP_BONDER: Item<TmpBonder> = Item::new("tmp_bonder");
@@ -77,12 +105,56 @@ pub fn get_tmp_bonder(deps: Deps) -> StdResult<TmpBonder> {
     TMP_BONDER.load(deps.storage)
 }
 
-pub fn set_tmp_bonder(deps: DepsMut, bonder: Addr, prev_amount: Uint128) -> StdResult<()> {
+pub fn set_tmp_bonder(
+    deps: DepsMut,
+    bonder: Addr,
+    recipient: Addr,
+    prev_amount: Uint128,
+    amount: Uint128,
+    min_out: Option<Uint128>,
+) -> StdResult<()> {
     TMP_BONDER.save(
         deps.storage,
         &TmpBonder {
             bonder,
+            recipient,
             prev_amount,
+            amount,
+            min_out,
         },
     )
 }
+
+/// Staker basis in the deposit asset. The planet keeps its own in aUST, which would value
+/// past deposits at today's exchange rate
+pub const DEPOSIT_BASIS: Map<&Addr, StakerBasis> = Map::new("deposit_basis");
+
+pub fn get_deposit_basis(deps: Deps, addr: &Addr) -> StdResult<StakerBasis> {
+    Ok(DEPOSIT_BASIS
+        .may_load(deps.storage, addr)?
+        .unwrap_or_default())
+}
+
+pub fn add_deposited(
+    deps: DepsMut,
+    addr: &Addr,
+    amount: Uint128,
+    shares: Uint128,
+) -> StdResult<()> {
+    let mut basis = get_deposit_basis(deps.as_ref(), addr)?;
+    basis.add_deposit(amount, shares)?;
+
+    DEPOSIT_BASIS.save(deps.storage, addr, &basis)
+}
+
+pub fn add_withdrawn(
+    deps: DepsMut,
+    addr: &Addr,
+    amount: Uint128,
+    shares: Uint128,
+) -> StdResult<()> {
+    let mut basis = get_deposit_basis(deps.as_ref(), addr)?;
+    basis.add_withdrawal(amount, shares)?;
+
+    DEPOSIT_BASIS.save(deps.storage, addr, &basis)
+}
@@ -0,0 +1,93 @@
+use cosmwasm_std::{
+    to_binary, Addr, Binary, Deps, DepsMut, Env, Reply, Response, StdResult, Uint128,
+};
+use planet::{error::ContractError, strategy::Strategy};
+use starflet_protocol::planet::QueryMsg;
+use terra_cosmwasm::TerraMsgWrapper;
+use terraswap::asset::Asset;
+
+use crate::contract::{
+    query_commission, query_config, query_convert_to_assets, query_convert_to_shares,
+    query_max_bond, query_preview_bond, query_preview_unbond, query_stake_info, query_total_assets,
+    strategy_reply, try_bond, try_unbond,
+};
+
+/// Keeps the vaults in Anchor aUST and arbitrages UST through the router on execute.
+/// Bonds and unbonds are taken and paid in UST, and the queries value the aUST in UST
+pub struct SwapArbitrage;
+
+impl Strategy for SwapArbitrage {
+    fn on_bond(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        sender: Addr,
+        recipient: Addr,
+        asset: Asset,
+        min_shares_out: Option<Uint128>,
+    ) -> Result<Response<TerraMsgWrapper>, ContractError> {
+        try_bond(deps, env, sender, recipient, asset, min_shares_out)
+    }
+
+    fn on_unbond(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        vaults_contract: Addr,
+        sender: Addr,
+        recipient: Addr,
+        shares: Uint128,
+        min_assets_out: Option<Uint128>,
+    ) -> Result<Response<TerraMsgWrapper>, ContractError> {
+        try_unbond(
+            deps,
+            env,
+            vaults_contract,
+            sender,
+            recipient,
+            shares,
+            min_assets_out,
+        )
+    }
+
+    fn on_reply(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        reply: Reply,
+    ) -> Result<Response<TerraMsgWrapper>, ContractError> {
+        strategy_reply(deps, env, reply)
+    }
+
+    fn extra_queries(&self, deps: Deps, env: &Env, msg: &QueryMsg) -> Option<StdResult<Binary>> {
+        let env = env.clone();
+        let res = match msg.clone() {
+            QueryMsg::Config {} => query_config(deps).and_then(|res| to_binary(&res)),
+            QueryMsg::StakerInfo { staker_addr } => {
+                query_stake_info(deps, env, staker_addr).and_then(|res| to_binary(&res))
+            }
+            QueryMsg::Commission {} => query_commission(deps, env).and_then(|res| to_binary(&res)),
+            QueryMsg::PreviewBond { asset } => {
+                query_preview_bond(deps, env, asset).and_then(|res| to_binary(&res))
+            }
+            QueryMsg::PreviewUnbond { shares } => {
+                query_preview_unbond(deps, env, shares).and_then(|res| to_binary(&res))
+            }
+            QueryMsg::ConvertToShares { amount } => {
+                query_convert_to_shares(deps, env, amount).and_then(|res| to_binary(&res))
+            }
+            QueryMsg::ConvertToAssets { shares } => {
+                query_convert_to_assets(deps, env, shares).and_then(|res| to_binary(&res))
+            }
+            QueryMsg::TotalAssets {} => {
+                query_total_assets(deps, env).and_then(|res| to_binary(&res))
+            }
+            QueryMsg::MaxBond { address, proof } => {
+                query_max_bond(deps, env, address, proof).and_then(|res| to_binary(&res))
+            }
+            _ => return None,
+        };
+
+        Some(res)
+    }
+}
@@ -5,7 +5,12 @@ use cosmwasm_schema::{export_schema, remove_schemas, schema_for};
 
 use planet::state::Config;
 use starflet_protocol::planet::{
-    ConfigResponse, ExecuteMsg, InstantiateMsg, QueryMsg, StakerInfoResponse,
+    CommissionResponse, ConfigResponse, ConvertToAssetsResponse, ConvertToSharesResponse,
+    Cw20HookMsg, DepositCapacityResponse, ExecuteMsg, ExecuteTargetResponse, ExecutionsResponse,
+    HealthResponse, InstantiateMsg, IsAllowedResponse, MaxBondResponse, MaxUnbondResponse,
+    PauseStateResponse, PendingConfigResponse, PendingUnbondsResponse, PreviewBondResponse,
+    PreviewUnbondResponse, QueryMsg, RateResponse, StakerInfoResponse, StatsResponse,
+    TotalAssetsResponse,
 };
 
 fn main() {
@@ -17,7 +22,26 @@ fn main() {
     export_schema(&schema_for!(InstantiateMsg), &out_dir);
     export_schema(&schema_for!(ExecuteMsg), &out_dir);
     export_schema(&schema_for!(QueryMsg), &out_dir);
+    export_schema(&schema_for!(Cw20HookMsg), &out_dir);
     export_schema(&schema_for!(Config), &out_dir);
     export_schema(&schema_for!(ConfigResponse), &out_dir);
     export_schema(&schema_for!(StakerInfoResponse), &out_dir);
+    export_schema(&schema_for!(CommissionResponse), &out_dir);
+    export_schema(&schema_for!(RateResponse), &out_dir);
+    export_schema(&schema_for!(PendingConfigResponse), &out_dir);
+    export_schema(&schema_for!(PendingUnbondsResponse), &out_dir);
+    export_schema(&schema_for!(DepositCapacityResponse), &out_dir);
+    export_schema(&schema_for!(IsAllowedResponse), &out_dir);
+    export_schema(&schema_for!(PauseStateResponse), &out_dir);
+    export_schema(&schema_for!(ExecuteTargetResponse), &out_dir);
+    export_schema(&schema_for!(ExecutionsResponse), &out_dir);
+    export_schema(&schema_for!(StatsResponse), &out_dir);
+    export_schema(&schema_for!(HealthResponse), &out_dir);
+    export_schema(&schema_for!(PreviewBondResponse), &out_dir);
+    export_schema(&schema_for!(PreviewUnbondResponse), &out_dir);
+    export_schema(&schema_for!(ConvertToSharesResponse), &out_dir);
+    export_schema(&schema_for!(ConvertToAssetsResponse), &out_dir);
+    export_schema(&schema_for!(TotalAssetsResponse), &out_dir);
+    export_schema(&schema_for!(MaxBondResponse), &out_dir);
+    export_schema(&schema_for!(MaxUnbondResponse), &out_dir);
 }
@@ -0,0 +1,175 @@
+use cosmwasm_std::{Addr, Binary, CosmosMsg, Deps, StdResult, WasmMsg};
+use sha2::{Digest, Sha256};
+use starflet_protocol::planet::AccessMode;
+use terra_cosmwasm::TerraMsgWrapper;
+
+use crate::error::ContractError;
+use crate::state::{allows_funds, get_config, get_execute_target, is_allowlisted};
+
+pub fn validate_access_mode(access_mode: &AccessMode) -> Result<(), ContractError> {
+    if let AccessMode::MerkleRoot { root } = access_mode {
+        let mut buf = [0u8; 32];
+        hex::decode_to_slice(root, &mut buf)
+            .map_err(|_| ContractError::InvalidMerkleRoot(root.to_string()))?;
+    }
+
+    Ok(())
+}
+
+/// Only `WasmMsg::Execute` on an allowlisted target may be dispatched by execute,
+/// with coins only when the target allows funds
+pub fn assert_execute_allowed(
+    deps: Deps,
+    msg: &CosmosMsg<TerraMsgWrapper>,
+) -> Result<(), ContractError> {
+    let (contract_addr, msg, funds) = match msg {
+        CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr,
+            msg,
+            funds,
+        }) => (contract_addr, msg, funds),
+        CosmosMsg::Bank(_) => return Err(ContractError::ExecuteNotAllowed("bank".to_string())),
+        CosmosMsg::Wasm(_) => {
+            return Err(ContractError::ExecuteNotAllowed(
+                "wasm instantiate or migrate".to_string(),
+            ))
+        }
+        _ => return Err(ContractError::ExecuteNotAllowed("message".to_string())),
+    };
+
+    let target = deps.api.addr_validate(contract_addr)?;
+    let messages = match get_execute_target(deps, &target)? {
+        Some(messages) => messages,
+        None => return Err(ContractError::ExecuteNotAllowed(contract_addr.to_string())),
+    };
+
+    if !funds.is_empty() && !allows_funds(deps, &target)? {
+        return Err(ContractError::ExecuteNotAllowed(format!(
+            "funds to {}",
+            contract_addr
+        )));
+    }
+
+    if messages.is_empty() {
+        return Ok(());
+    }
+
+    match message_name(msg) {
+        Some(name) if messages.contains(&name) => Ok(()),
+        Some(name) => Err(ContractError::ExecuteNotAllowed(format!(
+            "{} on {}",
+            name, contract_addr
+        ))),
+        None => Err(ContractError::ExecuteNotAllowed(format!(
+            "unnamed message on {}",
+            contract_addr
+        ))),
+    }
+}
+
+/// Top-level key of a `{"name": {...}}` execute message
+fn message_name(msg: &Binary) -> Option<String> {
+    // serde-json-wasm cannot deserialize into a map, so read the first key directly
+    let msg = std::str::from_utf8(msg.as_slice()).ok()?;
+    let rest = msg
+        .trim_start()
+        .strip_prefix('{')?
+        .trim_start()
+        .strip_prefix('"')?;
+
+    rest.find('"').map(|end| rest[..end].to_string())
+}
+
+pub fn is_allowed(deps: Deps, depositor: &Addr, proof: &Option<Vec<String>>) -> StdResult<bool> {
+    let config = get_config(deps)?;
+
+    match config.access_mode {
+        AccessMode::Open => Ok(true),
+        AccessMode::Allowlist => is_allowlisted(deps, depositor),
+        AccessMode::MerkleRoot { root } => Ok(match proof {
+            Some(proof) => verify_merkle_proof(&root, depositor.as_str(), proof),
+            None => false,
+        }),
+    }
+}
+
+pub fn assert_allowed(
+    deps: Deps,
+    depositor: &Addr,
+    proof: &Option<Vec<String>>,
+) -> Result<(), ContractError> {
+    if !is_allowed(deps, depositor, proof)? {
+        return Err(ContractError::NotAllowed(depositor.to_string()));
+    }
+
+    Ok(())
+}
+
+/// Sibling hashes are combined in sorted order, so the proof carries no left/right flags.
+pub fn verify_merkle_proof(root: &str, leaf: &str, proof: &[String]) -> bool {
+    let mut hash: [u8; 32] = Sha256::digest(leaf.as_bytes()).into();
+
+    for sibling in proof {
+        let mut sibling_hash = [0u8; 32];
+        if hex::decode_to_slice(sibling, &mut sibling_hash).is_err() {
+            return false;
+        }
+
+        let mut pair = [hash, sibling_hash];
+        pair.sort_unstable();
+        hash = Sha256::digest(&pair.concat()).into();
+    }
+
+    let mut root_hash = [0u8; 32];
+    if hex::decode_to_slice(root, &mut root_hash).is_err() {
+        return false;
+    }
+
+    hash == root_hash
+}
+
+#[cfg(test)]
+mod merkle {
+    use super::*;
+
+    fn leaf(addr: &str) -> [u8; 32] {
+        Sha256::digest(addr.as_bytes()).into()
+    }
+
+    fn parent(a: [u8; 32], b: [u8; 32]) -> [u8; 32] {
+        let mut pair = [a, b];
+        pair.sort_unstable();
+        Sha256::digest(&pair.concat()).into()
+    }
+
+    #[test]
+    fn verify_proof() {
+        let leaves = [
+            leaf("addr0000"),
+            leaf("addr0001"),
+            leaf("addr0002"),
+            leaf("addr0003"),
+        ];
+        let left = parent(leaves[0], leaves[1]);
+        let right = parent(leaves[2], leaves[3]);
+        let root = hex::encode(parent(left, right));
+
+        let proof = vec![hex::encode(leaves[3]), hex::encode(left)];
+        assert!(verify_merkle_proof(&root, "addr0002", &proof));
+        assert!(!verify_merkle_proof(&root, "addr0004", &proof));
+        assert!(!verify_merkle_proof(&root, "addr0002", &proof[..1]));
+        assert!(!verify_merkle_proof(&root, "addr0002", &["zz".to_string()]));
+    }
+
+    #[test]
+    fn invalid_merkle_root() {
+        assert!(validate_access_mode(&AccessMode::MerkleRoot {
+            root: "1234".to_string()
+        })
+        .is_err());
+        assert!(validate_access_mode(&AccessMode::MerkleRoot {
+            root: hex::encode([0u8; 32])
+        })
+        .is_ok());
+    }
+}
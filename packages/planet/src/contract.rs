@@ -1,29 +1,43 @@
 use cosmwasm_std::{
-    attr, from_binary, to_binary, Addr, Attribute, BankMsg, Binary, CanonicalAddr, CosmosMsg, Deps,
-    DepsMut, Env, MessageInfo, Reply, ReplyOn, Response, StdError, StdResult, SubMsg, Uint128,
-    WasmMsg,
+    attr, from_binary, to_binary, Addr, Attribute, Binary, CanonicalAddr, CosmosMsg, Deps, DepsMut,
+    Env, MessageInfo, Reply, ReplyOn, Response, StdError, StdResult, SubMsg, Uint128, WasmMsg,
 };
 use moneymarket::querier::query_supply;
 use starflet_protocol::planet::{
-    Action, CommissionResponse, ConfigResponse, Cw20HookMsg, ExecuteMsg, InstantiateMsg,
-    MigrateMsg, QueryMsg, RateResponse, StakerInfoResponse,
+    AccessMode, Action, CommissionResponse, ConfigResponse, ConvertToAssetsResponse,
+    ConvertToSharesResponse, Cw20HookMsg, DepositCapacityResponse, ExecuteMsg, ExecuteTarget,
+    ExecuteTargetResponse, Execution, ExecutionsResponse, HealthResponse, InstantiateMsg,
+    IsAllowedResponse, MaxBondResponse, MaxUnbondResponse, MigrateMsg, PauseStateResponse,
+    PendingConfigResponse, PendingUnbond, PendingUnbondsResponse, Pnl, PreviewBondResponse,
+    PreviewUnbondResponse, QueryMsg, RateResponse, StakerInfoResponse, StatsResponse,
+    TotalAssetsResponse,
 };
-use std::ops::{Div, Mul};
 use terra_cosmwasm::TerraMsgWrapper;
 use terraswap::asset::{Asset, AssetInfo};
 use terraswap::querier::query_token_balance;
 
+use crate::access::{assert_allowed, assert_execute_allowed, is_allowed, validate_access_mode};
 use crate::error::ContractError;
 use crate::state::{
-    add_commission, add_vaults, get_commission, get_config, get_vaults, init, set_config,
-    set_vaults, sub_all_commission, sub_commission, sub_vaults, Config,
+    add_allowlist, add_bonded, add_commission, add_commission_claimed, add_execution,
+    add_staker_deposit, add_staker_withdrawal, add_synced, add_unbonded, add_unbonding, add_vaults,
+    allows_funds, get_commission, get_config, get_execute_target, get_execution_totals,
+    get_pause_state, get_pending_config, get_staker_basis, get_stats, get_sync_totals,
+    get_tmp_execute, get_total_unbonding, get_unbondings, get_vaults, init, is_backing_pending,
+    is_shutdown, load_executions, migrate_state, remove_allowlist, remove_execute_target,
+    remove_pending_config, remove_tmp_execute, set_allow_funds, set_backing_pending, set_config,
+    set_execute_target, set_pause_state, set_pending_config, set_shutdown, set_tmp_execute,
+    set_vaults, sub_all_commission, sub_commission, sub_vaults, withdraw_unbondings, Config,
+    PauseState, PendingConfig, StakerBasis, TmpExecute, Unbonding,
 };
 
 use crate::response::MsgInstantiateContractResponse;
+use crate::strategy::{Hold, Strategy, PLANET_REPLY_IDS, STRATEGY_REPLY_IDS};
+use crate::vault_math;
 use cosmwasm_bignumber::{Decimal256, Uint256};
-use cw20::{Cw20ExecuteMsg, Cw20ReceiveMsg, MinterResponse};
+use cw20::{Cw20ExecuteMsg, Cw20QueryMsg, Cw20ReceiveMsg, Logo, MinterResponse, TokenInfoResponse};
 use protobuf::Message;
-use terraswap::token::InstantiateMsg as TokenInstantiateMsg;
+use terraswap::token::{InstantiateMarketingInfo, InstantiateMsg as TokenInstantiateMsg};
 
 use std::convert::From;
 
@@ -32,47 +46,151 @@ pub const MSG_REPLY_ID_EXECUTE: u64 = 2;
 pub const MSG_REPLY_ID_EXECUTE_SKIP: u64 = 3;
 pub const MSG_REPLY_ID_MUST_EXECUTE: u64 = 4;
 
+/// Hard cap on the commission rate, at instantiate and on every update
+pub fn max_commission_rate() -> Decimal256 {
+    Decimal256::percent(50)
+}
+
+pub fn validate_commission_rate(commission_rate: Decimal256) -> Result<(), ContractError> {
+    if commission_rate > max_commission_rate() {
+        return Err(ContractError::InvalidCommissionRate {
+            rate: commission_rate,
+            max: max_commission_rate(),
+        });
+    }
+
+    Ok(())
+}
+
 pub fn instantiate(
     mut deps: DepsMut,
     env: Env,
     info: MessageInfo,
     msg: InstantiateMsg,
 ) -> Result<Response, ContractError> {
+    validate_commission_rate(msg.commission_rate)?;
+
+    let access_mode = msg.access_mode.unwrap_or(AccessMode::Open);
+    validate_access_mode(&access_mode)?;
+
+    let guardian = match msg.guardian {
+        Some(guardian) => Some(deps.api.addr_validate(&guardian)?),
+        None => None,
+    };
+
+    let vaults_token = match msg.vaults_token {
+        Some(vaults_token) => Some(adopt_vaults_token(
+            deps.as_ref(),
+            &env,
+            deps.api.addr_validate(&vaults_token)?,
+        )?),
+        None => None,
+    };
+
     let state = Config {
         owner: info.sender.clone(),
         commission_rate: msg.commission_rate,
-        asset_info: msg.asset_info,
+        asset_info: msg.asset_info.clone(),
         token_code_id: msg.token_code_id,
-        token_address: None,
+        token_address: vaults_token.clone(),
+        unbonding_period: msg.unbonding_period.unwrap_or_default(),
+        max_total_deposit: msg.max_total_deposit,
+        max_deposit_per_user: msg.max_deposit_per_user,
+        access_mode,
+        guardian,
+        executors: vec![],
+        max_rate_change: msg.max_rate_change,
+        config_delay: msg.config_delay.unwrap_or_default(),
     };
 
-    set_config(deps.branch(), state).unwrap();
+    set_config(deps.branch(), state)?;
 
-    init(deps).unwrap();
+    init(deps.branch())?;
 
-    Ok(Response::new()
+    let res = Response::new()
         .add_attribute("action", Action::Instantiate.to_string())
-        .add_attribute("owner", info.sender)
-        .add_attribute("commission_rate", msg.commission_rate.to_string())
-        .add_submessage(SubMsg::reply_on_success(
-            CosmosMsg::Wasm(WasmMsg::Instantiate {
-                admin: Some(env.contract.address.to_string()),
-                code_id: msg.token_code_id,
-                funds: vec![],
-                label: "".to_string(),
-                msg: to_binary(&TokenInstantiateMsg {
-                    name: format!("{} vaults", msg.symbol),
-                    symbol: format!("v{}", msg.symbol),
-                    decimals: 6u8,
-                    initial_balances: vec![],
-                    mint: Some(MinterResponse {
-                        minter: env.contract.address.to_string(),
-                        cap: None,
-                    }),
-                })?,
-            }),
-            MSG_REPLY_ID_TOKEN_INSTANT,
-        )))
+        .add_attribute("owner", info.sender.to_string())
+        .add_attribute("commission_rate", msg.commission_rate.to_string());
+
+    if let Some(vaults_token) = vaults_token {
+        // shares already held are not backed by the empty vaults until assets are moved in,
+        // they would be priced at zero
+        let supply = query_vaults_supply(deps.as_ref(), vaults_token.clone())?;
+        if !supply.is_zero() {
+            set_pause_state(
+                deps.branch(),
+                PauseState {
+                    bond: true,
+                    unbond: true,
+                    execute: true,
+                    claim: false,
+                },
+            )?;
+            set_backing_pending(deps.branch(), true)?;
+        }
+
+        return Ok(res
+            .add_attribute("vaults_token", vaults_token)
+            .add_attribute("vaults_supply", supply.to_string()));
+    }
+
+    let token_admin = match msg.token_admin {
+        Some(token_admin) => deps.api.addr_validate(&token_admin)?,
+        None => env.contract.address.clone(),
+    };
+
+    // terra native coins all use 6 decimals
+    let decimals = match &msg.asset_info {
+        AssetInfo::Token { contract_addr } => {
+            let token_info: TokenInfoResponse = deps
+                .querier
+                .query_wasm_smart(contract_addr, &Cw20QueryMsg::TokenInfo {})
+                .map_err(ContractError::query_failed(contract_addr))?;
+            token_info.decimals
+        }
+        AssetInfo::NativeToken { .. } => 6u8,
+    };
+
+    let marketing = msg.marketing.map(|marketing| InstantiateMarketingInfo {
+        project: marketing.project,
+        description: marketing.description,
+        marketing: Some(info.sender.to_string()),
+        logo: marketing.logo_url.map(Logo::Url),
+    });
+
+    Ok(res.add_submessage(SubMsg::reply_on_success(
+        CosmosMsg::Wasm(WasmMsg::Instantiate {
+            admin: Some(token_admin.to_string()),
+            code_id: msg.token_code_id,
+            funds: vec![],
+            label: format!("{} vaults {}", msg.symbol, env.contract.address),
+            msg: to_binary(&TokenInstantiateMsg {
+                name: format!("{} vaults", msg.symbol),
+                symbol: format!("v{}", msg.symbol),
+                decimals,
+                initial_balances: vec![],
+                mint: Some(MinterResponse {
+                    minter: env.contract.address.to_string(),
+                    cap: None,
+                }),
+                marketing,
+            })?,
+        }),
+        MSG_REPLY_ID_TOKEN_INSTANT,
+    )))
+}
+
+/// Checks that the planet is the minter of an existing vaults token
+fn adopt_vaults_token(deps: Deps, env: &Env, vaults_token: Addr) -> Result<Addr, ContractError> {
+    let minter: Option<MinterResponse> = deps
+        .querier
+        .query_wasm_smart(&vaults_token, &Cw20QueryMsg::Minter {})
+        .map_err(ContractError::query_failed(&vaults_token))?;
+
+    match minter {
+        Some(minter) if minter.minter == env.contract.address.as_str() => Ok(vaults_token),
+        _ => Err(ContractError::NotMinter(vaults_token.to_string())),
+    }
 }
 
 pub fn execute(
@@ -80,33 +198,119 @@ pub fn execute(
     env: Env,
     info: MessageInfo,
     msg: ExecuteMsg,
+) -> Result<Response<TerraMsgWrapper>, ContractError> {
+    execute_with(&Hold, deps, env, info, msg)
+}
+
+pub fn execute_with<S: Strategy>(
+    strategy: &S,
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    msg: ExecuteMsg,
 ) -> Result<Response<TerraMsgWrapper>, ContractError> {
     match msg {
-        ExecuteMsg::Receive(msg) => receive_cw20(deps, env, info, msg),
+        ExecuteMsg::Receive(msg) => receive_cw20(strategy, deps, env, info, msg),
         ExecuteMsg::UpdateConfig {
             owner,
             commission_rate,
             code_id,
-        } => try_update_config(deps, info, owner, commission_rate, code_id),
-        ExecuteMsg::Bond { asset } => {
-            asset.assert_sent_native_token_balance(&info).unwrap();
-            try_bond(deps, info.sender, asset)
+            unbonding_period,
+            max_total_deposit,
+            max_deposit_per_user,
+            guardian,
+            max_rate_change,
+            config_delay,
+        } => try_update_config(
+            deps,
+            env,
+            info,
+            owner,
+            commission_rate,
+            code_id,
+            unbonding_period,
+            max_total_deposit,
+            max_deposit_per_user,
+            guardian,
+            max_rate_change,
+            config_delay,
+        ),
+        ExecuteMsg::ApplyConfig {} => try_apply_config(deps, env, info),
+        ExecuteMsg::CancelConfig {} => try_cancel_config(deps, info),
+        ExecuteMsg::Bond {
+            asset,
+            proof,
+            recipient,
+            min_shares_out,
+        } => {
+            assert_not_paused(deps.as_ref(), Action::Bond)?;
+            asset.assert_sent_native_token_balance(&info)?;
+            assert_allowed(deps.as_ref(), &info.sender, &proof)?;
+            let recipient = validate_recipient(deps.as_ref(), recipient, &info.sender)?;
+            strategy.on_bond(deps, env, info.sender, recipient, asset, min_shares_out)
+        }
+        ExecuteMsg::Execute {
+            msgs,
+            is_distribute,
+            max_loss,
+            min_profit,
+        } => {
+            assert_not_paused(deps.as_ref(), Action::Execute)?;
+            try_execute(deps, info, msgs, is_distribute, max_loss, min_profit)
+        }
+        ExecuteMsg::Claim {} => {
+            assert_not_paused(deps.as_ref(), Action::Claim)?;
+            try_claim(deps, info)
+        }
+        ExecuteMsg::WithdrawUnbonded {} => {
+            assert_not_paused(deps.as_ref(), Action::WithdrawUnbonded)?;
+            try_withdraw_unbonded(deps, env, info)
+        }
+        ExecuteMsg::UpdateAccessMode { access_mode } => {
+            try_update_access_mode(deps, info, access_mode)
         }
-        ExecuteMsg::Execute { msg, is_distribute } => {
-            try_execute(deps.as_ref(), info, msg, is_distribute)
+        ExecuteMsg::UpdateAllowlist { add, remove } => {
+            try_update_allowlist(deps, info, add, remove)
+        }
+        ExecuteMsg::SetPause {
+            bond,
+            unbond,
+            execute,
+            claim,
+        } => try_set_pause(deps, info, bond, unbond, execute, claim),
+        ExecuteMsg::UpdateExecutors { add, remove } => {
+            try_update_executors(deps, info, add, remove)
+        }
+        ExecuteMsg::UpdateExecuteTargets { add, remove } => {
+            try_update_execute_targets(deps, env, info, add, remove)
+        }
+        ExecuteMsg::Sync { absorb_shortfall } => {
+            try_sync(strategy, deps, env, info, absorb_shortfall)
+        }
+        ExecuteMsg::BookBacking { amount } => try_book_backing(strategy, deps, env, info, amount),
+        ExecuteMsg::Shutdown { unwind_msgs } => {
+            let msgs = parse_execute_msgs(deps.as_ref(), &unwind_msgs.unwrap_or_default())?;
+            Ok(try_shutdown(deps, info)?.add_messages(msgs))
         }
-        ExecuteMsg::Claim {} => try_claim(deps, info),
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn try_update_config(
-    deps: DepsMut,
+    mut deps: DepsMut,
+    env: Env,
     info: MessageInfo,
     owner: Option<String>,
     commission_rate: Option<Decimal256>,
     code_id: Option<u64>,
+    unbonding_period: Option<u64>,
+    max_total_deposit: Option<Option<Uint128>>,
+    max_deposit_per_user: Option<Option<Uint128>>,
+    guardian: Option<Option<String>>,
+    max_rate_change: Option<Option<Decimal256>>,
+    config_delay: Option<u64>,
 ) -> Result<Response<TerraMsgWrapper>, ContractError> {
-    let mut config: Config = get_config(deps.as_ref()).unwrap();
+    let mut config: Config = load_config(deps.as_ref())?;
     let mut res: Vec<Attribute> = vec![Attribute::new("action", Action::UpdateConfig.to_string())];
 
     // permission check
@@ -114,20 +318,36 @@ pub fn try_update_config(
         return Err(ContractError::Unauthorized {});
     }
 
+    // changes stakers can not react to wait for the config delay
+    let mut pending = PendingConfig::default();
+
     if let Some(owner) = owner {
         let _ = deps.api.addr_validate(&owner)?;
 
         let canonical_owner: CanonicalAddr = deps.api.addr_canonicalize(&owner)?;
-        config.owner = deps.api.addr_humanize(&canonical_owner)?;
-        res.push(Attribute::new("owner", owner));
+        pending.owner = Some(deps.api.addr_humanize(&canonical_owner)?);
     }
 
     if let Some(commission_rate) = commission_rate {
-        config.commission_rate = commission_rate;
-        res.push(Attribute::new(
-            "commission_rate",
-            commission_rate.to_string(),
-        ));
+        validate_commission_rate(commission_rate)?;
+        if commission_rate > config.commission_rate {
+            pending.commission_rate = Some(commission_rate);
+        } else {
+            config.commission_rate = commission_rate;
+            res.push(Attribute::new(
+                "commission_rate",
+                commission_rate.to_string(),
+            ));
+        }
+    }
+
+    if let Some(config_delay) = config_delay {
+        if config_delay < config.config_delay {
+            pending.config_delay = Some(config_delay);
+        } else {
+            config.config_delay = config_delay;
+            res.push(Attribute::new("config_delay", config_delay.to_string()));
+        }
     }
 
     if let Some(code_id) = code_id {
@@ -135,1274 +355,6656 @@ pub fn try_update_config(
         res.push(Attribute::new("code_id", code_id.to_string()));
     }
 
-    set_config(deps, config).unwrap();
+    if let Some(unbonding_period) = unbonding_period {
+        config.unbonding_period = unbonding_period;
+        res.push(Attribute::new(
+            "unbonding_period",
+            unbonding_period.to_string(),
+        ));
+    }
 
-    Ok(Response::new().add_attributes(res))
-}
+    // `Some(None)` removes the limit or the guardian, reported as "none"
+    if let Some(max_total_deposit) = max_total_deposit {
+        config.max_total_deposit = max_total_deposit;
+        res.push(Attribute::new(
+            "max_total_deposit",
+            display_or_none(max_total_deposit),
+        ));
+    }
+
+    if let Some(max_deposit_per_user) = max_deposit_per_user {
+        config.max_deposit_per_user = max_deposit_per_user;
+        res.push(Attribute::new(
+            "max_deposit_per_user",
+            display_or_none(max_deposit_per_user),
+        ));
+    }
 
-pub fn compute_share_rate(deps: Deps, vaults_contract: Addr) -> StdResult<Decimal256> {
-    let vaults_total_supply = query_supply(deps, vaults_contract).unwrap();
-    if vaults_total_supply.is_zero() {
-        return Ok(Decimal256::one());
+    if let Some(guardian) = guardian {
+        config.guardian = match guardian {
+            Some(guardian) => Some(deps.api.addr_validate(&guardian)?),
+            None => None,
+        };
+        res.push(Attribute::new(
+            "guardian",
+            display_or_none(config.guardian.clone()),
+        ));
     }
 
-    let balance = get_vaults(deps).unwrap();
+    if let Some(max_rate_change) = max_rate_change {
+        config.max_rate_change = max_rate_change;
+        res.push(Attribute::new(
+            "max_rate_change",
+            display_or_none(max_rate_change),
+        ));
+    }
 
-    let dec_commission = get_commission(deps).unwrap();
+    if pending != PendingConfig::default() {
+        if config.config_delay == 0 {
+            apply_pending_config(&mut config, pending, &mut res);
+        } else {
+            // replaces the config change scheduled before, scheduled execute targets are kept
+            pending.execute_targets = get_pending_config(deps.as_ref())?
+                .map(|pending| pending.execute_targets)
+                .unwrap_or_default();
+            pending.release_at = env.block.time.seconds() + config.config_delay;
+            res.push(Attribute::new(
+                "pending_release_at",
+                pending.release_at.to_string(),
+            ));
+            set_pending_config(deps.branch(), pending)?;
+        }
+    }
 
-    let revenue = balance - dec_commission;
+    set_config(deps, config)?;
 
-    Ok(revenue.div(Decimal256::from_uint256(vaults_total_supply)))
+    Ok(Response::new().add_attributes(res))
 }
 
-pub fn try_bond(
-    deps: DepsMut,
-    sender: Addr,
-    asset: Asset,
-) -> Result<Response<TerraMsgWrapper>, ContractError> {
-    let config = get_config(deps.as_ref()).unwrap();
+fn display_or_none<T: ToString>(value: Option<T>) -> String {
+    value.map_or_else(|| "none".to_string(), |value| value.to_string())
+}
 
-    let token_contract = config.token_address.unwrap();
-    let share_rate = compute_share_rate(deps.as_ref(), token_contract.clone()).unwrap();
-    let mint_amount = Uint256::from(asset.amount) / share_rate;
+fn apply_pending_config(config: &mut Config, pending: PendingConfig, res: &mut Vec<Attribute>) {
+    if let Some(owner) = pending.owner {
+        res.push(Attribute::new("owner", owner.to_string()));
+        config.owner = owner;
+    }
 
-    add_vaults(deps, Decimal256::from_uint256(Uint256::from(asset.amount))).unwrap();
+    if let Some(commission_rate) = pending.commission_rate {
+        config.commission_rate = commission_rate;
+        res.push(Attribute::new(
+            "commission_rate",
+            commission_rate.to_string(),
+        ));
+    }
 
-    Ok(Response::new()
-        .add_message(CosmosMsg::Wasm(WasmMsg::Execute {
-            contract_addr: token_contract.to_string(),
-            funds: vec![],
-            msg: to_binary(&Cw20ExecuteMsg::Mint {
-                recipient: sender.to_string(),
-                amount: mint_amount.into(),
-            })?,
-        }))
-        .add_attribute("action", Action::Bond.to_string())
-        .add_attribute("bonder", sender)
-        .add_attribute("asset", asset.to_string())
-        .add_attribute("mint_amount", mint_amount))
+    if let Some(config_delay) = pending.config_delay {
+        config.config_delay = config_delay;
+        res.push(Attribute::new("config_delay", config_delay.to_string()));
+    }
 }
 
-pub fn try_unbond(
+pub fn try_apply_config(
     mut deps: DepsMut,
-    vaults_contract: Addr,
-    asset_info: AssetInfo,
-    sender: Addr,
-    amount: Uint128,
+    env: Env,
+    info: MessageInfo,
 ) -> Result<Response<TerraMsgWrapper>, ContractError> {
-    let share_rate = compute_share_rate(deps.as_ref(), vaults_contract.clone()).unwrap();
-    let unbond_amount = Uint256::from(amount) * share_rate;
+    let mut config: Config = load_config(deps.as_ref())?;
 
-    let unbond_asset = Asset {
-        amount: unbond_amount.into(),
-        info: asset_info.clone(),
-    };
+    // permission check
+    if info.sender != config.owner {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let pending = get_pending_config(deps.as_ref())?.ok_or(ContractError::NoPendingConfig {})?;
+    if env.block.time.seconds() < pending.release_at {
+        return Err(ContractError::ConfigTimelocked {
+            release_at: pending.release_at,
+        });
+    }
 
-    sub_vaults(deps.branch(), Decimal256::from_uint256(unbond_amount)).unwrap();
+    let mut res: Vec<Attribute> = vec![Attribute::new("action", Action::ApplyConfig.to_string())];
+    for target in pending.execute_targets.iter() {
+        add_execute_target(deps.branch(), target)?;
+        res.push(Attribute::new("add", target.contract_addr.clone()));
+    }
+    apply_pending_config(&mut config, pending, &mut res);
 
-    let send_msg = match asset_info {
-        AssetInfo::Token { contract_addr } => CosmosMsg::Wasm(WasmMsg::Execute {
-            contract_addr,
-            msg: to_binary(&Cw20ExecuteMsg::Transfer {
-                recipient: sender.to_string(),
-                amount,
-            })?,
-            funds: vec![],
-        }),
-        AssetInfo::NativeToken { .. } => CosmosMsg::Bank(BankMsg::Send {
-            to_address: sender.to_string(),
-            amount: vec![unbond_asset.deduct_tax(&deps.querier)?],
-        }),
-    };
+    remove_pending_config(deps.branch());
+    set_config(deps, config)?;
 
-    Ok(Response::new()
-        .add_messages(vec![
-            send_msg,
-            CosmosMsg::Wasm(WasmMsg::Execute {
-                contract_addr: vaults_contract.to_string(),
-                funds: vec![],
-                msg: to_binary(&Cw20ExecuteMsg::Burn { amount })?,
-            }),
-        ])
-        .add_attribute("action", Action::Unbond.to_string())
-        .add_attribute("unbonder", sender)
-        .add_attribute("asset", unbond_asset.to_string())
-        .add_attribute("burn_amount", amount))
+    Ok(Response::new().add_attributes(res))
 }
 
-pub fn try_execute(
-    deps: Deps,
+pub fn try_cancel_config(
+    mut deps: DepsMut,
     info: MessageInfo,
-    msg: Binary,
-    is_distribute: bool,
 ) -> Result<Response<TerraMsgWrapper>, ContractError> {
-    let config = get_config(deps).unwrap();
-    if config.owner != info.sender {
+    let config: Config = load_config(deps.as_ref())?;
+
+    // permission check
+    if info.sender != config.owner {
         return Err(ContractError::Unauthorized {});
     }
 
-    Ok(Response::new().add_submessage(SubMsg {
-        id: if is_distribute {
-            MSG_REPLY_ID_EXECUTE
-        } else {
-            MSG_REPLY_ID_EXECUTE_SKIP
-        },
-        gas_limit: None,
-        msg: from_binary(&msg).unwrap(),
-        reply_on: ReplyOn::Success,
-    }))
+    get_pending_config(deps.as_ref())?.ok_or(ContractError::NoPendingConfig {})?;
+    remove_pending_config(deps.branch());
+
+    Ok(Response::new().add_attribute("action", Action::CancelConfig.to_string()))
 }
 
-pub fn try_claim(
-    mut deps: DepsMut,
+pub fn try_set_pause(
+    deps: DepsMut,
     info: MessageInfo,
+    bond: Option<bool>,
+    unbond: Option<bool>,
+    execute: Option<bool>,
+    claim: Option<bool>,
 ) -> Result<Response<TerraMsgWrapper>, ContractError> {
-    let config = get_config(deps.as_ref()).unwrap();
-    if config.owner != info.sender {
+    let config: Config = load_config(deps.as_ref())?;
+
+    // permission check
+    if info.sender != config.owner && Some(info.sender.clone()) != config.guardian {
         return Err(ContractError::Unauthorized {});
     }
 
-    let dec_amount = sub_all_commission(deps.branch()).unwrap();
-    let amount = Uint256::one() * dec_amount;
+    // a shutdown is final
+    if is_shutdown(deps.as_ref())? {
+        return Err(ContractError::Shutdown {});
+    }
 
-    let asset = Asset {
-        amount: amount.into(),
-        info: config.asset_info,
-    };
+    let mut pause_state = get_pause_state(deps.as_ref())?;
+    let mut res: Vec<Attribute> = vec![Attribute::new("action", Action::SetPause.to_string())];
+
+    for (operation, flag, paused) in [
+        (Action::Bond, &mut pause_state.bond, bond),
+        (Action::Unbond, &mut pause_state.unbond, unbond),
+        (Action::Execute, &mut pause_state.execute, execute),
+        (Action::Claim, &mut pause_state.claim, claim),
+    ] {
+        if let Some(paused) = paused {
+            *flag = paused;
+            res.push(Attribute::new(operation.to_string(), paused.to_string()));
+        }
+    }
 
-    Ok(Response::new()
-        .add_message(CosmosMsg::Bank(BankMsg::Send {
-            to_address: info.sender.to_string(),
-            amount: vec![asset.deduct_tax(&deps.querier)?],
-        }))
-        .add_attribute("action", Action::Claim.to_string())
-        .add_attribute("claimer", info.sender)
-        .add_attribute("asset", asset.to_string()))
+    set_pause_state(deps, pause_state)?;
+
+    Ok(Response::new().add_attributes(res))
 }
 
-pub fn reply(
+pub fn try_shutdown(
     mut deps: DepsMut,
-    env: Env,
-    msg: Reply,
+    info: MessageInfo,
 ) -> Result<Response<TerraMsgWrapper>, ContractError> {
-    match msg.id {
-        MSG_REPLY_ID_TOKEN_INSTANT => {
-            // get new token's contract address
-            let res: MsgInstantiateContractResponse = Message::parse_from_bytes(
-                msg.result.unwrap().data.unwrap().as_slice(),
-            )
-            .map_err(|_| {
-                ContractError::Std(StdError::parse_err(
-                    "MsgInstantiateContractResponse",
-                    "failed to parse data",
-                ))
-            })?;
-
-            let mut config = get_config(deps.as_ref()).unwrap();
-            config.token_address = Some(Addr::unchecked(res.get_contract_address()));
-            set_config(deps, config).unwrap();
-
-            Ok(Response::new()
-                .add_attribute("reply", "token_instant")
-                .add_attribute("token_address", res.get_contract_address()))
-        }
-        MSG_REPLY_ID_EXECUTE | MSG_REPLY_ID_MUST_EXECUTE => {
-            let mut attrs: Vec<Attribute> = vec![attr("reply", "execute")];
-
-            let post_vaults = get_vaults(deps.as_ref()).unwrap();
+    let config: Config = load_config(deps.as_ref())?;
 
-            let config = get_config(deps.as_ref()).unwrap();
-
-            let ubalance = config
-                .asset_info
-                .query_pool(&deps.querier, deps.api, env.contract.address)
-                .unwrap();
-
-            let balance = Decimal256::from_uint256(Uint256::from(ubalance));
-
-            if balance > post_vaults {
-                let revenue = balance - post_vaults;
-                let commission = revenue * config.commission_rate;
-                add_commission(deps.branch(), commission).unwrap();
+    // permission check
+    if info.sender != config.owner && Some(info.sender.clone()) != config.guardian {
+        return Err(ContractError::Unauthorized {});
+    }
 
-                attrs.push(Attribute::new("result", "success"));
-                attrs.push(Attribute::new("revenue", revenue.to_string()));
-                attrs.push(Attribute::new("add_commission", commission.to_string()));
-            } else if msg.id == MSG_REPLY_ID_MUST_EXECUTE {
-                let loss = post_vaults - balance;
-                sub_commission(deps.branch(), loss).unwrap();
+    if is_shutdown(deps.as_ref())? {
+        return Err(ContractError::Shutdown {});
+    }
 
-                attrs.push(Attribute::new("result", "fail"));
-                attrs.push(Attribute::new("loss", loss.to_string()));
-            } else {
-                return Err(ContractError::FailedExecute(
-                    post_vaults.to_string(),
-                    balance.to_string(),
-                ));
-            }
+    set_shutdown(deps.branch())?;
 
-            set_vaults(deps.branch(), balance).unwrap();
+    let mut pause_state = get_pause_state(deps.as_ref())?;
+    pause_state.bond = true;
+    pause_state.unbond = true;
+    pause_state.execute = true;
+    set_pause_state(deps, pause_state)?;
 
-            Ok(Response::new().add_attributes(attrs))
-        }
-        MSG_REPLY_ID_EXECUTE_SKIP => Ok(Response::new().add_attribute("reply", "execute_skip")),
-        _ => Err(ContractError::InvalidReplyId {}),
-    }
+    Ok(Response::new()
+        .add_attribute("action", Action::Shutdown.to_string())
+        .add_attribute("sender", info.sender))
 }
 
-pub fn receive_cw20(
+pub fn try_update_executors(
     deps: DepsMut,
-    _env: Env,
     info: MessageInfo,
-    cw20_msg: Cw20ReceiveMsg,
+    add: Vec<String>,
+    remove: Vec<String>,
 ) -> Result<Response<TerraMsgWrapper>, ContractError> {
-    let contract_addr = info.sender;
-    match from_binary(&cw20_msg.msg) {
-        Ok(Cw20HookMsg::Bond {}) => {
-            let config: Config = get_config(deps.as_ref()).unwrap();
-            if contract_addr != config.asset_info.to_string() {
-                return Err(ContractError::Unauthorized {});
-            }
+    let mut config: Config = load_config(deps.as_ref())?;
 
-            let cw20_sender_addr = deps.api.addr_validate(&cw20_msg.sender)?;
-            try_bond(
-                deps,
-                cw20_sender_addr,
-                Asset {
-                    info: config.asset_info,
-                    amount: cw20_msg.amount,
-                },
-            )
-        }
-        Ok(Cw20HookMsg::Unbond {}) => {
-            // only asset contract can execute this message
-            let config: Config = get_config(deps.as_ref()).unwrap();
-            if contract_addr != config.token_address.unwrap() {
-                return Err(ContractError::Unauthorized {});
-            }
+    // permission check
+    if info.sender != config.owner {
+        return Err(ContractError::Unauthorized {});
+    }
 
-            let cw20_sender_addr = deps.api.addr_validate(&cw20_msg.sender)?;
-            try_unbond(
-                deps,
-                contract_addr,
-                config.asset_info,
-                cw20_sender_addr,
-                cw20_msg.amount,
-            )
+    let mut res: Vec<Attribute> = vec![Attribute::new(
+        "action",
+        Action::UpdateExecutors.to_string(),
+    )];
+
+    for addr in add {
+        let addr = deps.api.addr_validate(&addr)?;
+        if !config.executors.contains(&addr) {
+            config.executors.push(addr.clone());
         }
-        _ => Err(ContractError::InvalidHookMsg {}),
+        res.push(Attribute::new("add", addr));
     }
-}
 
-pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
-    match msg {
-        QueryMsg::Config {} => to_binary(&query_config(deps)),
-        QueryMsg::StakerInfo { staker_addr } => to_binary(&query_stake_info(deps, staker_addr)),
-        QueryMsg::Commission {} => to_binary(&query_commission(deps)),
-        QueryMsg::Rate {} => to_binary(&query_rate(deps)),
+    for addr in remove {
+        let addr = deps.api.addr_validate(&addr)?;
+        config.executors.retain(|executor| executor != &addr);
+        res.push(Attribute::new("remove", addr));
     }
-}
 
-pub fn query_config(deps: Deps) -> ConfigResponse {
-    let config = get_config(deps).unwrap();
+    set_config(deps, config)?;
 
-    ConfigResponse {
-        owner: config.owner.to_string(),
-        commission_rate: config.commission_rate,
+    Ok(Response::new().add_attributes(res))
+}
+
+/// Removals apply at once, additions wait for the config delay like the other changes stakers can
+/// not react to
+pub fn try_update_execute_targets(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    add: Vec<ExecuteTarget>,
+    remove: Vec<String>,
+) -> Result<Response<TerraMsgWrapper>, ContractError> {
+    let config: Config = load_config(deps.as_ref())?;
+
+    // permission check
+    if info.sender != config.owner {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let mut res: Vec<Attribute> = vec![Attribute::new(
+        "action",
+        Action::UpdateExecuteTargets.to_string(),
+    )];
+
+    let mut scheduled: Vec<ExecuteTarget> = vec![];
+    for target in add {
+        let target = ExecuteTarget {
+            contract_addr: deps.api.addr_validate(&target.contract_addr)?.to_string(),
+            ..target
+        };
+        if config.config_delay == 0 {
+            add_execute_target(deps.branch(), &target)?;
+            res.push(Attribute::new("add", target.contract_addr));
+        } else {
+            res.push(Attribute::new("pending_add", target.contract_addr.clone()));
+            scheduled.push(target);
+        }
+    }
+
+    for addr in remove {
+        let addr = deps.api.addr_validate(&addr)?;
+        remove_execute_target(deps.branch(), &addr);
+        res.push(Attribute::new("remove", addr));
+    }
+
+    if !scheduled.is_empty() {
+        // joins the scheduled config change, which waits for the new delay as a whole
+        let mut pending = get_pending_config(deps.as_ref())?.unwrap_or_default();
+        pending.execute_targets.append(&mut scheduled);
+        pending.release_at = env.block.time.seconds() + config.config_delay;
+        res.push(Attribute::new(
+            "pending_release_at",
+            pending.release_at.to_string(),
+        ));
+        set_pending_config(deps, pending)?;
+    }
+
+    Ok(Response::new().add_attributes(res))
+}
+
+fn add_execute_target(mut deps: DepsMut, target: &ExecuteTarget) -> StdResult<()> {
+    let addr = Addr::unchecked(&target.contract_addr);
+    set_execute_target(
+        deps.branch(),
+        &addr,
+        target.messages.clone().unwrap_or_default(),
+    )?;
+    set_allow_funds(deps, &addr, target.allow_funds.unwrap_or(false))
+}
+
+/// Owner or one of the executors
+pub fn assert_executor(deps: Deps, sender: &Addr) -> Result<(), ContractError> {
+    let config = load_config(deps)?;
+    if &config.owner != sender && !config.executors.contains(sender) {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    Ok(())
+}
+
+pub fn assert_not_paused(deps: Deps, operation: Action) -> Result<(), ContractError> {
+    let pause_state = get_pause_state(deps)?;
+
+    // pending unbonds and the commission can still be withdrawn after a shutdown
+    if is_shutdown(deps)? {
+        match operation {
+            Action::Bond | Action::Unbond | Action::Execute => {
+                return Err(ContractError::Shutdown {})
+            }
+            // the shutdown set the unbond flag, and it can not be cleared anymore
+            Action::WithdrawUnbonded => return Ok(()),
+            _ => {}
+        }
+    }
+
+    let paused = match operation {
+        Action::Bond => pause_state.bond,
+        Action::Unbond | Action::WithdrawUnbonded => pause_state.unbond,
+        Action::Execute => pause_state.execute,
+        Action::Claim => pause_state.claim,
+        _ => false,
+    };
+
+    if paused {
+        return Err(ContractError::Paused {
+            operation: operation.to_string(),
+        });
+    }
+
+    Ok(())
+}
+
+pub fn try_update_access_mode(
+    deps: DepsMut,
+    info: MessageInfo,
+    access_mode: AccessMode,
+) -> Result<Response<TerraMsgWrapper>, ContractError> {
+    let mut config: Config = load_config(deps.as_ref())?;
+
+    // permission check
+    if info.sender != config.owner {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    validate_access_mode(&access_mode)?;
+
+    let mode = match &access_mode {
+        AccessMode::Open => "open".to_string(),
+        AccessMode::Allowlist => "allowlist".to_string(),
+        AccessMode::MerkleRoot { root } => format!("merkle_root:{}", root),
+    };
+
+    config.access_mode = access_mode;
+    set_config(deps, config)?;
+
+    Ok(Response::new()
+        .add_attribute("action", Action::UpdateAccessMode.to_string())
+        .add_attribute("access_mode", mode))
+}
+
+pub fn try_update_allowlist(
+    mut deps: DepsMut,
+    info: MessageInfo,
+    add: Vec<String>,
+    remove: Vec<String>,
+) -> Result<Response<TerraMsgWrapper>, ContractError> {
+    let config: Config = load_config(deps.as_ref())?;
+
+    // permission check
+    if info.sender != config.owner {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let mut res: Vec<Attribute> = vec![Attribute::new(
+        "action",
+        Action::UpdateAllowlist.to_string(),
+    )];
+
+    for addr in add {
+        let addr = deps.api.addr_validate(&addr)?;
+        add_allowlist(deps.branch(), &addr)?;
+        res.push(Attribute::new("add", addr));
+    }
+
+    for addr in remove {
+        let addr = deps.api.addr_validate(&addr)?;
+        remove_allowlist(deps.branch(), &addr);
+        res.push(Attribute::new("remove", addr));
+    }
+
+    Ok(Response::new().add_attributes(res))
+}
+
+/// Config, `NotInitialized` until instantiate stored it
+pub fn load_config(deps: Deps) -> Result<Config, ContractError> {
+    get_config(deps).map_err(|err| match err {
+        StdError::NotFound { .. } => ContractError::NotInitialized {},
+        err => ContractError::Std(err),
+    })
+}
+
+/// Vaults token address, `TokenNotInstantiated` until the instantiate reply stored it
+pub fn vaults_token(config: &Config) -> Result<Addr, ContractError> {
+    config
+        .token_address
+        .clone()
+        .ok_or(ContractError::TokenNotInstantiated {})
+}
+
+pub fn to_uint128(value: Uint256) -> Result<Uint128, ContractError> {
+    if value > Uint256::from(u128::MAX) {
+        return Err(ContractError::Overflow(format!(
+            "{} does not fit in Uint128",
+            value
+        )));
+    }
+
+    Ok(value.into())
+}
+
+fn query_vaults_supply(deps: Deps, vaults_contract: Addr) -> Result<Uint256, ContractError> {
+    query_supply(deps, vaults_contract.clone())
+        .map_err(ContractError::query_failed(vaults_contract))
+}
+
+/// Balance the planet holds for the vaults
+pub fn query_vaults_balance(
+    deps: Deps,
+    contract_addr: Addr,
+    config: &Config,
+) -> Result<Decimal256, ContractError> {
+    let balance = config
+        .asset_info
+        .query_pool(&deps.querier, deps.api, contract_addr)
+        .map_err(ContractError::query_failed(&config.asset_info))?;
+
+    // pending unbonds are still held by the planet but no longer belong to the vaults
+    let unbonding = get_total_unbonding(deps)?;
+    let balance = Decimal256::from_uint256(Uint256::from(balance));
+    Ok(if balance > unbonding {
+        balance - unbonding
+    } else {
+        Decimal256::zero()
+    })
+}
+
+/// Vaults minus the accrued commission
+pub fn compute_total_assets(deps: Deps) -> Result<Decimal256, ContractError> {
+    Ok(vault_math::total_assets(
+        get_vaults(deps)?,
+        get_commission(deps)?,
+    )?)
+}
+
+pub fn compute_share_rate(deps: Deps, vaults_contract: Addr) -> Result<Decimal256, ContractError> {
+    let vaults_total_supply = query_vaults_supply(deps, vaults_contract)?;
+
+    Ok(vault_math::share_rate(
+        compute_total_assets(deps)?,
+        vaults_total_supply,
+    )?)
+}
+
+/// Vault tokens minted for `amount`, rounded down
+pub fn compute_mint_amount(
+    deps: Deps,
+    vaults_contract: Addr,
+    amount: Uint128,
+) -> Result<Uint256, ContractError> {
+    let vaults_total_supply = query_vaults_supply(deps, vaults_contract)?;
+
+    Ok(vault_math::shares_to_mint(
+        Uint256::from(amount),
+        compute_total_assets(deps)?,
+        vaults_total_supply,
+    )?)
+}
+
+/// Assets paid out for burning `amount` vault tokens, rounded down
+pub fn compute_redeem_amount(
+    deps: Deps,
+    vaults_contract: Addr,
+    amount: Uint128,
+) -> Result<Uint256, ContractError> {
+    let vaults_total_supply = query_vaults_supply(deps, vaults_contract)?;
+
+    Ok(vault_math::assets_to_redeem(
+        Uint256::from(amount),
+        compute_total_assets(deps)?,
+        vaults_total_supply,
+    )?)
+}
+
+/// Share rate, one until the vault token exists
+fn current_share_rate(deps: Deps, config: &Config) -> Result<Decimal256, ContractError> {
+    match config.token_address.clone() {
+        Some(token_address) => compute_share_rate(deps, token_address),
+        None => Ok(Decimal256::one()),
+    }
+}
+
+/// `recipient` when given, `sender` otherwise
+pub fn validate_recipient(deps: Deps, recipient: Option<String>, sender: &Addr) -> StdResult<Addr> {
+    match recipient {
+        Some(recipient) => deps.api.addr_validate(&recipient),
+        None => Ok(sender.clone()),
+    }
+}
+
+/// Fails with `SlippageExceeded` when `actual` is below the optional `minimum`
+pub fn assert_min_out(minimum: Option<Uint128>, actual: Uint128) -> Result<(), ContractError> {
+    match minimum {
+        Some(minimum) if actual < minimum => {
+            Err(ContractError::SlippageExceeded { minimum, actual })
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Mints shares for `asset` to `recipient`. The per user cap applies to the recipient, who holds
+/// the shares
+pub fn try_bond(
+    mut deps: DepsMut,
+    sender: Addr,
+    recipient: Addr,
+    asset: Asset,
+    min_shares_out: Option<Uint128>,
+) -> Result<Response<TerraMsgWrapper>, ContractError> {
+    let config = load_config(deps.as_ref())?;
+
+    let capacity = compute_deposit_capacity(deps.as_ref(), &config, Some(recipient.clone()))?;
+    for remaining in [capacity.total_remaining, capacity.user_remaining]
+        .iter()
+        .flatten()
+    {
+        if asset.amount > *remaining {
+            return Err(ContractError::DepositCapExceeded {
+                remaining: *remaining,
+            });
+        }
+    }
+
+    let token_contract = vaults_token(&config)?;
+    let mint_amount = compute_mint_amount(deps.as_ref(), token_contract.clone(), asset.amount)?;
+    if mint_amount.is_zero() {
+        return Err(ContractError::ZeroShares {});
+    }
+    let mint_amount = to_uint128(mint_amount)?;
+    assert_min_out(min_shares_out, mint_amount)?;
+
+    add_vaults(
+        deps.branch(),
+        Decimal256::from_uint256(Uint256::from(asset.amount)),
+    )?;
+    let new_depositor = add_staker_deposit(deps.branch(), &recipient, asset.amount, mint_amount)?;
+    add_bonded(deps, asset.amount, new_depositor)?;
+
+    Ok(Response::new()
+        .add_message(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: token_contract.to_string(),
+            funds: vec![],
+            msg: to_binary(&Cw20ExecuteMsg::Mint {
+                recipient: recipient.to_string(),
+                amount: mint_amount,
+            })?,
+        }))
+        .add_attribute("action", Action::Bond.to_string())
+        .add_attribute("bonder", sender)
+        .add_attribute("recipient", recipient)
+        .add_attribute("asset", asset.to_string())
+        .add_attribute("mint_amount", mint_amount))
+}
+
+/// Burns `amount` shares of `sender`. The asset, or the pending unbonding, goes to `recipient`
+#[allow(clippy::too_many_arguments)]
+pub fn try_unbond(
+    mut deps: DepsMut,
+    env: Env,
+    vaults_contract: Addr,
+    asset_info: AssetInfo,
+    sender: Addr,
+    recipient: Addr,
+    amount: Uint128,
+    min_assets_out: Option<Uint128>,
+) -> Result<Response<TerraMsgWrapper>, ContractError> {
+    let config = load_config(deps.as_ref())?;
+
+    let unbond_amount = compute_redeem_amount(deps.as_ref(), vaults_contract.clone(), amount)?;
+
+    let unbond_asset = Asset {
+        amount: to_uint128(unbond_amount)?,
+        info: asset_info,
+    };
+
+    // compared with what the recipient receives, as preview_unbond reports it
+    let tax = unbond_asset.compute_tax(&deps.querier)?;
+    assert_min_out(
+        min_assets_out,
+        unbond_asset
+            .amount
+            .checked_sub(tax)
+            .map_err(StdError::from)?,
+    )?;
+
+    sub_vaults(deps.branch(), Decimal256::from_uint256(unbond_amount))?;
+    add_staker_withdrawal(deps.branch(), &sender, unbond_asset.amount, amount)?;
+    add_unbonded(deps.branch(), unbond_asset.amount)?;
+
+    let burn_msg = CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr: vaults_contract.to_string(),
+        funds: vec![],
+        msg: to_binary(&Cw20ExecuteMsg::Burn { amount })?,
+    });
+
+    let res = if config.unbonding_period == 0 {
+        Response::new().add_messages(vec![
+            unbond_asset
+                .clone()
+                .into_msg(&deps.querier, recipient.clone())?,
+            burn_msg,
+        ])
+    } else {
+        let release_at = env
+            .block
+            .time
+            .plus_seconds(config.unbonding_period)
+            .seconds();
+
+        add_unbonding(
+            deps.branch(),
+            &recipient,
+            Unbonding {
+                amount: unbond_asset.amount,
+                release_at,
+            },
+        )?;
+
+        Response::new()
+            .add_message(burn_msg)
+            .add_attribute("release_at", release_at.to_string())
+    };
+
+    Ok(res
+        .add_attribute("action", Action::Unbond.to_string())
+        .add_attribute("unbonder", sender)
+        .add_attribute("recipient", recipient)
+        .add_attribute("asset", unbond_asset.to_string())
+        .add_attribute("burn_amount", amount))
+}
+
+pub fn try_withdraw_unbonded(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+) -> Result<Response<TerraMsgWrapper>, ContractError> {
+    let config = load_config(deps.as_ref())?;
+
+    let amount = withdraw_unbondings(deps.branch(), &info.sender, env.block.time.seconds())?;
+    if amount.is_zero() {
+        return Err(ContractError::NothingToWithdraw {});
+    }
+
+    let asset = Asset {
+        amount,
+        info: config.asset_info,
+    };
+
+    Ok(Response::new()
+        .add_message(asset.clone().into_msg(&deps.querier, info.sender.clone())?)
+        .add_attribute("action", Action::WithdrawUnbonded.to_string())
+        .add_attribute("withdrawer", info.sender)
+        .add_attribute("asset", asset.to_string()))
+}
+
+/// Decodes base64 `CosmosMsg`s, each limited to the execute targets
+pub fn parse_execute_msgs(
+    deps: Deps,
+    msgs: &[Binary],
+) -> Result<Vec<CosmosMsg<TerraMsgWrapper>>, ContractError> {
+    msgs.iter()
+        .map(|msg| {
+            let msg: CosmosMsg<TerraMsgWrapper> = from_binary(msg)?;
+            assert_execute_allowed(deps, &msg)?;
+            Ok(msg)
+        })
+        .collect()
+}
+
+pub fn try_execute(
+    deps: DepsMut,
+    info: MessageInfo,
+    msgs: Vec<Binary>,
+    is_distribute: bool,
+    max_loss: Option<Uint128>,
+    min_profit: Option<Uint128>,
+) -> Result<Response<TerraMsgWrapper>, ContractError> {
+    assert_executor(deps.as_ref(), &info.sender)?;
+
+    let mut msgs = parse_execute_msgs(deps.as_ref(), &msgs)?;
+
+    // only the last step replies, so the accounting runs once after the whole chain.
+    // earlier steps have no reply and any failure reverts the entire batch
+    let last = msgs.pop().ok_or(ContractError::EmptyExecute {})?;
+
+    // must-execute always settles, booking a bounded loss instead of failing.
+    // a min profit needs the settlement as well
+    let id = match max_loss {
+        Some(_) => MSG_REPLY_ID_MUST_EXECUTE,
+        None if is_distribute || min_profit.is_some() => MSG_REPLY_ID_EXECUTE,
+        None => MSG_REPLY_ID_EXECUTE_SKIP,
+    };
+
+    if id != MSG_REPLY_ID_EXECUTE_SKIP {
+        set_tmp_execute(
+            deps,
+            TmpExecute {
+                max_loss: max_loss.map(Decimal256::from_uint256),
+                min_profit: min_profit.map(Decimal256::from_uint256),
+            },
+        )?;
+    }
+
+    Ok(Response::new().add_messages(msgs).add_submessage(SubMsg {
+        id,
+        gas_limit: None,
+        msg: last,
+        reply_on: ReplyOn::Success,
+    }))
+}
+
+pub fn try_claim(
+    mut deps: DepsMut,
+    info: MessageInfo,
+) -> Result<Response<TerraMsgWrapper>, ContractError> {
+    let config = load_config(deps.as_ref())?;
+    if config.owner != info.sender {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let dec_amount = sub_all_commission(deps.branch())?;
+    let amount = Uint256::one() * dec_amount;
+
+    // the vaults count the commission, only the paid part leaves them
+    sub_vaults(deps.branch(), Decimal256::from_uint256(amount))?;
+
+    let asset = Asset {
+        amount: to_uint128(amount)?,
+        info: config.asset_info,
+    };
+    add_commission_claimed(deps.branch(), asset.amount)?;
+
+    Ok(Response::new()
+        .add_message(asset.clone().into_msg(&deps.querier, info.sender.clone())?)
+        .add_attribute("action", Action::Claim.to_string())
+        .add_attribute("claimer", info.sender)
+        .add_attribute("asset", asset.to_string()))
+}
+
+/// Everything the planet holds that belongs to the holders: the planet asset without the pending
+/// unbonds and the accrued commission, and any other native balance
+pub fn query_emergency_holdings(
+    deps: Deps,
+    contract_addr: Addr,
+    config: &Config,
+) -> Result<Vec<Asset>, ContractError> {
+    let mut infos = vec![config.asset_info.clone()];
+    for coin in deps.querier.query_all_balances(&contract_addr)? {
+        let info = AssetInfo::NativeToken { denom: coin.denom };
+        if !infos.contains(&info) {
+            infos.push(info);
+        }
+    }
+
+    let reserved = vault_math::checked_add(get_total_unbonding(deps)?, get_commission(deps)?)?;
+    let reserved = to_uint128(Uint256::one() * reserved)?;
+
+    infos
+        .into_iter()
+        .map(|info| {
+            let mut amount = info
+                .query_pool(&deps.querier, deps.api, contract_addr.clone())
+                .map_err(ContractError::query_failed(&info))?;
+            if info == config.asset_info {
+                amount = amount.saturating_sub(reserved);
+            }
+
+            Ok(Asset { info, amount })
+        })
+        .collect()
+}
+
+/// Pays out by balance, so it holds up when the share rate or the strategy can not be trusted
+pub fn try_emergency_withdraw(
+    mut deps: DepsMut,
+    env: Env,
+    vaults_contract: Addr,
+    sender: Addr,
+    shares: Uint128,
+) -> Result<Response<TerraMsgWrapper>, ContractError> {
+    if !is_shutdown(deps.as_ref())? {
+        return Err(ContractError::NotShutdown {});
+    }
+
+    let config = load_config(deps.as_ref())?;
+    let supply = query_vaults_supply(deps.as_ref(), vaults_contract.clone())?;
+    let shares_256 = Uint256::from(shares);
+
+    let mut assets: Vec<Asset> = vec![];
+    for asset in query_emergency_holdings(deps.as_ref(), env.contract.address, &config)? {
+        let amount = vault_math::pro_rata(Uint256::from(asset.amount), shares_256, supply)?;
+        if !amount.is_zero() {
+            assets.push(Asset {
+                amount: to_uint128(amount)?,
+                info: asset.info,
+            });
+        }
+    }
+
+    // keep the vaults in step for the queries that still read them
+    let total_assets = compute_total_assets(deps.as_ref())?;
+    let withdrawn = vault_math::pro_rata(Uint256(total_assets.0), shares_256, supply)?;
+    sub_vaults(deps.branch(), Decimal256(withdrawn.0))?;
+
+    let mut res = Response::new().add_message(CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr: vaults_contract.to_string(),
+        funds: vec![],
+        msg: to_binary(&Cw20ExecuteMsg::Burn { amount: shares })?,
+    }));
+    for asset in assets.iter() {
+        res = res.add_message(asset.clone().into_msg(&deps.querier, sender.clone())?);
+    }
+
+    Ok(res
+        .add_attribute("action", Action::EmergencyWithdraw.to_string())
+        .add_attribute("withdrawer", sender)
+        .add_attribute("shares", shares)
+        .add_attribute(
+            "assets",
+            assets
+                .iter()
+                .map(|asset| asset.to_string())
+                .collect::<Vec<String>>()
+                .join(","),
+        ))
+}
+
+pub fn try_sync<S: Strategy>(
+    strategy: &S,
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    absorb_shortfall: Option<bool>,
+) -> Result<Response<TerraMsgWrapper>, ContractError> {
+    let config = load_config(deps.as_ref())?;
+    let absorb_shortfall = absorb_shortfall.unwrap_or(false);
+
+    // permission check
+    if absorb_shortfall {
+        if config.owner != info.sender {
+            return Err(ContractError::Unauthorized {});
+        }
+    } else {
+        assert_executor(deps.as_ref(), &info.sender)?;
+    }
+
+    let tracked = get_vaults(deps.as_ref())?;
+    let actual = strategy.total_assets(deps.as_ref(), &env)?;
+
+    let mut attrs: Vec<Attribute> = vec![attr("action", Action::Sync.to_string())];
+
+    if actual > tracked {
+        let revenue = actual - tracked;
+        let commission = revenue * config.commission_rate;
+        add_commission(deps.branch(), commission)?;
+        add_synced(deps.branch(), revenue, Decimal256::zero(), commission)?;
+        set_vaults(deps.branch(), actual)?;
+
+        attrs.push(attr("result", "surplus"));
+        attrs.push(attr("revenue", revenue.to_string()));
+        attrs.push(attr("add_commission", commission.to_string()));
+    } else if tracked > actual {
+        let shortfall = tracked - actual;
+        if absorb_shortfall {
+            // same as a must-execute loss, the commission absorbs it first
+            let accrued = get_commission(deps.as_ref())?;
+            let commission_loss = if shortfall > accrued {
+                accrued
+            } else {
+                shortfall
+            };
+            sub_commission(deps.branch(), commission_loss)?;
+            add_synced(
+                deps.branch(),
+                Decimal256::zero(),
+                shortfall,
+                Decimal256::zero(),
+            )?;
+            set_vaults(deps.branch(), actual)?;
+
+            attrs.push(attr("result", "absorbed"));
+            attrs.push(attr("loss", shortfall.to_string()));
+            attrs.push(attr("commission_loss", commission_loss.to_string()));
+            attrs.push(attr(
+                "staker_loss",
+                (shortfall - commission_loss).to_string(),
+            ));
+        } else {
+            attrs.push(attr("result", "shortfall"));
+            attrs.push(attr("shortfall", shortfall.to_string()));
+        }
+    } else {
+        attrs.push(attr("result", "in_sync"));
+    }
+
+    Ok(Response::new().add_attributes(attrs))
+}
+
+/// Adds assets moved in for the holders of an adopted vaults token to the vaults.
+/// Unlike a sync surplus it is principal, not revenue
+/// Books the backing of an adopted vaults token once, and resumes what the adoption paused
+pub fn try_book_backing<S: Strategy>(
+    strategy: &S,
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    amount: Uint128,
+) -> Result<Response<TerraMsgWrapper>, ContractError> {
+    let config = load_config(deps.as_ref())?;
+
+    // permission check
+    if config.owner != info.sender {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    // any other untracked balance is revenue, booked by sync
+    if !is_backing_pending(deps.as_ref())? {
+        return Err(ContractError::NoPendingBacking {});
+    }
+
+    let tracked = get_vaults(deps.as_ref())?;
+    let actual = strategy.total_assets(deps.as_ref(), &env)?;
+    let surplus = if actual > tracked {
+        actual - tracked
+    } else {
+        Decimal256::zero()
+    };
+
+    let amount = Decimal256::from_uint256(Uint256::from(amount));
+    if amount > surplus {
+        return Err(ContractError::BackingExceeded { amount, surplus });
+    }
+
+    add_vaults(deps.branch(), amount)?;
+    set_backing_pending(deps.branch(), false)?;
+
+    let mut pause_state = get_pause_state(deps.as_ref())?;
+    pause_state.bond = false;
+    pause_state.unbond = false;
+    pause_state.execute = false;
+    set_pause_state(deps, pause_state)?;
+
+    Ok(Response::new()
+        .add_attribute("action", Action::BookBacking.to_string())
+        .add_attribute("amount", amount.to_string()))
+}
+
+pub fn reply(
+    deps: DepsMut,
+    env: Env,
+    msg: Reply,
+) -> Result<Response<TerraMsgWrapper>, ContractError> {
+    reply_with(&Hold, deps, env, msg)
+}
+
+pub fn reply_with<S: Strategy>(
+    strategy: &S,
+    mut deps: DepsMut,
+    env: Env,
+    msg: Reply,
+) -> Result<Response<TerraMsgWrapper>, ContractError> {
+    match msg.id {
+        id if STRATEGY_REPLY_IDS.contains(&id) => strategy.on_reply(deps, env, msg),
+        id if !PLANET_REPLY_IDS.contains(&id) => Err(ContractError::InvalidReplyId {}),
+        MSG_REPLY_ID_TOKEN_INSTANT => {
+            // get new token's contract address
+            let data = msg
+                .result
+                .into_result()
+                .map_err(StdError::generic_err)?
+                .data
+                .ok_or_else(|| {
+                    ContractError::FailedToParse("MsgInstantiateContractResponse".to_string())
+                })?;
+            let res: MsgInstantiateContractResponse = Message::parse_from_bytes(data.as_slice())
+                .map_err(|_| {
+                    ContractError::Std(StdError::parse_err(
+                        "MsgInstantiateContractResponse",
+                        "failed to parse data",
+                    ))
+                })?;
+
+            let mut config = load_config(deps.as_ref())?;
+            if let Some(token_address) = config.token_address {
+                return Err(ContractError::AlreadyInstantiate(token_address.to_string()));
+            }
+            config.token_address = Some(Addr::unchecked(res.get_contract_address()));
+            set_config(deps, config)?;
+
+            Ok(Response::new()
+                .add_attribute("reply", "token_instant")
+                .add_attribute("token_address", res.get_contract_address()))
+        }
+        MSG_REPLY_ID_EXECUTE | MSG_REPLY_ID_MUST_EXECUTE => {
+            let mut attrs: Vec<Attribute> = vec![attr("reply", "execute")];
+
+            let tmp_execute = get_tmp_execute(deps.as_ref())?;
+            remove_tmp_execute(deps.branch());
+
+            let post_vaults = get_vaults(deps.as_ref())?;
+
+            let config = load_config(deps.as_ref())?;
+
+            let rate_before = current_share_rate(deps.as_ref(), &config)?;
+
+            let balance = strategy.total_assets(deps.as_ref(), &env)?;
+
+            let profit = if balance > post_vaults {
+                balance - post_vaults
+            } else {
+                Decimal256::zero()
+            };
+            if let Some(min_profit) = tmp_execute.min_profit {
+                if profit < min_profit {
+                    return Err(ContractError::ProfitBelowMinimum { profit, min_profit });
+                }
+            }
+
+            let mut revenue = Decimal256::zero();
+            let mut loss = Decimal256::zero();
+            let mut commission = Decimal256::zero();
+
+            if balance > post_vaults {
+                revenue = balance - post_vaults;
+                commission = revenue * config.commission_rate;
+                add_commission(deps.branch(), commission)?;
+
+                attrs.push(Attribute::new("result", "success"));
+                attrs.push(Attribute::new("revenue", revenue.to_string()));
+                attrs.push(Attribute::new("add_commission", commission.to_string()));
+            } else if msg.id == MSG_REPLY_ID_MUST_EXECUTE {
+                loss = post_vaults - balance;
+                if let Some(max_loss) = tmp_execute.max_loss {
+                    if loss > max_loss {
+                        return Err(ContractError::MaxLossExceeded { loss, max_loss });
+                    }
+                }
+
+                // the commission absorbs the loss first, the rest lowers the share rate
+                let accrued = get_commission(deps.as_ref())?;
+                let commission_loss = if loss > accrued { accrued } else { loss };
+                sub_commission(deps.branch(), commission_loss)?;
+
+                attrs.push(Attribute::new("result", "fail"));
+                attrs.push(Attribute::new("loss", loss.to_string()));
+                attrs.push(Attribute::new(
+                    "commission_loss",
+                    commission_loss.to_string(),
+                ));
+                attrs.push(Attribute::new(
+                    "staker_loss",
+                    (loss - commission_loss).to_string(),
+                ));
+            } else {
+                return Err(ContractError::FailedExecute(
+                    post_vaults.to_string(),
+                    balance.to_string(),
+                ));
+            }
+
+            set_vaults(deps.branch(), balance)?;
+
+            let rate_after = current_share_rate(deps.as_ref(), &config)?;
+            if let Some(max_rate_change) = config.max_rate_change {
+                let change = if rate_after > rate_before {
+                    rate_after - rate_before
+                } else {
+                    rate_before - rate_after
+                };
+
+                if !rate_before.is_zero() && change / rate_before > max_rate_change {
+                    return Err(ContractError::RateChangeExceeded {
+                        before: rate_before,
+                        after: rate_after,
+                        max_rate_change,
+                    });
+                }
+            }
+
+            let mut execution = Execution {
+                id: 0,
+                height: env.block.height,
+                time: env.block.time.seconds(),
+                revenue,
+                loss,
+                commission,
+                rate_before,
+                rate_after,
+            };
+            execution.id = add_execution(deps.branch(), execution.clone())?;
+
+            let res = Response::new()
+                .add_attributes(attrs)
+                .add_attribute("execution_id", execution.id.to_string());
+            strategy.on_execute_reply(deps, &env, &execution, res)
+        }
+        MSG_REPLY_ID_EXECUTE_SKIP => Ok(Response::new().add_attribute("reply", "execute_skip")),
+        _ => Err(ContractError::InvalidReplyId {}),
+    }
+}
+
+pub fn receive_cw20<S: Strategy>(
+    strategy: &S,
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    cw20_msg: Cw20ReceiveMsg,
+) -> Result<Response<TerraMsgWrapper>, ContractError> {
+    let contract_addr = info.sender;
+    match from_binary(&cw20_msg.msg) {
+        Ok(Cw20HookMsg::Bond {
+            proof,
+            recipient,
+            min_shares_out,
+        }) => {
+            assert_not_paused(deps.as_ref(), Action::Bond)?;
+
+            let config: Config = load_config(deps.as_ref())?;
+            if contract_addr != config.asset_info.to_string() {
+                return Err(ContractError::Unauthorized {});
+            }
+
+            let cw20_sender_addr = deps.api.addr_validate(&cw20_msg.sender)?;
+            assert_allowed(deps.as_ref(), &cw20_sender_addr, &proof)?;
+            let recipient = validate_recipient(deps.as_ref(), recipient, &cw20_sender_addr)?;
+            strategy.on_bond(
+                deps,
+                env,
+                cw20_sender_addr,
+                recipient,
+                Asset {
+                    info: config.asset_info,
+                    amount: cw20_msg.amount,
+                },
+                min_shares_out,
+            )
+        }
+        Ok(Cw20HookMsg::Unbond {
+            recipient,
+            min_assets_out,
+        }) => {
+            assert_not_paused(deps.as_ref(), Action::Unbond)?;
+
+            // only asset contract can execute this message
+            let config: Config = load_config(deps.as_ref())?;
+            if contract_addr != vaults_token(&config)? {
+                return Err(ContractError::Unauthorized {});
+            }
+
+            let cw20_sender_addr = deps.api.addr_validate(&cw20_msg.sender)?;
+            let recipient = validate_recipient(deps.as_ref(), recipient, &cw20_sender_addr)?;
+            strategy.on_unbond(
+                deps,
+                env,
+                contract_addr,
+                cw20_sender_addr,
+                recipient,
+                cw20_msg.amount,
+                min_assets_out,
+            )
+        }
+        Ok(Cw20HookMsg::EmergencyWithdraw {}) => {
+            // only asset contract can execute this message
+            let config: Config = load_config(deps.as_ref())?;
+            if contract_addr != vaults_token(&config)? {
+                return Err(ContractError::Unauthorized {});
+            }
+
+            let cw20_sender_addr = deps.api.addr_validate(&cw20_msg.sender)?;
+            try_emergency_withdraw(deps, env, contract_addr, cw20_sender_addr, cw20_msg.amount)
+        }
+        _ => Err(ContractError::InvalidHookMsg {}),
+    }
+}
+
+pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
+    query_with(&Hold, deps, env, msg)
+}
+
+pub fn query_with<S: Strategy>(
+    strategy: &S,
+    deps: Deps,
+    env: Env,
+    msg: QueryMsg,
+) -> StdResult<Binary> {
+    if let Some(res) = strategy.extra_queries(deps, &env, &msg) {
+        return res;
+    }
+
+    match msg {
+        QueryMsg::Config {} => to_binary(&query_config(deps)?),
+        QueryMsg::StakerInfo { staker_addr } => to_binary(&query_stake_info(deps, staker_addr)?),
+        QueryMsg::Commission {} => to_binary(&query_commission(deps)?),
+        QueryMsg::Rate {} => to_binary(&query_rate(deps)?),
+        QueryMsg::PendingConfig {} => to_binary(&query_pending_config(deps)?),
+        QueryMsg::PendingUnbonds { address } => {
+            to_binary(&query_pending_unbonds(deps, env, address)?)
+        }
+        QueryMsg::DepositCapacity { address } => to_binary(&query_deposit_capacity(deps, address)?),
+        QueryMsg::IsAllowed { address, proof } => {
+            to_binary(&query_is_allowed(deps, address, proof)?)
+        }
+        QueryMsg::PauseState {} => to_binary(&query_pause_state(deps)?),
+        QueryMsg::ExecuteTarget { contract_addr } => {
+            to_binary(&query_execute_target(deps, contract_addr)?)
+        }
+        QueryMsg::Executions { start_after, limit } => {
+            to_binary(&query_executions(deps, start_after, limit)?)
+        }
+        QueryMsg::Stats {} => to_binary(&query_stats(deps)?),
+        QueryMsg::Health {} => to_binary(&query_health(strategy, deps, env)?),
+        QueryMsg::PreviewBond { asset } => to_binary(&query_preview_bond(deps, asset)?),
+        QueryMsg::PreviewUnbond { shares } => to_binary(&query_preview_unbond(deps, shares)?),
+        QueryMsg::ConvertToShares { amount } => to_binary(&query_convert_to_shares(deps, amount)?),
+        QueryMsg::ConvertToAssets { shares } => to_binary(&query_convert_to_assets(deps, shares)?),
+        QueryMsg::TotalAssets {} => to_binary(&query_total_assets(deps)?),
+        QueryMsg::MaxBond { address, proof } => to_binary(&query_max_bond(deps, address, proof)?),
+        QueryMsg::MaxUnbond { address } => to_binary(&query_max_unbond(deps, address)?),
+    }
+}
+
+pub fn query_config(deps: Deps) -> StdResult<ConfigResponse> {
+    let config = load_config(deps)?;
+
+    Ok(ConfigResponse {
+        owner: config.owner.to_string(),
+        commission_rate: config.commission_rate,
         asset_info: config.asset_info,
         token_code_id: config.token_code_id,
         token_address: match config.token_address {
             Some(token_address) => token_address.to_string(),
             None => String::default(),
         },
+        unbonding_period: config.unbonding_period,
+        max_total_deposit: config.max_total_deposit,
+        max_deposit_per_user: config.max_deposit_per_user,
+        access_mode: config.access_mode,
+        guardian: config.guardian.map(|guardian| guardian.to_string()),
+        executors: config
+            .executors
+            .iter()
+            .map(|executor| executor.to_string())
+            .collect(),
+        max_rate_change: config.max_rate_change,
+        config_delay: config.config_delay,
+    })
+}
+
+pub fn query_pending_config(deps: Deps) -> StdResult<PendingConfigResponse> {
+    Ok(match get_pending_config(deps)? {
+        Some(pending) => PendingConfigResponse {
+            owner: pending.owner.map(|owner| owner.to_string()),
+            commission_rate: pending.commission_rate,
+            config_delay: pending.config_delay,
+            execute_targets: pending.execute_targets,
+            release_at: Some(pending.release_at),
+        },
+        None => PendingConfigResponse {
+            owner: None,
+            commission_rate: None,
+            config_delay: None,
+            execute_targets: vec![],
+            release_at: None,
+        },
+    })
+}
+
+pub fn query_stake_info(deps: Deps, staker_addr: String) -> StdResult<StakerInfoResponse> {
+    let config = load_config(deps)?;
+
+    let staker = Addr::unchecked(staker_addr);
+    let vaults_token_address = vaults_token(&config)?;
+
+    let balance = query_token_balance(&deps.querier, vaults_token_address.clone(), staker.clone())
+        .unwrap_or_else(|_| Uint128::zero());
+
+    let amount = to_uint128(compute_redeem_amount(deps, vaults_token_address, balance)?)?;
+
+    staker_info(
+        Asset {
+            info: config.asset_info,
+            amount,
+        },
+        balance,
+        &get_staker_basis(deps, &staker)?,
+    )
+}
+
+/// Values `shares` worth `asset` against the basis they were bonded at
+pub fn staker_info(
+    asset: Asset,
+    shares: Uint128,
+    basis: &StakerBasis,
+) -> StdResult<StakerInfoResponse> {
+    // shares above the tracked ones came in by transfer and cost nothing
+    let cost_basis = if basis.shares.is_zero() {
+        Uint128::zero()
+    } else {
+        basis
+            .cost
+            .multiply_ratio(shares.min(basis.shares), basis.shares)
+    };
+
+    Ok(StakerInfoResponse {
+        deposited: basis.deposited,
+        withdrawn: basis.withdrawn,
+        cost_basis,
+        realized_pnl: pnl(basis.withdrawn.checked_add(basis.cost)?, basis.deposited),
+        unrealized_pnl: pnl(asset.amount, cost_basis),
+        asset,
+    })
+}
+
+fn pnl(value: Uint128, cost: Uint128) -> Pnl {
+    Pnl {
+        profit: value.saturating_sub(cost),
+        loss: cost.saturating_sub(value),
+    }
+}
+
+pub fn query_commission(deps: Deps) -> StdResult<CommissionResponse> {
+    let config = load_config(deps)?;
+
+    let commission = get_commission(deps)?;
+
+    Ok(CommissionResponse {
+        asset: Asset {
+            info: config.asset_info,
+            amount: to_uint128(Uint256::one() * commission)?,
+        },
+    })
+}
+
+fn query_rate(deps: Deps) -> StdResult<RateResponse> {
+    let config = load_config(deps)?;
+
+    let rate = compute_share_rate(deps, vaults_token(&config)?)?;
+
+    Ok(RateResponse { rate })
+}
+
+pub fn compute_deposit_capacity(
+    deps: Deps,
+    config: &Config,
+    depositor: Option<Addr>,
+) -> Result<DepositCapacityResponse, ContractError> {
+    let total_remaining = match config.max_total_deposit {
+        Some(max_total_deposit) => {
+            let vaults = to_uint128(Uint256::one() * get_vaults(deps)?)?;
+            Some(max_total_deposit.saturating_sub(vaults))
+        }
+        None => None,
+    };
+
+    let user_remaining = match (config.max_deposit_per_user, depositor) {
+        (Some(max_deposit_per_user), Some(depositor)) => {
+            let vaults_contract = vaults_token(config)?;
+            let balance = query_token_balance(&deps.querier, vaults_contract.clone(), depositor)
+                .map_err(ContractError::query_failed(&vaults_contract))?;
+            let value = to_uint128(compute_redeem_amount(deps, vaults_contract, balance)?)?;
+
+            Some(max_deposit_per_user.saturating_sub(value))
+        }
+        _ => None,
+    };
+
+    Ok(DepositCapacityResponse {
+        total_remaining,
+        user_remaining,
+    })
+}
+
+pub fn query_deposit_capacity(
+    deps: Deps,
+    address: Option<String>,
+) -> StdResult<DepositCapacityResponse> {
+    let config = load_config(deps)?;
+    let depositor = match address {
+        Some(address) => Some(deps.api.addr_validate(&address)?),
+        None => None,
+    };
+
+    Ok(compute_deposit_capacity(deps, &config, depositor)?)
+}
+
+pub fn query_pause_state(deps: Deps) -> StdResult<PauseStateResponse> {
+    let pause_state = get_pause_state(deps)?;
+
+    Ok(PauseStateResponse {
+        bond: pause_state.bond,
+        unbond: pause_state.unbond,
+        execute: pause_state.execute,
+        claim: pause_state.claim,
+        shutdown: is_shutdown(deps)?,
+    })
+}
+
+pub fn query_execute_target(deps: Deps, contract_addr: String) -> StdResult<ExecuteTargetResponse> {
+    let contract_addr = deps.api.addr_validate(&contract_addr)?;
+
+    Ok(match get_execute_target(deps, &contract_addr)? {
+        Some(messages) => ExecuteTargetResponse {
+            allowed: true,
+            messages: if messages.is_empty() {
+                None
+            } else {
+                Some(messages)
+            },
+            allow_funds: allows_funds(deps, &contract_addr)?,
+        },
+        None => ExecuteTargetResponse {
+            allowed: false,
+            messages: None,
+            allow_funds: false,
+        },
+    })
+}
+
+pub fn query_executions(
+    deps: Deps,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<ExecutionsResponse> {
+    let totals = get_execution_totals(deps)?;
+
+    Ok(ExecutionsResponse {
+        executions: load_executions(deps, start_after, limit)?,
+        total_count: totals.count,
+        total_revenue: totals.revenue,
+        total_loss: totals.loss,
+        total_commission: totals.commission,
+    })
+}
+
+pub fn query_stats(deps: Deps) -> StdResult<StatsResponse> {
+    let totals = get_execution_totals(deps)?;
+    let synced = get_sync_totals(deps)?;
+    let stats = get_stats(deps)?;
+
+    Ok(StatsResponse {
+        total_bonded: stats.bonded,
+        total_unbonded: stats.unbonded,
+        total_revenue: totals.revenue,
+        total_commission: totals.commission,
+        total_commission_claimed: stats.commission_claimed,
+        total_loss: totals.loss,
+        execute_count: totals.count,
+        sync_count: synced.count,
+        synced_revenue: synced.revenue,
+        synced_commission: synced.commission,
+        synced_loss: synced.loss,
+        depositor_count: stats.depositors,
+        first_execution_height: stats.first_execution_height,
+        last_execution_height: stats.last_execution_height,
+    })
+}
+
+pub fn query_health<S: Strategy>(strategy: &S, deps: Deps, env: Env) -> StdResult<HealthResponse> {
+    let tracked = get_vaults(deps)?;
+    let actual = strategy.total_assets(deps, &env)?;
+    let synced = get_sync_totals(deps)?;
+
+    Ok(HealthResponse {
+        tracked,
+        actual,
+        surplus: if actual > tracked {
+            actual - tracked
+        } else {
+            Decimal256::zero()
+        },
+        shortfall: if tracked > actual {
+            tracked - actual
+        } else {
+            Decimal256::zero()
+        },
+        synced_revenue: synced.revenue,
+        synced_loss: synced.loss,
+    })
+}
+
+/// The sender pays the tax of a native bond on top of the funds, so the planet mints on the full
+/// amount
+pub fn query_preview_bond(deps: Deps, asset: Asset) -> StdResult<PreviewBondResponse> {
+    let config = load_config(deps)?;
+    if asset.info != config.asset_info {
+        return Err(StdError::generic_err(format!(
+            "{} is not the bond asset",
+            asset.info
+        )));
+    }
+
+    let shares = compute_mint_amount(deps, vaults_token(&config)?, asset.amount)?;
+
+    Ok(PreviewBondResponse {
+        shares: to_uint128(shares)?,
+        tax: Uint128::zero(),
+    })
+}
+
+pub fn query_preview_unbond(deps: Deps, shares: Uint128) -> StdResult<PreviewUnbondResponse> {
+    let config = load_config(deps)?;
+
+    let amount = to_uint128(compute_redeem_amount(deps, vaults_token(&config)?, shares)?)?;
+    let asset = Asset {
+        info: config.asset_info,
+        amount,
+    };
+    let tax = asset.compute_tax(&deps.querier)?;
+
+    Ok(PreviewUnbondResponse {
+        asset: Asset {
+            info: asset.info,
+            amount: amount.checked_sub(tax)?,
+        },
+        tax,
+    })
+}
+
+pub fn query_convert_to_shares(deps: Deps, amount: Uint128) -> StdResult<ConvertToSharesResponse> {
+    let config = load_config(deps)?;
+    let shares = compute_mint_amount(deps, vaults_token(&config)?, amount)?;
+
+    Ok(ConvertToSharesResponse {
+        shares: to_uint128(shares)?,
+    })
+}
+
+pub fn query_convert_to_assets(deps: Deps, shares: Uint128) -> StdResult<ConvertToAssetsResponse> {
+    let config = load_config(deps)?;
+    let amount = compute_redeem_amount(deps, vaults_token(&config)?, shares)?;
+
+    Ok(ConvertToAssetsResponse {
+        asset: Asset {
+            info: config.asset_info,
+            amount: to_uint128(amount)?,
+        },
+    })
+}
+
+pub fn query_total_assets(deps: Deps) -> StdResult<TotalAssetsResponse> {
+    let config = load_config(deps)?;
+    let total_assets = compute_total_assets(deps)?;
+
+    Ok(TotalAssetsResponse {
+        asset: Asset {
+            info: config.asset_info,
+            amount: to_uint128(Uint256::one() * total_assets)?,
+        },
+    })
+}
+
+/// Zero while bond is paused or before the vaults token exists, otherwise the tighter deposit cap
+/// Zero when the bond would be refused whatever the amount: paused, or `address` not allowed
+pub fn query_max_bond(
+    deps: Deps,
+    address: String,
+    proof: Option<Vec<String>>,
+) -> StdResult<MaxBondResponse> {
+    let address = deps.api.addr_validate(&address)?;
+    let config = load_config(deps)?;
+
+    if config.token_address.is_none()
+        || get_pause_state(deps)?.bond
+        || !is_allowed(deps, &address, &proof)?
+    {
+        return Ok(MaxBondResponse {
+            amount: Some(Uint128::zero()),
+        });
+    }
+
+    let capacity = compute_deposit_capacity(deps, &config, Some(address))?;
+
+    Ok(MaxBondResponse {
+        amount: [capacity.total_remaining, capacity.user_remaining]
+            .iter()
+            .flatten()
+            .min()
+            .copied(),
+    })
+}
+
+pub fn query_max_unbond(deps: Deps, address: String) -> StdResult<MaxUnbondResponse> {
+    let address = deps.api.addr_validate(&address)?;
+    let config = load_config(deps)?;
+
+    if get_pause_state(deps)?.unbond {
+        return Ok(MaxUnbondResponse {
+            shares: Uint128::zero(),
+        });
+    }
+
+    let vaults_contract = vaults_token(&config)?;
+    let shares = query_token_balance(&deps.querier, vaults_contract.clone(), address)
+        .map_err(ContractError::query_failed(&vaults_contract))?;
+
+    Ok(MaxUnbondResponse { shares })
+}
+
+pub fn query_is_allowed(
+    deps: Deps,
+    address: String,
+    proof: Option<Vec<String>>,
+) -> StdResult<IsAllowedResponse> {
+    let address = deps.api.addr_validate(&address)?;
+
+    Ok(IsAllowedResponse {
+        allowed: is_allowed(deps, &address, &proof)?,
+    })
+}
+
+pub fn query_pending_unbonds(
+    deps: Deps,
+    env: Env,
+    address: String,
+) -> StdResult<PendingUnbondsResponse> {
+    let config = load_config(deps)?;
+    let address = deps.api.addr_validate(&address)?;
+
+    let unbondings = get_unbondings(deps, &address)?;
+
+    let withdrawable = unbondings
+        .iter()
+        .filter(|unbonding| unbonding.release_at <= env.block.time.seconds())
+        .fold(Uint128::zero(), |acc, unbonding| acc + unbonding.amount);
+
+    Ok(PendingUnbondsResponse {
+        pending_unbonds: unbondings
+            .into_iter()
+            .map(|unbonding| PendingUnbond {
+                asset: Asset {
+                    info: config.asset_info.clone(),
+                    amount: unbonding.amount,
+                },
+                release_at: unbonding.release_at,
+            })
+            .collect(),
+        withdrawable: Asset {
+            info: config.asset_info,
+            amount: withdrawable,
+        },
+    })
+}
+
+#[cfg(test)]
+mod testing {
+    use super::*;
+    use terraswap::asset::AssetInfo::NativeToken;
+
+    use std::str::FromStr;
+
+    /// uusd vault with a 10% commission, override the fields a test needs
+    pub fn mock_instantiate_msg() -> InstantiateMsg {
+        InstantiateMsg {
+            commission_rate: Decimal256::from_str("0.1").unwrap(),
+            asset_info: NativeToken {
+                denom: "uusd".to_string(),
+            },
+            token_code_id: 123u64,
+            symbol: "TTN".to_string(),
+            unbonding_period: None,
+            max_total_deposit: None,
+            max_deposit_per_user: None,
+            access_mode: None,
+            guardian: None,
+            max_rate_change: None,
+            config_delay: None,
+            marketing: None,
+            token_admin: None,
+            vaults_token: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_instantiate {
+    use super::*;
+    use crate::contract::testing::mock_instantiate_msg;
+    use cosmwasm_std::testing::{mock_env, mock_info, MOCK_CONTRACT_ADDR};
+    use cosmwasm_std::{attr, coins, ContractResult, SubMsgExecutionResponse, WasmMsg};
+    use starflet_protocol::mock_querier::mock_dependencies;
+    use starflet_protocol::planet::TokenMarketing;
+
+    use crate::response::MsgInstantiateContractResponse;
+    use terraswap::asset::AssetInfo::NativeToken;
+
+    static OWNER: &str = "owner0000";
+    static COMMISSION_RATE: &str = "0.1";
+    static SYMBOL: &str = "TTN";
+    static CODE_ID: u64 = 123u64;
+    static ASSET_TOKEN: &str = "asset0000";
+    static TOKEN_ADMIN: &str = "admin0000";
+    static VAULTS_TOKEN: &str = "vaults0000";
+
+    #[test]
+    fn proper_initialization() {
+        let mut deps = mock_dependencies(&[]);
+        let asset_info = NativeToken {
+            denom: "uusd".to_string(),
+        };
+        let msg = InstantiateMsg {
+            asset_info: asset_info.clone(),
+            ..mock_instantiate_msg()
+        };
+
+        let info = mock_info(OWNER, &[]);
+
+        let res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        assert_eq!(
+            res.attributes,
+            vec![
+                attr("action", "instantiate"),
+                attr("owner", OWNER.to_string()),
+                attr("commission_rate", COMMISSION_RATE.to_string()),
+            ]
+        );
+
+        assert_eq!(
+            res.messages,
+            vec![SubMsg::reply_on_success(
+                CosmosMsg::Wasm(WasmMsg::Instantiate {
+                    admin: Some(MOCK_CONTRACT_ADDR.to_string()),
+                    code_id: CODE_ID,
+                    funds: vec![],
+                    label: format!("{} vaults {}", SYMBOL, MOCK_CONTRACT_ADDR),
+                    msg: to_binary(&TokenInstantiateMsg {
+                        name: format!("{} vaults", SYMBOL.to_string()),
+                        symbol: format!("v{}", SYMBOL),
+                        decimals: 6u8,
+                        initial_balances: vec![],
+                        mint: Some(MinterResponse {
+                            minter: MOCK_CONTRACT_ADDR.to_string(),
+                            cap: None,
+                        }),
+                        marketing: None,
+                    })
+                    .unwrap(),
+                }),
+                MSG_REPLY_ID_TOKEN_INSTANT
+            )]
+        );
+
+        let res_config = query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap();
+        let config: ConfigResponse = from_binary(&res_config).unwrap();
+        assert_eq!(OWNER.to_string(), config.owner);
+        assert_eq!(
+            COMMISSION_RATE.to_string(),
+            config.commission_rate.to_string()
+        );
+        assert_eq!(asset_info, config.asset_info);
+        assert_eq!(CODE_ID, config.token_code_id);
+    }
+
+    #[test]
+    fn token_metadata() {
+        let mut deps = mock_dependencies(&[]);
+        deps.querier.with_token_balances(&[(
+            &ASSET_TOKEN.to_string(),
+            &[(&OWNER.to_string(), &Uint128::from(100u128))],
+        )]);
+
+        let msg = InstantiateMsg {
+            asset_info: AssetInfo::Token {
+                contract_addr: ASSET_TOKEN.to_string(),
+            },
+            marketing: Some(TokenMarketing {
+                project: Some("starflet".to_string()),
+                description: None,
+                logo_url: Some("https://starflet.io/logo.png".to_string()),
+            }),
+            token_admin: Some(TOKEN_ADMIN.to_string()),
+            ..mock_instantiate_msg()
+        };
+
+        let res = instantiate(deps.as_mut(), mock_env(), mock_info(OWNER, &[]), msg).unwrap();
+
+        assert_eq!(
+            res.messages,
+            vec![SubMsg::reply_on_success(
+                CosmosMsg::Wasm(WasmMsg::Instantiate {
+                    admin: Some(TOKEN_ADMIN.to_string()),
+                    code_id: CODE_ID,
+                    funds: vec![],
+                    label: format!("{} vaults {}", SYMBOL, MOCK_CONTRACT_ADDR),
+                    msg: to_binary(&TokenInstantiateMsg {
+                        name: format!("{} vaults", SYMBOL),
+                        symbol: format!("v{}", SYMBOL),
+                        // from the mock TokenInfo of the asset token
+                        decimals: 6u8,
+                        initial_balances: vec![],
+                        mint: Some(MinterResponse {
+                            minter: MOCK_CONTRACT_ADDR.to_string(),
+                            cap: None,
+                        }),
+                        marketing: Some(InstantiateMarketingInfo {
+                            project: Some("starflet".to_string()),
+                            description: None,
+                            marketing: Some(OWNER.to_string()),
+                            logo: Some(Logo::Url("https://starflet.io/logo.png".to_string())),
+                        }),
+                    })
+                    .unwrap(),
+                }),
+                MSG_REPLY_ID_TOKEN_INSTANT
+            )]
+        );
+    }
+
+    #[test]
+    fn unknown_asset_token_will_err() {
+        let mut deps = mock_dependencies(&[]);
+
+        let msg = InstantiateMsg {
+            asset_info: AssetInfo::Token {
+                contract_addr: ASSET_TOKEN.to_string(),
+            },
+            ..mock_instantiate_msg()
+        };
+
+        let res = instantiate(deps.as_mut(), mock_env(), mock_info(OWNER, &[]), msg);
+        assert!(matches!(res, Err(ContractError::QueryFailed { .. })));
+    }
+
+    fn adopt_msg() -> InstantiateMsg {
+        InstantiateMsg {
+            vaults_token: Some(VAULTS_TOKEN.to_string()),
+            ..mock_instantiate_msg()
+        }
+    }
+
+    #[test]
+    fn adopt_vaults_token() {
+        let mut deps = mock_dependencies(&[]);
+        deps.querier
+            .with_token_balances(&[(&VAULTS_TOKEN.to_string(), &[])]);
+        deps.querier
+            .with_token_minters(&[(&VAULTS_TOKEN.to_string(), &MOCK_CONTRACT_ADDR.to_string())]);
+
+        let res = instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(OWNER, &[]),
+            adopt_msg(),
+        )
+        .unwrap();
+        assert!(res.messages.is_empty());
+        assert!(res.attributes.contains(&attr("vaults_token", VAULTS_TOKEN)));
+
+        let config = get_config(deps.as_ref()).unwrap();
+        assert_eq!(config.token_address, Some(Addr::unchecked(VAULTS_TOKEN)));
+        assert_eq!(
+            get_pause_state(deps.as_ref()).unwrap(),
+            PauseState::default()
+        );
+
+        // the reply of a fresh token must not replace it
+        let mut token_res = MsgInstantiateContractResponse::new();
+        token_res.set_contract_address("other0000".to_string());
+        let reply_msg = Reply {
+            id: MSG_REPLY_ID_TOKEN_INSTANT,
+            result: ContractResult::Ok(SubMsgExecutionResponse {
+                events: vec![],
+                data: Some(token_res.write_to_bytes().unwrap().into()),
+            }),
+        };
+        assert_eq!(
+            reply(deps.as_mut(), mock_env(), reply_msg).unwrap_err(),
+            ContractError::AlreadyInstantiate(VAULTS_TOKEN.to_string())
+        );
+    }
+
+    #[test]
+    fn adopt_vaults_token_with_holders_pauses() {
+        let mut deps = mock_dependencies(&[]);
+        deps.querier.with_token_balances(&[(
+            &VAULTS_TOKEN.to_string(),
+            &[(&OWNER.to_string(), &Uint128::from(100u128))],
+        )]);
+        deps.querier
+            .with_token_minters(&[(&VAULTS_TOKEN.to_string(), &MOCK_CONTRACT_ADDR.to_string())]);
+
+        instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(OWNER, &[]),
+            adopt_msg(),
+        )
+        .unwrap();
+
+        let pause_state = get_pause_state(deps.as_ref()).unwrap();
+        assert!(pause_state.bond);
+        assert!(pause_state.unbond);
+        assert!(pause_state.execute);
+        assert!(!pause_state.claim);
+
+        // a holder can not unbond against the empty vaults
+        let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+            sender: OWNER.to_string(),
+            amount: Uint128::from(100u128),
+            msg: to_binary(&Cw20HookMsg::Unbond {
+                recipient: None,
+                min_assets_out: None,
+            })
+            .unwrap(),
+        });
+        let res = execute(deps.as_mut(), mock_env(), mock_info(VAULTS_TOKEN, &[]), msg);
+        assert_eq!(
+            res.unwrap_err(),
+            ContractError::Paused {
+                operation: "unbond".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn book_backing() {
+        let mut deps = mock_dependencies(&[]);
+        deps.querier.with_token_balances(&[(
+            &VAULTS_TOKEN.to_string(),
+            &[(&OWNER.to_string(), &Uint128::from(100u128))],
+        )]);
+        deps.querier
+            .with_token_minters(&[(&VAULTS_TOKEN.to_string(), &MOCK_CONTRACT_ADDR.to_string())]);
+
+        instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(OWNER, &[]),
+            adopt_msg(),
+        )
+        .unwrap();
+
+        // the backing of the old planet is moved in
+        deps.querier
+            .with_balance(&[(&MOCK_CONTRACT_ADDR.to_string(), coins(100, "uusd"))]);
+
+        let msg = ExecuteMsg::BookBacking {
+            amount: Uint128::from(100u128),
+        };
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(TOKEN_ADMIN, &[]),
+            msg.clone(),
+        );
+        assert_eq!(res.unwrap_err(), ContractError::Unauthorized {});
+
+        let msg_over = ExecuteMsg::BookBacking {
+            amount: Uint128::from(101u128),
+        };
+        let res = execute(deps.as_mut(), mock_env(), mock_info(OWNER, &[]), msg_over);
+        assert_eq!(
+            res.unwrap_err(),
+            ContractError::BackingExceeded {
+                amount: Decimal256::from_uint256(101u64),
+                surplus: Decimal256::from_uint256(100u64),
+            }
+        );
+
+        let res = execute(deps.as_mut(), mock_env(), mock_info(OWNER, &[]), msg).unwrap();
+        assert_eq!(
+            res.attributes,
+            vec![attr("action", "book_backing"), attr("amount", "100")]
+        );
+
+        // principal, neither commission nor revenue
+        assert_eq!(
+            get_vaults(deps.as_ref()).unwrap(),
+            Decimal256::from_uint256(100u64)
+        );
+        assert_eq!(get_commission(deps.as_ref()).unwrap(), Decimal256::zero());
+        assert_eq!(
+            query_stats(deps.as_ref()).unwrap().total_revenue,
+            Decimal256::zero()
+        );
+        assert_eq!(query_rate(deps.as_ref()).unwrap().rate, Decimal256::one());
+        assert_eq!(
+            get_pause_state(deps.as_ref()).unwrap(),
+            PauseState::default()
+        );
+
+        // only once, and only for an adopted token
+        let msg = ExecuteMsg::BookBacking {
+            amount: Uint128::zero(),
+        };
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(OWNER, &[]),
+            msg.clone(),
+        );
+        assert_eq!(res.unwrap_err(), ContractError::NoPendingBacking {});
+
+        let mut deps = mock_dependencies(&[]);
+        instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(OWNER, &[]),
+            mock_instantiate_msg(),
+        )
+        .unwrap();
+        let res = execute(deps.as_mut(), mock_env(), mock_info(OWNER, &[]), msg);
+        assert_eq!(res.unwrap_err(), ContractError::NoPendingBacking {});
+    }
+
+    #[test]
+    fn adopt_vaults_token_not_minter_will_err() {
+        let mut deps = mock_dependencies(&[]);
+        deps.querier
+            .with_token_balances(&[(&VAULTS_TOKEN.to_string(), &[])]);
+
+        // no minter at all
+        let res = instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(OWNER, &[]),
+            adopt_msg(),
+        );
+        assert_eq!(
+            res.unwrap_err(),
+            ContractError::NotMinter(VAULTS_TOKEN.to_string())
+        );
+
+        deps.querier
+            .with_token_minters(&[(&VAULTS_TOKEN.to_string(), &OWNER.to_string())]);
+        let res = instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(OWNER, &[]),
+            adopt_msg(),
+        );
+        assert_eq!(
+            res.unwrap_err(),
+            ContractError::NotMinter(VAULTS_TOKEN.to_string())
+        );
+    }
+}
+
+#[cfg(test)]
+mod config {
+    use super::*;
+    use crate::contract::testing::mock_instantiate_msg;
+    use cosmwasm_std::testing::{mock_env, mock_info};
+    use starflet_protocol::mock_querier::mock_dependencies;
+    use terraswap::asset::AssetInfo::NativeToken;
+
+    use std::str::FromStr;
+
+    static OWNER: &str = "owner0000";
+    static COMMISSION_RATE: &str = "0.1";
+    static CODE_ID: u64 = 123u64;
+
+    static CHANGE_OWNER: &str = "owner0001";
+    static CHANGE_COMMISSION_RATE: &str = "0.5";
+    static CHANGE_CODE_ID: u64 = 456u64;
+
+    fn init(mut deps: DepsMut) {
+        let asset_info = NativeToken {
+            denom: "uusd".to_string(),
+        };
+        let msg = InstantiateMsg {
+            asset_info,
+            ..mock_instantiate_msg()
+        };
+
+        let info = mock_info(OWNER, &[]);
+
+        instantiate(deps.branch(), mock_env(), info, msg).unwrap();
+    }
+
+    #[test]
+    fn update_config_owner() {
+        let mut deps = mock_dependencies(&[]);
+        init(deps.as_mut());
+
+        let asset_info = NativeToken {
+            denom: "uusd".to_string(),
+        };
+
+        let msg = ExecuteMsg::UpdateConfig {
+            owner: Some(CHANGE_OWNER.to_string()),
+            commission_rate: None,
+            code_id: None,
+            unbonding_period: None,
+            max_total_deposit: None,
+            max_deposit_per_user: None,
+            guardian: None,
+            max_rate_change: None,
+            config_delay: None,
+        };
+
+        let info = mock_info(OWNER, &[]);
+        execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let res_config = query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap();
+        let config: ConfigResponse = from_binary(&res_config).unwrap();
+        assert_eq!(CHANGE_OWNER.to_string(), config.owner);
+        assert_eq!(
+            COMMISSION_RATE.to_string(),
+            config.commission_rate.to_string()
+        );
+        assert_eq!(asset_info, config.asset_info);
+        assert_eq!(CODE_ID, config.token_code_id);
+    }
+
+    #[test]
+    #[should_panic]
+    fn update_config_diffrent_owner_will_panic() {
+        let mut deps = mock_dependencies(&[]);
+        init(deps.as_mut());
+
+        let msg = ExecuteMsg::UpdateConfig {
+            owner: Some(CHANGE_OWNER.to_string()),
+            commission_rate: None,
+            code_id: None,
+            unbonding_period: None,
+            max_total_deposit: None,
+            max_deposit_per_user: None,
+            guardian: None,
+            max_rate_change: None,
+            config_delay: None,
+        };
+
+        let info = mock_info(CHANGE_OWNER, &[]);
+        execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+    }
+
+    #[test]
+    fn update_config_multiple_commission_rate_and_owner() {
+        let mut deps = mock_dependencies(&[]);
+        init(deps.as_mut());
+
+        let change_asset_info = NativeToken {
+            denom: "uusd".to_string(),
+        };
+
+        let msg = ExecuteMsg::UpdateConfig {
+            owner: Some(CHANGE_OWNER.to_string()),
+            commission_rate: Some(Decimal256::from_str(CHANGE_COMMISSION_RATE).unwrap()),
+            code_id: None,
+            unbonding_period: None,
+            max_total_deposit: None,
+            max_deposit_per_user: None,
+            guardian: None,
+            max_rate_change: None,
+            config_delay: None,
+        };
+
+        let info = mock_info(OWNER, &[]);
+        execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let res_config = query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap();
+        let config: ConfigResponse = from_binary(&res_config).unwrap();
+        assert_eq!(CHANGE_OWNER.to_string(), config.owner);
+        assert_eq!(
+            CHANGE_COMMISSION_RATE.to_string(),
+            config.commission_rate.to_string()
+        );
+        assert_eq!(change_asset_info, config.asset_info);
+        assert_eq!(CODE_ID, config.token_code_id);
+    }
+
+    #[test]
+    fn update_config_code_id() {
+        let mut deps = mock_dependencies(&[]);
+        init(deps.as_mut());
+
+        let asset_info = NativeToken {
+            denom: "uusd".to_string(),
+        };
+
+        let msg = ExecuteMsg::UpdateConfig {
+            owner: None,
+            commission_rate: None,
+            code_id: Some(CHANGE_CODE_ID),
+            unbonding_period: None,
+            max_total_deposit: None,
+            max_deposit_per_user: None,
+            guardian: None,
+            max_rate_change: None,
+            config_delay: None,
+        };
+
+        let info = mock_info(OWNER, &[]);
+        execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let res_config = query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap();
+        let config: ConfigResponse = from_binary(&res_config).unwrap();
+        assert_eq!(OWNER.to_string(), config.owner);
+        assert_eq!(
+            COMMISSION_RATE.to_string(),
+            config.commission_rate.to_string()
+        );
+        assert_eq!(asset_info, config.asset_info);
+        assert_eq!(CHANGE_CODE_ID, config.token_code_id);
+    }
+
+    #[test]
+    fn update_config_clears_limits() {
+        let mut deps = mock_dependencies(&[]);
+        init(deps.as_mut());
+
+        let info = mock_info(OWNER, &[]);
+        let msg: ExecuteMsg = from_binary(&Binary::from(
+            br#"{"update_config":{"max_total_deposit":"1000","max_deposit_per_user":"100","guardian":"guardian0000","max_rate_change":"0.1"}}"#
+                .as_ref(),
+        ))
+        .unwrap();
+        execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+
+        let config = get_config(deps.as_ref()).unwrap();
+        assert_eq!(config.max_total_deposit, Some(Uint128::from(1000u128)));
+        assert_eq!(config.max_deposit_per_user, Some(Uint128::from(100u128)));
+        assert_eq!(config.guardian, Some(Addr::unchecked("guardian0000")));
+        assert_eq!(config.max_rate_change, Some(Decimal256::percent(10)));
+
+        // a missing field is left as is, null clears it
+        let msg: ExecuteMsg = from_binary(&Binary::from(
+            br#"{"update_config":{"max_total_deposit":null,"guardian":null}}"#.as_ref(),
+        ))
+        .unwrap();
+        let res = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+        assert_eq!(
+            res.attributes,
+            vec![
+                attr("action", "update_config"),
+                attr("max_total_deposit", "none"),
+                attr("guardian", "none"),
+            ]
+        );
+
+        let config = get_config(deps.as_ref()).unwrap();
+        assert_eq!(config.max_total_deposit, None);
+        assert_eq!(config.max_deposit_per_user, Some(Uint128::from(100u128)));
+        assert_eq!(config.guardian, None);
+        assert_eq!(config.max_rate_change, Some(Decimal256::percent(10)));
+
+        let msg = ExecuteMsg::UpdateConfig {
+            owner: None,
+            commission_rate: None,
+            code_id: None,
+            unbonding_period: None,
+            max_total_deposit: None,
+            max_deposit_per_user: Some(None),
+            guardian: None,
+            max_rate_change: Some(None),
+            config_delay: None,
+        };
+        execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let config = get_config(deps.as_ref()).unwrap();
+        assert_eq!(config.max_deposit_per_user, None);
+        assert_eq!(config.max_rate_change, None);
+    }
+
+    fn update_msg(
+        owner: Option<&str>,
+        commission_rate: Option<&str>,
+        config_delay: Option<u64>,
+    ) -> ExecuteMsg {
+        ExecuteMsg::UpdateConfig {
+            owner: owner.map(|owner| owner.to_string()),
+            commission_rate: commission_rate.map(|rate| Decimal256::from_str(rate).unwrap()),
+            code_id: None,
+            unbonding_period: None,
+            max_total_deposit: None,
+            max_deposit_per_user: None,
+            guardian: None,
+            max_rate_change: None,
+            config_delay,
+        }
+    }
+
+    #[test]
+    fn commission_rate_above_max_will_err() {
+        let mut deps = mock_dependencies(&[]);
+        init(deps.as_mut());
+
+        let info = mock_info(OWNER, &[]);
+        let msg = update_msg(None, Some("0.6"), None);
+        let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+        assert_eq!(
+            res,
+            ContractError::InvalidCommissionRate {
+                rate: Decimal256::from_str("0.6").unwrap(),
+                max: max_commission_rate(),
+            }
+        );
+
+        let msg = InstantiateMsg {
+            commission_rate: Decimal256::from_str("1.1").unwrap(),
+            ..mock_instantiate_msg()
+        };
+        let mut deps = mock_dependencies(&[]);
+        let res = instantiate(deps.as_mut(), mock_env(), mock_info(OWNER, &[]), msg).unwrap_err();
+        assert_eq!(
+            res,
+            ContractError::InvalidCommissionRate {
+                rate: Decimal256::from_str("1.1").unwrap(),
+                max: max_commission_rate(),
+            }
+        );
+    }
+
+    #[test]
+    fn timelock() {
+        let mut deps = mock_dependencies(&[]);
+        init(deps.as_mut());
+
+        // a longer delay applies at once
+        let info = mock_info(OWNER, &[]);
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            info.clone(),
+            update_msg(None, None, Some(100)),
+        )
+        .unwrap();
+
+        // a lower commission as well, the new owner and the higher rate wait
+        let env = mock_env();
+        let msg = update_msg(Some(CHANGE_OWNER), Some("0.05"), None);
+        execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+        let msg = update_msg(Some(CHANGE_OWNER), Some(CHANGE_COMMISSION_RATE), Some(10));
+        let res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+        let release_at = env.block.time.seconds() + 100;
+        assert_eq!(
+            res.attributes,
+            vec![
+                attr("action", "update_config"),
+                attr("pending_release_at", release_at.to_string()),
+            ]
+        );
+
+        let config = query_config(deps.as_ref()).unwrap();
+        assert_eq!(config.owner, OWNER);
+        assert_eq!(
+            config.commission_rate,
+            Decimal256::from_str("0.05").unwrap()
+        );
+        assert_eq!(config.config_delay, 100);
+        assert_eq!(
+            query_pending_config(deps.as_ref()).unwrap(),
+            PendingConfigResponse {
+                owner: Some(CHANGE_OWNER.to_string()),
+                commission_rate: Some(Decimal256::from_str(CHANGE_COMMISSION_RATE).unwrap()),
+                config_delay: Some(10),
+                execute_targets: vec![],
+                release_at: Some(release_at),
+            }
+        );
+
+        let res = execute(
+            deps.as_mut(),
+            env.clone(),
+            info.clone(),
+            ExecuteMsg::ApplyConfig {},
+        )
+        .unwrap_err();
+        assert_eq!(res, ContractError::ConfigTimelocked { release_at });
+
+        let mut env = env;
+        env.block.time = env.block.time.plus_seconds(100);
+        let res = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(CHANGE_OWNER, &[]),
+            ExecuteMsg::ApplyConfig {},
+        )
+        .unwrap_err();
+        assert_eq!(res, ContractError::Unauthorized {});
+
+        let res = execute(
+            deps.as_mut(),
+            env.clone(),
+            info.clone(),
+            ExecuteMsg::ApplyConfig {},
+        )
+        .unwrap();
+        assert_eq!(
+            res.attributes,
+            vec![
+                attr("action", "apply_config"),
+                attr("owner", CHANGE_OWNER),
+                attr("commission_rate", CHANGE_COMMISSION_RATE),
+                attr("config_delay", "10"),
+            ]
+        );
+
+        let config = query_config(deps.as_ref()).unwrap();
+        assert_eq!(config.owner, CHANGE_OWNER);
+        assert_eq!(
+            config.commission_rate,
+            Decimal256::from_str(CHANGE_COMMISSION_RATE).unwrap()
+        );
+        assert_eq!(config.config_delay, 10);
+        assert_eq!(
+            query_pending_config(deps.as_ref()).unwrap().release_at,
+            None
+        );
+
+        let info = mock_info(CHANGE_OWNER, &[]);
+        let res = execute(deps.as_mut(), env, info, ExecuteMsg::ApplyConfig {}).unwrap_err();
+        assert_eq!(res, ContractError::NoPendingConfig {});
+    }
+
+    #[test]
+    fn cancel_timelocked_change() {
+        let mut deps = mock_dependencies(&[]);
+        init(deps.as_mut());
+
+        let info = mock_info(OWNER, &[]);
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            info.clone(),
+            update_msg(None, None, Some(100)),
+        )
+        .unwrap();
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            info.clone(),
+            update_msg(None, Some(CHANGE_COMMISSION_RATE), None),
+        )
+        .unwrap();
+
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(CHANGE_OWNER, &[]),
+            ExecuteMsg::CancelConfig {},
+        )
+        .unwrap_err();
+        assert_eq!(res, ContractError::Unauthorized {});
+
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            info.clone(),
+            ExecuteMsg::CancelConfig {},
+        )
+        .unwrap();
+        assert_eq!(res.attributes, vec![attr("action", "cancel_config")]);
+        assert_eq!(
+            query_pending_config(deps.as_ref()).unwrap().commission_rate,
+            None
+        );
+
+        let res =
+            execute(deps.as_mut(), mock_env(), info, ExecuteMsg::CancelConfig {}).unwrap_err();
+        assert_eq!(res, ContractError::NoPendingConfig {});
+    }
+}
+
+#[cfg(test)]
+mod reply {
+    use super::*;
+    use crate::contract::testing::mock_instantiate_msg;
+    use cosmwasm_std::testing::{mock_env, mock_info, MOCK_CONTRACT_ADDR};
+    use cosmwasm_std::{attr, coins, ContractResult, SubMsgExecutionResponse};
+    use starflet_protocol::mock_querier::mock_dependencies;
+    use terraswap::asset::AssetInfo::NativeToken;
+
+    use crate::response::MsgExecuteContractResponse;
+
+    use std::str::FromStr;
+
+    static OWNER: &str = "owner0000";
+
+    fn init(mut deps: DepsMut) {
+        let asset_info = NativeToken {
+            denom: "uusd".to_string(),
+        };
+        let msg = InstantiateMsg {
+            asset_info,
+            ..mock_instantiate_msg()
+        };
+
+        let info = mock_info(OWNER, &[]);
+
+        instantiate(deps.branch(), mock_env(), info, msg).unwrap();
+    }
+
+    #[test]
+    #[should_panic]
+    fn unknown_reply_id_will_panic() {
+        let mut deps = mock_dependencies(&[]);
+        init(deps.as_mut());
+
+        let mut res = MsgInstantiateContractResponse::new();
+        res.set_contract_address(MOCK_CONTRACT_ADDR.to_string());
+
+        let reply_msg = Reply {
+            id: 123u64,
+            result: ContractResult::Ok(SubMsgExecutionResponse {
+                events: vec![],
+                data: Some(res.write_to_bytes().unwrap().into()),
+            }),
+        };
+
+        reply(deps.as_mut(), mock_env(), reply_msg).unwrap();
+    }
+
+    #[test]
+    fn token_instant_reply() {
+        let mut deps = mock_dependencies(&coins(100u128, "uusd"));
+        init(deps.as_mut());
+
+        let mut res = MsgInstantiateContractResponse::new();
+        res.set_contract_address(MOCK_CONTRACT_ADDR.to_string());
+
+        let reply_msg = Reply {
+            id: MSG_REPLY_ID_TOKEN_INSTANT,
+            result: ContractResult::Ok(SubMsgExecutionResponse {
+                events: vec![],
+                data: Some(res.write_to_bytes().unwrap().into()),
+            }),
+        };
+
+        let res = reply(deps.as_mut(), mock_env(), reply_msg).unwrap();
+
+        assert_eq!(
+            res.attributes,
+            vec![
+                attr("reply", "token_instant"),
+                attr("token_address", MOCK_CONTRACT_ADDR),
+            ]
+        );
+
+        let config = get_config(deps.as_ref()).unwrap();
+        assert_eq!(
+            config.token_address.unwrap().to_string(),
+            MOCK_CONTRACT_ADDR
+        )
+    }
+
+    #[test]
+    fn revenue_execute_reply() {
+        let mut deps = mock_dependencies(&coins(100u128, "uusd"));
+        deps.querier.with_token_balances(&[(
+            &MOCK_CONTRACT_ADDR.to_string(),
+            &[(&OWNER.to_string(), &Uint128::zero())],
+        )]);
+        init(deps.as_mut());
+
+        let mut res = MsgInstantiateContractResponse::new();
+        res.set_contract_address(MOCK_CONTRACT_ADDR.to_string());
+
+        let reply_msg = Reply {
+            id: MSG_REPLY_ID_TOKEN_INSTANT,
+            result: ContractResult::Ok(SubMsgExecutionResponse {
+                events: vec![],
+                data: Some(res.write_to_bytes().unwrap().into()),
+            }),
+        };
+        reply(deps.as_mut(), mock_env(), reply_msg).unwrap();
+
+        let res = MsgExecuteContractResponse::new();
+
+        let reply_msg = Reply {
+            id: MSG_REPLY_ID_EXECUTE,
+            result: ContractResult::Ok(SubMsgExecutionResponse {
+                events: vec![],
+                data: Some(res.write_to_bytes().unwrap().into()),
+            }),
+        };
+
+        assert_eq!(Decimal256::zero(), get_vaults(deps.as_ref()).unwrap());
+
+        let res = reply(deps.as_mut(), mock_env(), reply_msg).unwrap();
+
+        assert_eq!(
+            res.attributes,
+            vec![
+                attr("reply", "execute"),
+                attr("result", "success"),
+                attr(
+                    "revenue",
+                    Decimal256::from_uint256(Uint256::from(100u128)).to_string()
+                ),
+                attr(
+                    "add_commission",
+                    Decimal256::from_uint256(Uint256::from(10u128)).to_string()
+                ),
+                attr("execution_id", "1"),
+            ]
+        );
+
+        assert_eq!(
+            Decimal256::from_uint256(Uint256::from(100u128)),
+            get_vaults(deps.as_ref()).unwrap()
+        );
+
+        assert_eq!(
+            Decimal256::from_uint256(Uint256::from(10u128)),
+            get_commission(deps.as_ref()).unwrap()
+        );
+    }
+
+    #[test]
+    fn loss_execute_reply() {
+        let mut deps = mock_dependencies(&coins(100u128, "uusd"));
+        init(deps.as_mut());
+
+        let mut res = MsgInstantiateContractResponse::new();
+        res.set_contract_address(MOCK_CONTRACT_ADDR.to_string());
+
+        let res = MsgExecuteContractResponse::new();
+        let reply_msg = Reply {
+            id: MSG_REPLY_ID_MUST_EXECUTE,
+            result: ContractResult::Ok(SubMsgExecutionResponse {
+                events: vec![],
+                data: Some(res.write_to_bytes().unwrap().into()),
+            }),
+        };
+
+        set_vaults(
+            deps.as_mut(),
+            Decimal256::from_uint256(Uint256::from(110u128)),
+        )
+        .unwrap();
+        add_commission(
+            deps.as_mut(),
+            Decimal256::from_uint256(Uint256::from(10u128)),
+        )
+        .unwrap();
+
+        let res = reply(deps.as_mut(), mock_env(), reply_msg).unwrap();
+
+        assert_eq!(
+            res.attributes,
+            vec![
+                attr("reply", "execute"),
+                attr("result", "fail"),
+                attr(
+                    "loss",
+                    Decimal256::from_uint256(Uint256::from(10u128)).to_string()
+                ),
+                attr(
+                    "commission_loss",
+                    Decimal256::from_uint256(Uint256::from(10u128)).to_string()
+                ),
+                attr("staker_loss", Decimal256::zero().to_string()),
+                attr("execution_id", "1"),
+            ]
+        );
+
+        assert_eq!(
+            Decimal256::from_uint256(Uint256::from(100u128)),
+            get_vaults(deps.as_ref()).unwrap()
+        );
+
+        assert_eq!(
+            Decimal256::from_uint256(Uint256::from(0u128)),
+            get_commission(deps.as_ref()).unwrap()
+        );
+    }
+
+    #[test]
+    fn loss_over_commission_execute_reply() {
+        let mut deps = mock_dependencies(&coins(100u128, "uusd"));
+        init(deps.as_mut());
+
+        set_vaults(
+            deps.as_mut(),
+            Decimal256::from_uint256(Uint256::from(130u128)),
+        )
+        .unwrap();
+        add_commission(
+            deps.as_mut(),
+            Decimal256::from_uint256(Uint256::from(10u128)),
+        )
+        .unwrap();
+
+        let reply_msg = Reply {
+            id: MSG_REPLY_ID_MUST_EXECUTE,
+            result: ContractResult::Ok(SubMsgExecutionResponse {
+                events: vec![],
+                data: None,
+            }),
+        };
+
+        let res = reply(deps.as_mut(), mock_env(), reply_msg).unwrap();
+        assert_eq!(
+            res.attributes,
+            vec![
+                attr("reply", "execute"),
+                attr("result", "fail"),
+                attr(
+                    "loss",
+                    Decimal256::from_uint256(Uint256::from(30u128)).to_string()
+                ),
+                attr(
+                    "commission_loss",
+                    Decimal256::from_uint256(Uint256::from(10u128)).to_string()
+                ),
+                attr(
+                    "staker_loss",
+                    Decimal256::from_uint256(Uint256::from(20u128)).to_string()
+                ),
+                attr("execution_id", "1"),
+            ]
+        );
+
+        assert_eq!(
+            Decimal256::from_uint256(Uint256::from(100u128)),
+            get_vaults(deps.as_ref()).unwrap()
+        );
+        assert_eq!(Decimal256::zero(), get_commission(deps.as_ref()).unwrap());
+
+        let res = query_executions(deps.as_ref(), None, None).unwrap();
+        assert_eq!(
+            res,
+            ExecutionsResponse {
+                executions: vec![Execution {
+                    id: 1,
+                    height: mock_env().block.height,
+                    time: mock_env().block.time.seconds(),
+                    revenue: Decimal256::zero(),
+                    loss: Decimal256::from_uint256(Uint256::from(30u128)),
+                    commission: Decimal256::zero(),
+                    rate_before: Decimal256::one(),
+                    rate_after: Decimal256::one(),
+                }],
+                total_count: 1,
+                total_revenue: Decimal256::zero(),
+                total_loss: Decimal256::from_uint256(Uint256::from(30u128)),
+                total_commission: Decimal256::zero(),
+            }
+        );
+    }
+
+    #[test]
+    fn max_loss_execute_reply() {
+        let mut deps = mock_dependencies(&coins(100u128, "uusd"));
+        init(deps.as_mut());
+
+        set_vaults(
+            deps.as_mut(),
+            Decimal256::from_uint256(Uint256::from(130u128)),
+        )
+        .unwrap();
+        set_execute_target(deps.as_mut(), &Addr::unchecked(MOCK_CONTRACT_ADDR), vec![]).unwrap();
+
+        let execute_msg: CosmosMsg<TerraMsgWrapper> = CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: MOCK_CONTRACT_ADDR.to_string(),
+            funds: vec![],
+            msg: to_binary(&Cw20ExecuteMsg::Burn {
+                amount: Uint128::zero(),
+            })
+            .unwrap(),
+        });
+        let msg = ExecuteMsg::Execute {
+            msgs: vec![to_binary(&execute_msg).unwrap()],
+            is_distribute: false,
+            max_loss: Some(Uint128::from(20u128)),
+            min_profit: None,
+        };
+        let res = execute(deps.as_mut(), mock_env(), mock_info(OWNER, &[]), msg).unwrap();
+        assert_eq!(
+            res.messages,
+            vec![SubMsg::reply_on_success(
+                execute_msg,
+                MSG_REPLY_ID_MUST_EXECUTE
+            )]
+        );
+
+        let reply_msg = Reply {
+            id: MSG_REPLY_ID_MUST_EXECUTE,
+            result: ContractResult::Ok(SubMsgExecutionResponse {
+                events: vec![],
+                data: None,
+            }),
+        };
+        let res = reply(deps.as_mut(), mock_env(), reply_msg);
+        assert_eq!(
+            res.unwrap_err(),
+            ContractError::MaxLossExceeded {
+                loss: Decimal256::from_uint256(Uint256::from(30u128)),
+                max_loss: Decimal256::from_uint256(Uint256::from(20u128)),
+            }
+        );
+    }
+
+    #[test]
+    fn min_profit_execute_reply() {
+        let mut deps = mock_dependencies(&coins(100u128, "uusd"));
+        init(deps.as_mut());
+
+        set_vaults(
+            deps.as_mut(),
+            Decimal256::from_uint256(Uint256::from(80u128)),
+        )
+        .unwrap();
+
+        let reply_msg = Reply {
+            id: MSG_REPLY_ID_EXECUTE,
+            result: ContractResult::Ok(SubMsgExecutionResponse {
+                events: vec![],
+                data: None,
+            }),
+        };
+
+        set_tmp_execute(
+            deps.as_mut(),
+            TmpExecute {
+                max_loss: None,
+                min_profit: Some(Decimal256::from_uint256(Uint256::from(50u128))),
+            },
+        )
+        .unwrap();
+        let res = reply(deps.as_mut(), mock_env(), reply_msg.clone());
+        assert_eq!(
+            res.unwrap_err(),
+            ContractError::ProfitBelowMinimum {
+                profit: Decimal256::from_uint256(Uint256::from(20u128)),
+                min_profit: Decimal256::from_uint256(Uint256::from(50u128)),
+            }
+        );
+
+        set_tmp_execute(
+            deps.as_mut(),
+            TmpExecute {
+                max_loss: None,
+                min_profit: Some(Decimal256::from_uint256(Uint256::from(20u128))),
+            },
+        )
+        .unwrap();
+        reply(deps.as_mut(), mock_env(), reply_msg).unwrap();
+
+        assert_eq!(
+            Decimal256::from_uint256(Uint256::from(100u128)),
+            get_vaults(deps.as_ref()).unwrap()
+        );
+        assert_eq!(
+            TmpExecute::default(),
+            get_tmp_execute(deps.as_ref()).unwrap()
+        );
+    }
+
+    #[test]
+    fn max_rate_change_execute_reply() {
+        let mut deps = mock_dependencies(&coins(100u128, "uusd"));
+        deps.querier.with_token_balances(&[(
+            &MOCK_CONTRACT_ADDR.to_string(),
+            &[(&OWNER.to_string(), &Uint128::from(100u128))],
+        )]);
+        init(deps.as_mut());
+
+        let mut config = get_config(deps.as_ref()).unwrap();
+        config.token_address = Some(Addr::unchecked(MOCK_CONTRACT_ADDR));
+        config.max_rate_change = Some(Decimal256::from_str("0.1").unwrap());
+        set_config(deps.as_mut(), config).unwrap();
+
+        set_vaults(
+            deps.as_mut(),
+            Decimal256::from_uint256(Uint256::from(80u128)),
+        )
+        .unwrap();
+
+        let reply_msg = Reply {
+            id: MSG_REPLY_ID_EXECUTE,
+            result: ContractResult::Ok(SubMsgExecutionResponse {
+                events: vec![],
+                data: None,
+            }),
+        };
+
+        // below one the rate is the plain 80 / 100 -> (100 - 2 commission) / 100
+        let res = reply(deps.as_mut(), mock_env(), reply_msg.clone());
+        assert_eq!(
+            res.unwrap_err(),
+            ContractError::RateChangeExceeded {
+                before: Decimal256::from_str("0.8").unwrap(),
+                after: Decimal256::from_str("0.98").unwrap(),
+                max_rate_change: Decimal256::from_str("0.1").unwrap(),
+            }
+        );
+
+        set_vaults(
+            deps.as_mut(),
+            Decimal256::from_uint256(Uint256::from(95u128)),
+        )
+        .unwrap();
+        reply(deps.as_mut(), mock_env(), reply_msg).unwrap();
+    }
+
+    #[test]
+    fn skip_execute_reply() {
+        let mut deps = mock_dependencies(&coins(100u128, "uusd"));
+        init(deps.as_mut());
+
+        let mut res = MsgInstantiateContractResponse::new();
+        res.set_contract_address(MOCK_CONTRACT_ADDR.to_string());
+
+        let res = MsgExecuteContractResponse::new();
+
+        let reply_msg = Reply {
+            id: MSG_REPLY_ID_EXECUTE_SKIP,
+            result: ContractResult::Ok(SubMsgExecutionResponse {
+                events: vec![],
+                data: Some(res.write_to_bytes().unwrap().into()),
+            }),
+        };
+
+        let res = reply(deps.as_mut(), mock_env(), reply_msg).unwrap();
+
+        assert_eq!(res.attributes, vec![attr("reply", "execute_skip"),]);
+
+        assert_eq!(
+            Decimal256::from_uint256(Uint256::from(0u128)),
+            get_vaults(deps.as_ref()).unwrap()
+        );
+
+        assert_eq!(
+            Decimal256::from_uint256(Uint256::from(0u128)),
+            get_commission(deps.as_ref()).unwrap()
+        );
+    }
+}
+
+#[cfg(test)]
+mod bond {
+    use super::*;
+    use crate::contract::testing::mock_instantiate_msg;
+    use cosmwasm_std::testing::{mock_env, mock_info, MOCK_CONTRACT_ADDR};
+    use cosmwasm_std::{attr, coins, ContractResult, SubMsgExecutionResponse};
+    use starflet_protocol::mock_querier::mock_dependencies;
+    use terraswap::asset::AssetInfo::{NativeToken, Token};
+
+    use crate::response::MsgInstantiateContractResponse;
+
+    static OWNER: &str = "owner0000";
+
+    static BONDER1: &str = "bonder0000";
+    static BONDER1_AMOUNT: u128 = 100u128;
+
+    static VAULTS_ASSET_TOKEN: &str = "vaultsassettoken";
+
+    fn init(mut deps: DepsMut) {
+        let asset_info = NativeToken {
+            denom: "uusd".to_string(),
+        };
+        let msg = InstantiateMsg {
+            asset_info,
+            ..mock_instantiate_msg()
+        };
+
+        let info = mock_info(OWNER, &[]);
+
+        instantiate(deps.branch(), mock_env(), info, msg).unwrap();
+
+        let mut res = MsgInstantiateContractResponse::new();
+        res.set_contract_address(MOCK_CONTRACT_ADDR.to_string());
+
+        let reply_msg = Reply {
+            id: MSG_REPLY_ID_TOKEN_INSTANT,
+            result: ContractResult::Ok(SubMsgExecutionResponse {
+                events: vec![],
+                data: Some(res.write_to_bytes().unwrap().into()),
+            }),
+        };
+
+        reply(deps.branch(), mock_env(), reply_msg).unwrap();
+    }
+
+    fn init_cw20(mut deps: DepsMut) {
+        let asset_info = Token {
+            contract_addr: VAULTS_ASSET_TOKEN.to_string(),
+        };
+        let msg = InstantiateMsg {
+            asset_info,
+            ..mock_instantiate_msg()
+        };
+
+        let info = mock_info(OWNER, &[]);
+
+        instantiate(deps.branch(), mock_env(), info, msg).unwrap();
+
+        let mut res = MsgInstantiateContractResponse::new();
+        res.set_contract_address(MOCK_CONTRACT_ADDR.to_string());
+
+        let reply_msg = Reply {
+            id: MSG_REPLY_ID_TOKEN_INSTANT,
+            result: ContractResult::Ok(SubMsgExecutionResponse {
+                events: vec![],
+                data: Some(res.write_to_bytes().unwrap().into()),
+            }),
+        };
+
+        reply(deps.branch(), mock_env(), reply_msg).unwrap();
+    }
+
+    #[test]
+    fn normal_bond() {
+        let mut deps = mock_dependencies(&[]);
+        deps.querier.with_token_balances(&[(
+            &MOCK_CONTRACT_ADDR.to_string(),
+            &[(&BONDER1.to_string(), &Uint128::from(0u128))],
+        )]);
+
+        init(deps.as_mut());
+
+        let bond_asset = Asset {
+            amount: Uint128::from(BONDER1_AMOUNT),
+            info: NativeToken {
+                denom: "uusd".to_string(),
+            },
+        };
+
+        let bond_msg = ExecuteMsg::Bond {
+            asset: bond_asset.clone(),
+            proof: None,
+            recipient: None,
+            min_shares_out: None,
+        };
+
+        let info = mock_info(BONDER1, &coins(100, "uusd"));
+
+        let res = execute(deps.as_mut(), mock_env(), info, bond_msg).unwrap();
+
+        assert_eq!(
+            res.attributes,
+            vec![
+                attr("action", "bond"),
+                attr("bonder", BONDER1.to_string()),
+                attr("recipient", BONDER1.to_string()),
+                attr("asset", bond_asset.to_string()),
+                attr("mint_amount", bond_asset.amount.to_string()),
+            ]
+        );
+
+        assert_eq!(
+            res.messages,
+            vec![SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: MOCK_CONTRACT_ADDR.to_string(),
+                funds: vec![],
+                msg: to_binary(&Cw20ExecuteMsg::Mint {
+                    recipient: BONDER1.to_string(),
+                    amount: bond_asset.amount,
+                })
+                .unwrap(),
+            }))]
+        );
+
+        assert_eq!(
+            Decimal256::from_uint256(Uint256::from(100u128)),
+            get_vaults(deps.as_ref()).unwrap()
+        );
+    }
+
+    #[test]
+    fn normal_bond_with_received() {
+        let mut deps = mock_dependencies(&[]);
+        deps.querier.with_token_balances(&[
+            (&VAULTS_ASSET_TOKEN.to_string(), &[]),
+            (
+                &MOCK_CONTRACT_ADDR.to_string(),
+                &[(&BONDER1.to_string(), &Uint128::from(0u128))],
+            ),
+        ]);
+
+        init_cw20(deps.as_mut());
+
+        let bond_msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+            sender: BONDER1.to_string(),
+            amount: Uint128::from(BONDER1_AMOUNT),
+            msg: to_binary(&Cw20HookMsg::Bond {
+                proof: None,
+                recipient: None,
+                min_shares_out: None,
+            })
+            .unwrap(),
+        });
+
+        let info = mock_info(VAULTS_ASSET_TOKEN, &[]);
+
+        let res = execute(deps.as_mut(), mock_env(), info, bond_msg).unwrap();
+
+        assert_eq!(
+            res.attributes,
+            vec![
+                attr("action", "bond"),
+                attr("bonder", BONDER1.to_string()),
+                attr("recipient", BONDER1.to_string()),
+                attr(
+                    "asset",
+                    BONDER1_AMOUNT.to_string() + &VAULTS_ASSET_TOKEN.to_string()
+                ),
+                attr("mint_amount", BONDER1_AMOUNT.to_string()),
+            ]
+        );
+
+        assert_eq!(
+            Decimal256::from_uint256(Uint256::from(BONDER1_AMOUNT)),
+            get_vaults(deps.as_ref()).unwrap()
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn insufficient_bond_will_panic() {
+        let mut deps = mock_dependencies(&[]);
+        deps.querier.with_token_balances(&[(
+            &MOCK_CONTRACT_ADDR.to_string(),
+            &[(&BONDER1.to_string(), &Uint128::from(0u128))],
+        )]);
+
+        let bond_asset = Asset {
+            amount: Uint128::from(BONDER1_AMOUNT),
+            info: NativeToken {
+                denom: "uusd".to_string(),
+            },
+        };
+
+        let bond_msg = ExecuteMsg::Bond {
+            asset: bond_asset,
+            proof: None,
+            recipient: None,
+            min_shares_out: None,
+        };
+
+        let info = mock_info(BONDER1, &coins(99, "uusd"));
+
+        execute(deps.as_mut(), mock_env(), info, bond_msg).unwrap();
+    }
+
+    fn init_with_caps(
+        mut deps: DepsMut,
+        max_total_deposit: Option<Uint128>,
+        max_deposit_per_user: Option<Uint128>,
+    ) {
+        let msg = InstantiateMsg {
+            max_total_deposit,
+            max_deposit_per_user,
+            ..mock_instantiate_msg()
+        };
+
+        instantiate(deps.branch(), mock_env(), mock_info(OWNER, &[]), msg).unwrap();
+
+        let mut config = get_config(deps.as_ref()).unwrap();
+        config.token_address = Some(Addr::unchecked(MOCK_CONTRACT_ADDR));
+        set_config(deps, config).unwrap();
+    }
+
+    fn bond_msg(amount: u128) -> ExecuteMsg {
+        ExecuteMsg::Bond {
+            asset: Asset {
+                amount: Uint128::from(amount),
+                info: NativeToken {
+                    denom: "uusd".to_string(),
+                },
+            },
+            proof: None,
+            recipient: None,
+            min_shares_out: None,
+        }
+    }
+
+    #[test]
+    fn inflated_share_rate_bond_will_err() {
+        let mut deps = mock_dependencies(&[]);
+        deps.querier.with_token_balances(&[(
+            &MOCK_CONTRACT_ADDR.to_string(),
+            &[(&OWNER.to_string(), &Uint128::from(1u128))],
+        )]);
+
+        init(deps.as_mut());
+
+        // one share minted, then 1000 donated and booked as revenue
+        set_vaults(
+            deps.as_mut(),
+            Decimal256::from_uint256(Uint256::from(1001u128)),
+        )
+        .unwrap();
+
+        // rate is (1001 + 1000) / (1 + 1000), the virtual shares took almost all the donation,
+        // so only dust mints nothing
+        let info = mock_info(BONDER1, &coins(1, "uusd"));
+        let res = execute(deps.as_mut(), mock_env(), info, bond_msg(1));
+        assert_eq!(res.unwrap_err(), ContractError::ZeroShares {});
+
+        let info = mock_info(BONDER1, &coins(BONDER1_AMOUNT * 10, "uusd"));
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            info,
+            bond_msg(BONDER1_AMOUNT * 10),
+        )
+        .unwrap();
+        // 1000 * 1001 / 2001
+        assert_eq!(res.attributes[4], attr("mint_amount", "500"));
+    }
+
+    #[test]
+    fn bond_over_total_cap_will_err() {
+        let mut deps = mock_dependencies(&[]);
+        deps.querier.with_token_balances(&[(
+            &MOCK_CONTRACT_ADDR.to_string(),
+            &[(&BONDER1.to_string(), &Uint128::from(0u128))],
+        )]);
+
+        init_with_caps(deps.as_mut(), Some(Uint128::from(150u128)), None);
+
+        let info = mock_info(BONDER1, &coins(BONDER1_AMOUNT, "uusd"));
+        execute(deps.as_mut(), mock_env(), info, bond_msg(BONDER1_AMOUNT)).unwrap();
+
+        let res = query_deposit_capacity(deps.as_ref(), None).unwrap();
+        assert_eq!(
+            res,
+            DepositCapacityResponse {
+                total_remaining: Some(Uint128::from(50u128)),
+                user_remaining: None,
+            }
+        );
+
+        let info = mock_info(BONDER1, &coins(BONDER1_AMOUNT, "uusd"));
+        let res = execute(deps.as_mut(), mock_env(), info, bond_msg(BONDER1_AMOUNT));
+        assert_eq!(
+            res.unwrap_err(),
+            ContractError::DepositCapExceeded {
+                remaining: Uint128::from(50u128)
+            }
+        );
+
+        let info = mock_info(BONDER1, &coins(50, "uusd"));
+        execute(deps.as_mut(), mock_env(), info, bond_msg(50)).unwrap();
+    }
+
+    #[test]
+    fn bond_over_user_cap_will_err() {
+        let mut deps = mock_dependencies(&[]);
+        deps.querier.with_token_balances(&[(
+            &MOCK_CONTRACT_ADDR.to_string(),
+            &[(&BONDER1.to_string(), &Uint128::from(BONDER1_AMOUNT))],
+        )]);
+
+        init_with_caps(deps.as_mut(), None, Some(Uint128::from(150u128)));
+        set_vaults(
+            deps.as_mut(),
+            Decimal256::from_uint256(Uint256::from(BONDER1_AMOUNT)),
+        )
+        .unwrap();
+
+        let res = query_deposit_capacity(deps.as_ref(), Some(BONDER1.to_string())).unwrap();
+        assert_eq!(
+            res,
+            DepositCapacityResponse {
+                total_remaining: None,
+                user_remaining: Some(Uint128::from(50u128)),
+            }
+        );
+
+        let info = mock_info(BONDER1, &coins(BONDER1_AMOUNT, "uusd"));
+        let res = execute(deps.as_mut(), mock_env(), info, bond_msg(BONDER1_AMOUNT));
+        assert_eq!(
+            res.unwrap_err(),
+            ContractError::DepositCapExceeded {
+                remaining: Uint128::from(50u128)
+            }
+        );
+
+        // another depositor still has the full per-user room
+        let info = mock_info("bonder0001", &coins(BONDER1_AMOUNT, "uusd"));
+        execute(deps.as_mut(), mock_env(), info, bond_msg(BONDER1_AMOUNT)).unwrap();
+    }
+
+    #[test]
+    fn bond_for_recipient() {
+        let mut deps = mock_dependencies(&[]);
+        deps.querier.with_token_balances(&[(
+            &MOCK_CONTRACT_ADDR.to_string(),
+            &[(&BONDER1.to_string(), &Uint128::from(BONDER1_AMOUNT))],
+        )]);
+
+        init_with_caps(deps.as_mut(), None, Some(Uint128::from(150u128)));
+        set_vaults(
+            deps.as_mut(),
+            Decimal256::from_uint256(Uint256::from(BONDER1_AMOUNT)),
+        )
+        .unwrap();
+
+        let bond_for = |recipient: &str| ExecuteMsg::Bond {
+            asset: Asset {
+                amount: Uint128::from(BONDER1_AMOUNT),
+                info: NativeToken {
+                    denom: "uusd".to_string(),
+                },
+            },
+            proof: None,
+            recipient: Some(recipient.to_string()),
+            min_shares_out: None,
+        };
+
+        let info = mock_info(BONDER1, &coins(BONDER1_AMOUNT, "uusd"));
+        let res = execute(deps.as_mut(), mock_env(), info.clone(), bond_for("")).unwrap_err();
+        assert!(matches!(res, ContractError::Std(_)));
+
+        // the per-user cap applies to the recipient, who holds the shares
+        let info1 = mock_info("bonder0001", &coins(BONDER1_AMOUNT, "uusd"));
+        let res = execute(deps.as_mut(), mock_env(), info1, bond_for(BONDER1));
+        assert_eq!(
+            res.unwrap_err(),
+            ContractError::DepositCapExceeded {
+                remaining: Uint128::from(50u128)
+            }
+        );
+
+        let res = execute(deps.as_mut(), mock_env(), info, bond_for("bonder0001")).unwrap();
+        assert_eq!(res.attributes[1], attr("bonder", BONDER1));
+        assert_eq!(res.attributes[2], attr("recipient", "bonder0001"));
+        assert_eq!(
+            res.messages,
+            vec![SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: MOCK_CONTRACT_ADDR.to_string(),
+                funds: vec![],
+                msg: to_binary(&Cw20ExecuteMsg::Mint {
+                    recipient: "bonder0001".to_string(),
+                    amount: Uint128::from(BONDER1_AMOUNT),
+                })
+                .unwrap(),
+            }))]
+        );
+    }
+
+    #[test]
+    fn bond_with_min_shares_out() {
+        let mut deps = mock_dependencies(&[]);
+        deps.querier.with_token_balances(&[(
+            &MOCK_CONTRACT_ADDR.to_string(),
+            &[(&BONDER1.to_string(), &Uint128::from(BONDER1_AMOUNT))],
+        )]);
+
+        init(deps.as_mut());
+        // 100 shares backed by 200 assets, the rate moved since the bond was signed
+        set_vaults(
+            deps.as_mut(),
+            Decimal256::from_uint256(Uint256::from(BONDER1_AMOUNT * 2)),
+        )
+        .unwrap();
+
+        let bond_msg = |min_shares_out: u128| ExecuteMsg::Bond {
+            asset: Asset {
+                amount: Uint128::from(BONDER1_AMOUNT),
+                info: NativeToken {
+                    denom: "uusd".to_string(),
+                },
+            },
+            proof: None,
+            recipient: None,
+            min_shares_out: Some(Uint128::from(min_shares_out)),
+        };
+
+        let info = mock_info(BONDER1, &coins(BONDER1_AMOUNT, "uusd"));
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            info.clone(),
+            bond_msg(BONDER1_AMOUNT),
+        );
+        assert_eq!(
+            res.unwrap_err(),
+            ContractError::SlippageExceeded {
+                minimum: Uint128::from(BONDER1_AMOUNT),
+                // 100 * (100 + 1000) / (200 + 1000)
+                actual: Uint128::from(91u128),
+            }
+        );
+
+        execute(deps.as_mut(), mock_env(), info, bond_msg(91)).unwrap();
+    }
+}
+
+#[cfg(test)]
+mod unbond {
+    use super::*;
+    use crate::contract::testing::mock_instantiate_msg;
+    use crate::response::MsgInstantiateContractResponse;
+    use cosmwasm_std::testing::{mock_env, mock_info, MOCK_CONTRACT_ADDR};
+    use cosmwasm_std::{attr, coins, BankMsg, ContractResult, Decimal, SubMsgExecutionResponse};
+    use starflet_protocol::mock_querier::mock_dependencies;
+    use terraswap::asset::AssetInfo::NativeToken;
+
+    static OWNER: &str = "owner0000";
+
+    static BONDER1: &str = "bonder0000";
+    static BONDER1_AMOUNT: u128 = 100u128;
+    static UNBONDER1_AMOUNT: u128 = 10u128;
+    static RECIPIENT: &str = "recipient0000";
+
+    static UNBONDING_PERIOD: u64 = 86400u64;
+
+    fn init(mut deps: DepsMut, unbonding_period: Option<u64>) {
+        let asset_info = NativeToken {
+            denom: "uusd".to_string(),
+        };
+        let msg = InstantiateMsg {
+            asset_info,
+            unbonding_period,
+            ..mock_instantiate_msg()
+        };
+
+        let info = mock_info(OWNER, &[]);
+
+        instantiate(deps.branch(), mock_env(), info, msg).unwrap();
+
+        let mut res = MsgInstantiateContractResponse::new();
+        res.set_contract_address(MOCK_CONTRACT_ADDR.to_string());
+
+        let reply_msg = Reply {
+            id: MSG_REPLY_ID_TOKEN_INSTANT,
+            result: ContractResult::Ok(SubMsgExecutionResponse {
+                events: vec![],
+                data: Some(res.write_to_bytes().unwrap().into()),
+            }),
+        };
+
+        reply(deps.branch(), mock_env(), reply_msg).unwrap();
+
+        let bond_asset = Asset {
+            amount: Uint128::from(BONDER1_AMOUNT),
+            info: NativeToken {
+                denom: "uusd".to_string(),
+            },
+        };
+
+        let bond_msg = ExecuteMsg::Bond {
+            asset: bond_asset,
+            proof: None,
+            recipient: None,
+            min_shares_out: None,
+        };
+
+        let info = mock_info(BONDER1, &coins(100, "uusd"));
+
+        execute(deps, mock_env(), info, bond_msg).unwrap();
+    }
+
+    #[test]
+    fn normal_unbond() {
+        let mut deps = mock_dependencies(&[]);
+        deps.querier.with_token_balances(&[(
+            &MOCK_CONTRACT_ADDR.to_string(),
+            &[(&BONDER1.to_string(), &Uint128::from(BONDER1_AMOUNT))],
+        )]);
+
+        init(deps.as_mut(), None);
+
+        let unbond_msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+            sender: BONDER1.to_string(),
+            amount: Uint128::from(UNBONDER1_AMOUNT),
+            msg: to_binary(&Cw20HookMsg::Unbond {
+                recipient: None,
+                min_assets_out: None,
+            })
+            .unwrap(),
+        });
+
+        let info = mock_info(MOCK_CONTRACT_ADDR, &[]);
+
+        let res = execute(deps.as_mut(), mock_env(), info, unbond_msg).unwrap();
+
+        assert_eq!(
+            res.attributes,
+            vec![
+                attr("action", "unbond"),
+                attr("unbonder", BONDER1.to_string()),
+                attr("recipient", BONDER1.to_string()),
+                attr("asset", UNBONDER1_AMOUNT.to_string() + &"uusd".to_string()),
+                attr("burn_amount", UNBONDER1_AMOUNT.to_string()),
+            ]
+        );
+
+        assert_eq!(
+            Decimal256::from_uint256(Uint256::from(90u128)),
+            get_vaults(deps.as_ref()).unwrap()
+        );
+    }
+
+    #[test]
+    fn unbond_with_unbonding_period() {
+        let mut deps = mock_dependencies(&[]);
+        deps.querier.with_token_balances(&[(
+            &MOCK_CONTRACT_ADDR.to_string(),
+            &[(&BONDER1.to_string(), &Uint128::from(BONDER1_AMOUNT))],
+        )]);
+
+        init(deps.as_mut(), Some(UNBONDING_PERIOD));
+
+        let unbond_msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+            sender: BONDER1.to_string(),
+            amount: Uint128::from(UNBONDER1_AMOUNT),
+            msg: to_binary(&Cw20HookMsg::Unbond {
+                recipient: None,
+                min_assets_out: None,
+            })
+            .unwrap(),
+        });
+
+        let env = mock_env();
+        let release_at = env.block.time.plus_seconds(UNBONDING_PERIOD).seconds();
+
+        let info = mock_info(MOCK_CONTRACT_ADDR, &[]);
+        let res = execute(deps.as_mut(), env.clone(), info, unbond_msg).unwrap();
+
+        assert_eq!(
+            res.attributes,
+            vec![
+                attr("release_at", release_at.to_string()),
+                attr("action", "unbond"),
+                attr("unbonder", BONDER1.to_string()),
+                attr("recipient", BONDER1.to_string()),
+                attr("asset", UNBONDER1_AMOUNT.to_string() + "uusd"),
+                attr("burn_amount", UNBONDER1_AMOUNT.to_string()),
+            ]
+        );
+
+        assert_eq!(
+            res.messages,
+            vec![SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: MOCK_CONTRACT_ADDR.to_string(),
+                funds: vec![],
+                msg: to_binary(&Cw20ExecuteMsg::Burn {
+                    amount: Uint128::from(UNBONDER1_AMOUNT),
+                })
+                .unwrap(),
+            }))]
+        );
+
+        assert_eq!(
+            Decimal256::from_uint256(Uint256::from(90u128)),
+            get_vaults(deps.as_ref()).unwrap()
+        );
+        assert_eq!(
+            Decimal256::from_uint256(Uint256::from(UNBONDER1_AMOUNT)),
+            get_total_unbonding(deps.as_ref()).unwrap()
+        );
+
+        let res = query_pending_unbonds(deps.as_ref(), env.clone(), BONDER1.to_string()).unwrap();
+        assert_eq!(
+            res.pending_unbonds,
+            vec![PendingUnbond {
+                asset: Asset {
+                    info: NativeToken {
+                        denom: "uusd".to_string(),
+                    },
+                    amount: Uint128::from(UNBONDER1_AMOUNT),
+                },
+                release_at,
+            }]
+        );
+        assert_eq!(res.withdrawable.amount, Uint128::zero());
+
+        // too early
+        let res = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(BONDER1, &[]),
+            ExecuteMsg::WithdrawUnbonded {},
+        );
+        assert_eq!(res.unwrap_err(), ContractError::NothingToWithdraw {});
+
+        let mut env = env;
+        env.block.time = env.block.time.plus_seconds(UNBONDING_PERIOD);
+
+        let res = query_pending_unbonds(deps.as_ref(), env.clone(), BONDER1.to_string()).unwrap();
+        assert_eq!(res.withdrawable.amount, Uint128::from(UNBONDER1_AMOUNT));
+
+        let res = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(BONDER1, &[]),
+            ExecuteMsg::WithdrawUnbonded {},
+        )
+        .unwrap();
+
+        assert_eq!(
+            res.attributes,
+            vec![
+                attr("action", "withdraw_unbonded"),
+                attr("withdrawer", BONDER1.to_string()),
+                attr("asset", UNBONDER1_AMOUNT.to_string() + "uusd"),
+            ]
+        );
+        assert_eq!(
+            res.messages,
+            vec![SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+                to_address: BONDER1.to_string(),
+                amount: coins(UNBONDER1_AMOUNT, "uusd"),
+            }))]
+        );
+
+        let res = query_pending_unbonds(deps.as_ref(), env, BONDER1.to_string()).unwrap();
+        assert!(res.pending_unbonds.is_empty());
+        assert_eq!(
+            Decimal256::zero(),
+            get_total_unbonding(deps.as_ref()).unwrap()
+        );
+    }
+
+    #[test]
+    fn pending_unbonds_are_not_revenue() {
+        let mut deps = mock_dependencies(&coins(100u128, "uusd"));
+        deps.querier.with_token_balances(&[(
+            &MOCK_CONTRACT_ADDR.to_string(),
+            &[(&BONDER1.to_string(), &Uint128::from(BONDER1_AMOUNT))],
+        )]);
+
+        init(deps.as_mut(), Some(UNBONDING_PERIOD));
+
+        deps.querier.with_token_balances(&[(
+            &MOCK_CONTRACT_ADDR.to_string(),
+            &[(&BONDER1.to_string(), &Uint128::from(BONDER1_AMOUNT))],
+        )]);
+
+        let unbond_msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+            sender: BONDER1.to_string(),
+            amount: Uint128::from(UNBONDER1_AMOUNT),
+            msg: to_binary(&Cw20HookMsg::Unbond {
+                recipient: None,
+                min_assets_out: None,
+            })
+            .unwrap(),
+        });
+        let info = mock_info(MOCK_CONTRACT_ADDR, &[]);
+        execute(deps.as_mut(), mock_env(), info, unbond_msg).unwrap();
+
+        let reply_msg = Reply {
+            id: MSG_REPLY_ID_MUST_EXECUTE,
+            result: ContractResult::Ok(SubMsgExecutionResponse {
+                events: vec![],
+                data: None,
+            }),
+        };
+        let res = reply(deps.as_mut(), mock_env(), reply_msg).unwrap();
+
+        assert_eq!(
+            res.attributes,
+            vec![
+                attr("reply", "execute"),
+                attr("result", "fail"),
+                attr("loss", Decimal256::zero().to_string()),
+                attr("commission_loss", Decimal256::zero().to_string()),
+                attr("staker_loss", Decimal256::zero().to_string()),
+                attr("execution_id", "1"),
+            ]
+        );
+        assert_eq!(
+            Decimal256::from_uint256(Uint256::from(90u128)),
+            get_vaults(deps.as_ref()).unwrap()
+        );
+    }
+
+    #[test]
+    fn unbond_to_recipient() {
+        let mut deps = mock_dependencies(&[]);
+        deps.querier.with_token_balances(&[(
+            &MOCK_CONTRACT_ADDR.to_string(),
+            &[(&BONDER1.to_string(), &Uint128::from(BONDER1_AMOUNT))],
+        )]);
+
+        init(deps.as_mut(), None);
+
+        let unbond_msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+            sender: BONDER1.to_string(),
+            amount: Uint128::from(UNBONDER1_AMOUNT),
+            msg: to_binary(&Cw20HookMsg::Unbond {
+                recipient: Some(RECIPIENT.to_string()),
+                min_assets_out: None,
+            })
+            .unwrap(),
+        });
+
+        let info = mock_info(MOCK_CONTRACT_ADDR, &[]);
+        let res = execute(deps.as_mut(), mock_env(), info, unbond_msg).unwrap();
+
+        assert_eq!(
+            res.messages[0],
+            SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+                to_address: RECIPIENT.to_string(),
+                amount: coins(UNBONDER1_AMOUNT, "uusd"),
+            }))
+        );
+        assert_eq!(res.attributes[1], attr("unbonder", BONDER1));
+        assert_eq!(res.attributes[2], attr("recipient", RECIPIENT));
+    }
+
+    #[test]
+    fn unbond_to_recipient_with_unbonding_period() {
+        let mut deps = mock_dependencies(&[]);
+        deps.querier.with_token_balances(&[(
+            &MOCK_CONTRACT_ADDR.to_string(),
+            &[(&BONDER1.to_string(), &Uint128::from(BONDER1_AMOUNT))],
+        )]);
+
+        init(deps.as_mut(), Some(UNBONDING_PERIOD));
+
+        let unbond_msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+            sender: BONDER1.to_string(),
+            amount: Uint128::from(UNBONDER1_AMOUNT),
+            msg: to_binary(&Cw20HookMsg::Unbond {
+                recipient: Some(RECIPIENT.to_string()),
+                min_assets_out: None,
+            })
+            .unwrap(),
+        });
+
+        let env = mock_env();
+        let info = mock_info(MOCK_CONTRACT_ADDR, &[]);
+        execute(deps.as_mut(), env.clone(), info, unbond_msg).unwrap();
+
+        // the recipient withdraws the unbonding, not the sender
+        let res = query_pending_unbonds(deps.as_ref(), env.clone(), BONDER1.to_string()).unwrap();
+        assert!(res.pending_unbonds.is_empty());
+        let res = query_pending_unbonds(deps.as_ref(), env, RECIPIENT.to_string()).unwrap();
+        assert_eq!(res.pending_unbonds.len(), 1);
+    }
+
+    #[test]
+    fn unbond_with_min_assets_out() {
+        let mut deps = mock_dependencies(&[]);
+        deps.querier.with_token_balances(&[(
+            &MOCK_CONTRACT_ADDR.to_string(),
+            &[(&BONDER1.to_string(), &Uint128::from(BONDER1_AMOUNT))],
+        )]);
+        deps.querier.with_tax(
+            Decimal::percent(1),
+            &[(&"uusd".to_string(), &Uint128::from(1_000_000u128))],
+        );
+
+        init(deps.as_mut(), None);
+
+        let unbond_msg = |min_assets_out: Uint128| {
+            ExecuteMsg::Receive(Cw20ReceiveMsg {
+                sender: BONDER1.to_string(),
+                amount: Uint128::from(UNBONDER1_AMOUNT),
+                msg: to_binary(&Cw20HookMsg::Unbond {
+                    recipient: None,
+                    min_assets_out: Some(min_assets_out),
+                })
+                .unwrap(),
+            })
+        };
+
+        // the minimum is compared after tax, as preview_unbond reports it
+        let preview = query_preview_unbond(deps.as_ref(), Uint128::from(UNBONDER1_AMOUNT)).unwrap();
+        assert!(!preview.tax.is_zero());
+
+        let info = mock_info(MOCK_CONTRACT_ADDR, &[]);
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            info.clone(),
+            unbond_msg(preview.asset.amount + Uint128::from(1u128)),
+        );
+        assert_eq!(
+            res.unwrap_err(),
+            ContractError::SlippageExceeded {
+                minimum: preview.asset.amount + Uint128::from(1u128),
+                actual: preview.asset.amount,
+            }
+        );
+
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            info,
+            unbond_msg(preview.asset.amount),
+        )
+        .unwrap();
+    }
+}
+
+#[cfg(test)]
+mod claim {
+    use super::*;
+    use crate::contract::testing::mock_instantiate_msg;
+    use cosmwasm_std::testing::{mock_env, mock_info, MOCK_CONTRACT_ADDR};
+    use cosmwasm_std::{attr, coins, BankMsg};
+    use starflet_protocol::mock_querier::mock_dependencies;
+    use terraswap::asset::AssetInfo::NativeToken;
+
+    use crate::response::MsgInstantiateContractResponse;
+
+    static OWNER: &str = "owner0000";
+
+    static BONDER1: &str = "bonder0000";
+    static CLAIM_BALANCE: Decimal256 = Decimal256::one();
+
+    fn init(mut deps: DepsMut) {
+        let asset_info = NativeToken {
+            denom: "uusd".to_string(),
+        };
+        let msg = InstantiateMsg {
+            asset_info,
+            ..mock_instantiate_msg()
+        };
+
+        let info = mock_info(OWNER, &[]);
+
+        instantiate(deps.branch(), mock_env(), info, msg).unwrap();
+
+        let mut res = MsgInstantiateContractResponse::new();
+        res.set_contract_address(MOCK_CONTRACT_ADDR.to_string());
+
+        add_vaults(deps.branch(), CLAIM_BALANCE).unwrap();
+        add_commission(deps.branch(), CLAIM_BALANCE).unwrap();
+    }
+
+    #[test]
+    fn normal_claim() {
+        let mut deps = mock_dependencies(&[]);
+
+        init(deps.as_mut());
+
+        let res_commission = query_commission(deps.as_ref()).unwrap();
+
+        let msg = ExecuteMsg::Claim {};
+
+        let info = mock_info(OWNER, &[]);
+
+        let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        assert_eq!(
+            res.attributes,
+            vec![
+                attr("action", "claim"),
+                attr("claimer", OWNER.to_string()),
+                attr("asset", res_commission.asset.to_string()),
+            ]
+        );
+
+        assert_eq!(
+            res.messages,
+            vec![SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+                to_address: OWNER.to_string(),
+                amount: coins(1, "uusd"),
+            }))]
+        );
+    }
+
+    #[test]
+    fn unknown_owner_claim_will_err() {
+        let mut deps = mock_dependencies(&[]);
+
+        init(deps.as_mut());
+
+        let msg = ExecuteMsg::Claim {};
+
+        let info = mock_info(BONDER1, &[]);
+
+        execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+    }
+}
+
+#[cfg(test)]
+mod rate {
+    use super::*;
+    use crate::contract::testing::mock_instantiate_msg;
+    use cosmwasm_std::testing::{mock_env, mock_info, MOCK_CONTRACT_ADDR};
+    use cosmwasm_std::{coin, coins};
+    use starflet_protocol::mock_querier::mock_dependencies;
+    use terraswap::asset::AssetInfo::NativeToken;
+
+    use crate::response::MsgInstantiateContractResponse;
+
+    static OWNER: &str = "owner0000";
+
+    static BONDER0: &str = "bonder0000";
+    static BONDER1: &str = "bonder0001";
+
+    static VAULTS_TOKEN_CONTRACT: &str = "vaults0000";
+
+    fn init(mut deps: DepsMut) {
+        let asset_info = NativeToken {
+            denom: "uusd".to_string(),
+        };
+        let msg = InstantiateMsg {
+            asset_info,
+            ..mock_instantiate_msg()
+        };
+
+        let info = mock_info(OWNER, &[]);
+
+        instantiate(deps.branch(), mock_env(), info, msg).unwrap();
+
+        let mut res = MsgInstantiateContractResponse::new();
+        res.set_contract_address(MOCK_CONTRACT_ADDR.to_string());
+
+        let mut config = get_config(deps.as_ref()).unwrap();
+        config.token_address = Some(Addr::unchecked(VAULTS_TOKEN_CONTRACT));
+        set_config(deps, config).unwrap();
+    }
+
+    #[test]
+    fn more_than_share_rate() {
+        let mut deps = mock_dependencies(&coins(110u128, "uusd"));
+        deps.querier
+            .with_balance(&[(&MOCK_CONTRACT_ADDR.to_string(), vec![coin(110u128, "uusd")])]);
+        deps.querier.with_token_balances(&[(
+            &VAULTS_TOKEN_CONTRACT.to_string(),
+            &[
+                (&BONDER0.to_string(), &Uint128::from(1u128)),
+                (&BONDER1.to_string(), &Uint128::from(9u128)),
+            ],
+        )]);
+
+        init(deps.as_mut());
+
+        set_vaults(
+            deps.as_mut(),
+            Decimal256::from_uint256(Uint256::from(110u128)),
+        )
+        .unwrap();
+        add_commission(
+            deps.as_mut(),
+            Decimal256::from_uint256(Uint256::from(10u128)),
+        )
+        .unwrap();
+
+        // the virtual shares hold 1000 / 1010 of the assets, 1 * (100 + 1000) / (10 + 1000)
+        let bonder0_balance = query_stake_info(deps.as_ref(), BONDER0.to_string()).unwrap();
+        assert_eq!("1uusd", bonder0_balance.asset.to_string(),);
+
+        let bonder1_balance = query_stake_info(deps.as_ref(), BONDER1.to_string()).unwrap();
+        assert_eq!("9uusd", bonder1_balance.asset.to_string());
+    }
+
+    #[test]
+    fn less_than_share_rate() {
+        let mut deps = mock_dependencies(&coins(110u128, "uusd"));
+        deps.querier
+            .with_balance(&[(&MOCK_CONTRACT_ADDR.to_string(), vec![coin(110u128, "uusd")])]);
+
+        deps.querier.with_token_balances(&[(
+            &VAULTS_TOKEN_CONTRACT.to_string(),
+            &[
+                (&BONDER0.to_string(), &Uint128::from(10000u128)),
+                (&BONDER1.to_string(), &Uint128::from(90000u128)),
+            ],
+        )]);
+
+        init(deps.as_mut());
+
+        set_vaults(
+            deps.as_mut(),
+            Decimal256::from_uint256(Uint256::from(110u128)),
+        )
+        .unwrap();
+        add_commission(
+            deps.as_mut(),
+            Decimal256::from_uint256(Uint256::from(10u128)),
+        )
+        .unwrap();
+
+        let bonder0_balance = query_stake_info(deps.as_ref(), BONDER0.to_string()).unwrap();
+        assert_eq!("10uusd", bonder0_balance.asset.to_string(),);
+
+        let bonder1_balance = query_stake_info(deps.as_ref(), BONDER1.to_string()).unwrap();
+        assert_eq!("90uusd", bonder1_balance.asset.to_string());
+    }
+}
+
+#[cfg(test)]
+mod cw20_asset {
+    use super::*;
+    use crate::contract::testing::mock_instantiate_msg;
+    use cosmwasm_std::testing::{mock_env, mock_info, MOCK_CONTRACT_ADDR};
+    use cosmwasm_std::{attr, ContractResult, SubMsgExecutionResponse};
+    use starflet_protocol::mock_querier::mock_dependencies;
+    use terraswap::asset::AssetInfo::Token;
+
+    use crate::response::{MsgExecuteContractResponse, MsgInstantiateContractResponse};
+
+    static OWNER: &str = "owner0000";
+
+    static BONDER1: &str = "bonder0000";
+    static BONDER1_AMOUNT: u128 = 100u128;
+
+    static VAULTS_ASSET_TOKEN: &str = "vaultsassettoken";
+
+    fn init(mut deps: DepsMut) {
+        let asset_info = Token {
+            contract_addr: VAULTS_ASSET_TOKEN.to_string(),
+        };
+        let msg = InstantiateMsg {
+            asset_info,
+            ..mock_instantiate_msg()
+        };
+
+        let info = mock_info(OWNER, &[]);
+
+        instantiate(deps.branch(), mock_env(), info, msg).unwrap();
+
+        let mut res = MsgInstantiateContractResponse::new();
+        res.set_contract_address(MOCK_CONTRACT_ADDR.to_string());
+
+        let reply_msg = Reply {
+            id: MSG_REPLY_ID_TOKEN_INSTANT,
+            result: ContractResult::Ok(SubMsgExecutionResponse {
+                events: vec![],
+                data: Some(res.write_to_bytes().unwrap().into()),
+            }),
+        };
+
+        reply(deps.branch(), mock_env(), reply_msg).unwrap();
+    }
+
+    #[test]
+    fn bond() {
+        let mut deps = mock_dependencies(&[]);
+        deps.querier.with_token_balances(&[
+            (&VAULTS_ASSET_TOKEN.to_string(), &[]),
+            (
+                &MOCK_CONTRACT_ADDR.to_string(),
+                &[(&BONDER1.to_string(), &Uint128::from(0u128))],
+            ),
+        ]);
+
+        init(deps.as_mut());
+
+        let bond_msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+            sender: BONDER1.to_string(),
+            amount: Uint128::from(BONDER1_AMOUNT),
+            msg: to_binary(&Cw20HookMsg::Bond {
+                proof: None,
+                recipient: None,
+                min_shares_out: None,
+            })
+            .unwrap(),
+        });
+
+        let info = mock_info(VAULTS_ASSET_TOKEN, &[]);
+        let res = execute(deps.as_mut(), mock_env(), info, bond_msg).unwrap();
+
+        assert_eq!(
+            res.messages,
+            vec![SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: MOCK_CONTRACT_ADDR.to_string(),
+                funds: vec![],
+                msg: to_binary(&Cw20ExecuteMsg::Mint {
+                    recipient: BONDER1.to_string(),
+                    amount: Uint128::from(BONDER1_AMOUNT),
+                })
+                .unwrap(),
+            }))]
+        );
+
+        assert_eq!(
+            Decimal256::from_uint256(Uint256::from(BONDER1_AMOUNT)),
+            get_vaults(deps.as_ref()).unwrap()
+        );
+    }
+
+    #[test]
+    fn bond_from_unknown_token_will_err() {
+        let mut deps = mock_dependencies(&[]);
+        deps.querier
+            .with_token_balances(&[(&VAULTS_ASSET_TOKEN.to_string(), &[])]);
+
+        init(deps.as_mut());
+
+        let bond_msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+            sender: BONDER1.to_string(),
+            amount: Uint128::from(BONDER1_AMOUNT),
+            msg: to_binary(&Cw20HookMsg::Bond {
+                proof: None,
+                recipient: None,
+                min_shares_out: None,
+            })
+            .unwrap(),
+        });
+
+        let info = mock_info("unknowntoken", &[]);
+        let res = execute(deps.as_mut(), mock_env(), info, bond_msg);
+
+        assert_eq!(res.unwrap_err(), ContractError::Unauthorized {});
+    }
+
+    #[test]
+    fn execute_revenue() {
+        let mut deps = mock_dependencies(&[]);
+        deps.querier.with_token_balances(&[
+            (
+                &VAULTS_ASSET_TOKEN.to_string(),
+                &[(&MOCK_CONTRACT_ADDR.to_string(), &Uint128::from(110u128))],
+            ),
+            (
+                &MOCK_CONTRACT_ADDR.to_string(),
+                &[(&OWNER.to_string(), &Uint128::zero())],
+            ),
+        ]);
+
+        init(deps.as_mut());
+
+        set_vaults(
+            deps.as_mut(),
+            Decimal256::from_uint256(Uint256::from(100u128)),
+        )
+        .unwrap();
+        set_execute_target(deps.as_mut(), &Addr::unchecked(VAULTS_ASSET_TOKEN), vec![]).unwrap();
+
+        let execute_msg: CosmosMsg<TerraMsgWrapper> = CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: VAULTS_ASSET_TOKEN.to_string(),
+            funds: vec![],
+            msg: to_binary(&Cw20ExecuteMsg::Burn {
+                amount: Uint128::zero(),
+            })
+            .unwrap(),
+        });
+
+        let msg = ExecuteMsg::Execute {
+            msgs: vec![to_binary(&execute_msg).unwrap()],
+            is_distribute: true,
+            max_loss: None,
+            min_profit: None,
+        };
+
+        let res = execute(deps.as_mut(), mock_env(), mock_info(OWNER, &[]), msg).unwrap();
+        assert_eq!(
+            res.messages,
+            vec![SubMsg::reply_on_success(execute_msg, MSG_REPLY_ID_EXECUTE)]
+        );
+
+        let reply_msg = Reply {
+            id: MSG_REPLY_ID_EXECUTE,
+            result: ContractResult::Ok(SubMsgExecutionResponse {
+                events: vec![],
+                data: Some(
+                    MsgExecuteContractResponse::new()
+                        .write_to_bytes()
+                        .unwrap()
+                        .into(),
+                ),
+            }),
+        };
+
+        let res = reply(deps.as_mut(), mock_env(), reply_msg).unwrap();
+        assert_eq!(
+            res.attributes,
+            vec![
+                attr("reply", "execute"),
+                attr("result", "success"),
+                attr(
+                    "revenue",
+                    Decimal256::from_uint256(Uint256::from(10u128)).to_string()
+                ),
+                attr("add_commission", Decimal256::one().to_string()),
+                attr("execution_id", "1"),
+            ]
+        );
+
+        assert_eq!(
+            Decimal256::from_uint256(Uint256::from(110u128)),
+            get_vaults(deps.as_ref()).unwrap()
+        );
+        assert_eq!(Decimal256::one(), get_commission(deps.as_ref()).unwrap());
+    }
+
+    #[test]
+    fn claim() {
+        let mut deps = mock_dependencies(&[]);
+        deps.querier
+            .with_token_balances(&[(&VAULTS_ASSET_TOKEN.to_string(), &[])]);
+
+        init(deps.as_mut());
+
+        add_vaults(
+            deps.as_mut(),
+            Decimal256::from_uint256(Uint256::from(5u128)),
+        )
+        .unwrap();
+        add_commission(
+            deps.as_mut(),
+            Decimal256::from_uint256(Uint256::from(5u128)),
+        )
+        .unwrap();
+
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(OWNER, &[]),
+            ExecuteMsg::Claim {},
+        )
+        .unwrap();
+
+        assert_eq!(
+            res.attributes,
+            vec![
+                attr("action", "claim"),
+                attr("claimer", OWNER.to_string()),
+                attr("asset", "5".to_string() + VAULTS_ASSET_TOKEN),
+            ]
+        );
+
+        assert_eq!(
+            res.messages,
+            vec![SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: VAULTS_ASSET_TOKEN.to_string(),
+                funds: vec![],
+                msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                    recipient: OWNER.to_string(),
+                    amount: Uint128::from(5u128),
+                })
+                .unwrap(),
+            }))]
+        );
+
+        assert_eq!(Decimal256::zero(), get_commission(deps.as_ref()).unwrap());
+    }
+
+    #[test]
+    fn unbond() {
+        let mut deps = mock_dependencies(&[]);
+        deps.querier.with_token_balances(&[
+            (&VAULTS_ASSET_TOKEN.to_string(), &[]),
+            (
+                &MOCK_CONTRACT_ADDR.to_string(),
+                &[(&BONDER1.to_string(), &Uint128::from(BONDER1_AMOUNT))],
+            ),
+        ]);
+
+        init(deps.as_mut());
+
+        set_vaults(
+            deps.as_mut(),
+            Decimal256::from_uint256(Uint256::from(120u128)),
+        )
+        .unwrap();
+
+        let unbond_msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+            sender: BONDER1.to_string(),
+            amount: Uint128::from(10u128),
+            msg: to_binary(&Cw20HookMsg::Unbond {
+                recipient: None,
+                min_assets_out: None,
+            })
+            .unwrap(),
+        });
+
+        let info = mock_info(MOCK_CONTRACT_ADDR, &[]);
+        let res = execute(deps.as_mut(), mock_env(), info, unbond_msg).unwrap();
+
+        assert_eq!(
+            res.attributes,
+            vec![
+                attr("action", "unbond"),
+                attr("unbonder", BONDER1.to_string()),
+                attr("recipient", BONDER1.to_string()),
+                // 10 * (120 + 1000) / (100 + 1000)
+                attr("asset", "10".to_string() + VAULTS_ASSET_TOKEN),
+                attr("burn_amount", "10"),
+            ]
+        );
+
+        assert_eq!(
+            res.messages,
+            vec![
+                SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+                    contract_addr: VAULTS_ASSET_TOKEN.to_string(),
+                    funds: vec![],
+                    msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                        recipient: BONDER1.to_string(),
+                        amount: Uint128::from(10u128),
+                    })
+                    .unwrap(),
+                })),
+                SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+                    contract_addr: MOCK_CONTRACT_ADDR.to_string(),
+                    funds: vec![],
+                    msg: to_binary(&Cw20ExecuteMsg::Burn {
+                        amount: Uint128::from(10u128),
+                    })
+                    .unwrap(),
+                })),
+            ]
+        );
+
+        assert_eq!(
+            Decimal256::from_uint256(Uint256::from(110u128)),
+            get_vaults(deps.as_ref()).unwrap()
+        );
+    }
+}
+
+#[cfg(test)]
+mod pause {
+    use super::*;
+    use crate::contract::testing::mock_instantiate_msg;
+    use cosmwasm_std::testing::{mock_env, mock_info, MOCK_CONTRACT_ADDR};
+    use cosmwasm_std::{attr, coins};
+    use starflet_protocol::mock_querier::mock_dependencies;
+    use terraswap::asset::AssetInfo::NativeToken;
+
+    static OWNER: &str = "owner0000";
+    static GUARDIAN: &str = "guardian0000";
+
+    static BONDER1: &str = "bonder0000";
+    static BONDER_AMOUNT: u128 = 100u128;
+
+    fn init(mut deps: DepsMut) {
+        let msg = InstantiateMsg {
+            guardian: Some(GUARDIAN.to_string()),
+            ..mock_instantiate_msg()
+        };
+
+        instantiate(deps.branch(), mock_env(), mock_info(OWNER, &[]), msg).unwrap();
+
+        let mut config = get_config(deps.as_ref()).unwrap();
+        config.token_address = Some(Addr::unchecked(MOCK_CONTRACT_ADDR));
+        set_config(deps, config).unwrap();
+    }
+
+    fn bond_msg() -> ExecuteMsg {
+        ExecuteMsg::Bond {
+            asset: Asset {
+                amount: Uint128::from(BONDER_AMOUNT),
+                info: NativeToken {
+                    denom: "uusd".to_string(),
+                },
+            },
+            proof: None,
+            recipient: None,
+            min_shares_out: None,
+        }
     }
-}
 
-pub fn query_stake_info(deps: Deps, staker_addr: String) -> StakerInfoResponse {
-    let config = get_config(deps).unwrap();
+    #[test]
+    fn set_pause() {
+        let mut deps = mock_dependencies(&[]);
+        init(deps.as_mut());
 
-    let staker = Addr::unchecked(staker_addr);
-    let vaults_token_address = config.token_address.unwrap();
+        let config = query_config(deps.as_ref()).unwrap();
+        assert_eq!(config.guardian, Some(GUARDIAN.to_string()));
 
-    let balance: Uint256 =
-        match query_token_balance(&deps.querier, vaults_token_address.clone(), staker) {
-            Ok(balance) => balance.into(),
-            Err(_) => Uint256::zero(),
+        let msg = ExecuteMsg::SetPause {
+            bond: Some(true),
+            unbond: None,
+            execute: Some(true),
+            claim: None,
         };
 
-    let rate = compute_share_rate(deps, vaults_token_address).unwrap();
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(BONDER1, &[]),
+            msg.clone(),
+        );
+        assert_eq!(res.unwrap_err(), ContractError::Unauthorized {});
 
-    StakerInfoResponse {
-        asset: Asset {
-            info: config.asset_info,
-            amount: rate.mul(balance).into(),
-        },
+        let res = execute(deps.as_mut(), mock_env(), mock_info(GUARDIAN, &[]), msg).unwrap();
+        assert_eq!(
+            res.attributes,
+            vec![
+                attr("action", "set_pause"),
+                attr("bond", "true"),
+                attr("execute", "true"),
+            ]
+        );
+
+        let res = query_pause_state(deps.as_ref()).unwrap();
+        assert_eq!(
+            res,
+            PauseStateResponse {
+                bond: true,
+                unbond: false,
+                execute: true,
+                claim: false,
+                shutdown: false,
+            }
+        );
+
+        let msg = ExecuteMsg::SetPause {
+            bond: Some(false),
+            unbond: None,
+            execute: None,
+            claim: None,
+        };
+        execute(deps.as_mut(), mock_env(), mock_info(OWNER, &[]), msg).unwrap();
+
+        let res = query_pause_state(deps.as_ref()).unwrap();
+        assert!(!res.bond);
+        assert!(res.execute);
     }
-}
 
-fn query_commission(deps: Deps) -> CommissionResponse {
-    let config = get_config(deps).unwrap();
+    #[test]
+    fn paused_operations() {
+        let mut deps = mock_dependencies(&[]);
+        deps.querier.with_token_balances(&[(
+            &MOCK_CONTRACT_ADDR.to_string(),
+            &[(&BONDER1.to_string(), &Uint128::from(0u128))],
+        )]);
+        init(deps.as_mut());
+
+        let msg = ExecuteMsg::SetPause {
+            bond: Some(true),
+            unbond: Some(true),
+            execute: Some(true),
+            claim: Some(true),
+        };
+        execute(deps.as_mut(), mock_env(), mock_info(OWNER, &[]), msg).unwrap();
 
-    let commission = get_commission(deps).unwrap();
+        let info = mock_info(BONDER1, &coins(BONDER_AMOUNT, "uusd"));
+        let res = execute(deps.as_mut(), mock_env(), info, bond_msg());
+        assert_eq!(
+            res.unwrap_err(),
+            ContractError::Paused {
+                operation: "bond".to_string()
+            }
+        );
 
-    CommissionResponse {
-        asset: Asset {
-            info: config.asset_info,
-            amount: (Uint256::one() * commission).into(),
-        },
+        let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+            sender: BONDER1.to_string(),
+            amount: Uint128::from(BONDER_AMOUNT),
+            msg: to_binary(&Cw20HookMsg::Unbond {
+                recipient: None,
+                min_assets_out: None,
+            })
+            .unwrap(),
+        });
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(MOCK_CONTRACT_ADDR, &[]),
+            msg,
+        );
+        assert_eq!(
+            res.unwrap_err(),
+            ContractError::Paused {
+                operation: "unbond".to_string()
+            }
+        );
+
+        let msg = ExecuteMsg::Execute {
+            msgs: vec![to_binary(&Cw20ExecuteMsg::Burn {
+                amount: Uint128::from(1u128),
+            })
+            .unwrap()],
+            is_distribute: false,
+            max_loss: None,
+            min_profit: None,
+        };
+        let res = execute(deps.as_mut(), mock_env(), mock_info(OWNER, &[]), msg);
+        assert_eq!(
+            res.unwrap_err(),
+            ContractError::Paused {
+                operation: "execute".to_string()
+            }
+        );
+
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(OWNER, &[]),
+            ExecuteMsg::Claim {},
+        );
+        assert_eq!(
+            res.unwrap_err(),
+            ContractError::Paused {
+                operation: "claim".to_string()
+            }
+        );
+
+        let msg = ExecuteMsg::SetPause {
+            bond: Some(false),
+            unbond: None,
+            execute: None,
+            claim: None,
+        };
+        execute(deps.as_mut(), mock_env(), mock_info(GUARDIAN, &[]), msg).unwrap();
+
+        let info = mock_info(BONDER1, &coins(BONDER_AMOUNT, "uusd"));
+        execute(deps.as_mut(), mock_env(), info, bond_msg()).unwrap();
     }
 }
 
-fn query_rate(deps: Deps) -> RateResponse {
-    let config = get_config(deps).unwrap();
+#[cfg(test)]
+mod executor {
+    use super::*;
+    use crate::contract::testing::mock_instantiate_msg;
+    use cosmwasm_std::attr;
+    use cosmwasm_std::testing::{mock_env, mock_info, MOCK_CONTRACT_ADDR};
+    use starflet_protocol::mock_querier::mock_dependencies;
+
+    static OWNER: &str = "owner0000";
+    static EXECUTOR: &str = "executor0000";
+
+    fn init(mut deps: DepsMut) {
+        let msg = mock_instantiate_msg();
+
+        instantiate(deps.branch(), mock_env(), mock_info(OWNER, &[]), msg).unwrap();
+
+        let mut config = get_config(deps.as_ref()).unwrap();
+        config.token_address = Some(Addr::unchecked(MOCK_CONTRACT_ADDR));
+        set_config(deps, config).unwrap();
+    }
+
+    fn execute_msg() -> ExecuteMsg {
+        ExecuteMsg::Execute {
+            msgs: vec![
+                to_binary(&CosmosMsg::<TerraMsgWrapper>::Wasm(WasmMsg::Execute {
+                    contract_addr: MOCK_CONTRACT_ADDR.to_string(),
+                    funds: vec![],
+                    msg: to_binary(&Cw20ExecuteMsg::Burn {
+                        amount: Uint128::from(1u128),
+                    })
+                    .unwrap(),
+                }))
+                .unwrap(),
+            ],
+            is_distribute: true,
+            max_loss: None,
+            min_profit: None,
+        }
+    }
+
+    #[test]
+    fn executor_execute() {
+        let mut deps = mock_dependencies(&[]);
+        init(deps.as_mut());
+        set_execute_target(deps.as_mut(), &Addr::unchecked(MOCK_CONTRACT_ADDR), vec![]).unwrap();
+
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(EXECUTOR, &[]),
+            execute_msg(),
+        );
+        assert_eq!(res.unwrap_err(), ContractError::Unauthorized {});
+
+        let msg = ExecuteMsg::UpdateExecutors {
+            add: vec![EXECUTOR.to_string()],
+            remove: vec![],
+        };
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(EXECUTOR, &[]),
+            msg.clone(),
+        );
+        assert_eq!(res.unwrap_err(), ContractError::Unauthorized {});
+
+        let res = execute(deps.as_mut(), mock_env(), mock_info(OWNER, &[]), msg).unwrap();
+        assert_eq!(
+            res.attributes,
+            vec![attr("action", "update_executors"), attr("add", EXECUTOR)]
+        );
+
+        let config = query_config(deps.as_ref()).unwrap();
+        assert_eq!(config.executors, vec![EXECUTOR.to_string()]);
+
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(EXECUTOR, &[]),
+            execute_msg(),
+        )
+        .unwrap();
+        assert_eq!(res.messages.len(), 1);
+        assert_eq!(res.messages[0].id, MSG_REPLY_ID_EXECUTE);
+
+        // executor can neither claim nor change config
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(EXECUTOR, &[]),
+            ExecuteMsg::Claim {},
+        );
+        assert_eq!(res.unwrap_err(), ContractError::Unauthorized {});
+
+        let msg = ExecuteMsg::UpdateConfig {
+            owner: Some(EXECUTOR.to_string()),
+            commission_rate: None,
+            code_id: None,
+            unbonding_period: None,
+            max_total_deposit: None,
+            max_deposit_per_user: None,
+            guardian: None,
+            max_rate_change: None,
+            config_delay: None,
+        };
+        let res = execute(deps.as_mut(), mock_env(), mock_info(EXECUTOR, &[]), msg);
+        assert_eq!(res.unwrap_err(), ContractError::Unauthorized {});
+
+        let msg = ExecuteMsg::UpdateExecutors {
+            add: vec![],
+            remove: vec![EXECUTOR.to_string()],
+        };
+        execute(deps.as_mut(), mock_env(), mock_info(OWNER, &[]), msg).unwrap();
 
-    let rate = compute_share_rate(deps, config.token_address.unwrap()).unwrap();
+        let config = query_config(deps.as_ref()).unwrap();
+        assert!(config.executors.is_empty());
 
-    RateResponse { rate }
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(EXECUTOR, &[]),
+            execute_msg(),
+        );
+        assert_eq!(res.unwrap_err(), ContractError::Unauthorized {});
+    }
 }
 
 #[cfg(test)]
-mod test_instantiate {
+mod execute_target {
     use super::*;
+    use crate::contract::testing::mock_instantiate_msg;
     use cosmwasm_std::testing::{mock_env, mock_info, MOCK_CONTRACT_ADDR};
-    use cosmwasm_std::{attr, WasmMsg};
+    use cosmwasm_std::{attr, coins, BankMsg};
     use starflet_protocol::mock_querier::mock_dependencies;
-    use terraswap::asset::AssetInfo::NativeToken;
-
-    use std::str::FromStr;
 
     static OWNER: &str = "owner0000";
-    static COMMISSION_RATE: &str = "0.1";
-    static SYMBOL: &str = "TTN";
     static CODE_ID: u64 = 123u64;
 
+    static TARGET: &str = "target0000";
+
+    fn init(mut deps: DepsMut) {
+        let msg = mock_instantiate_msg();
+
+        instantiate(deps.branch(), mock_env(), mock_info(OWNER, &[]), msg).unwrap();
+
+        let mut config = get_config(deps.as_ref()).unwrap();
+        config.token_address = Some(Addr::unchecked(MOCK_CONTRACT_ADDR));
+        set_config(deps, config).unwrap();
+    }
+
+    fn execute_msg(msg: CosmosMsg<TerraMsgWrapper>) -> ExecuteMsg {
+        ExecuteMsg::Execute {
+            msgs: vec![to_binary(&msg).unwrap()],
+            is_distribute: true,
+            max_loss: None,
+            min_profit: None,
+        }
+    }
+
+    fn wasm_execute(contract_addr: &str, msg: Binary) -> CosmosMsg<TerraMsgWrapper> {
+        CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: contract_addr.to_string(),
+            funds: vec![],
+            msg,
+        })
+    }
+
     #[test]
-    fn proper_initialization() {
+    fn reject_bank_send() {
         let mut deps = mock_dependencies(&[]);
-        let asset_info = NativeToken {
-            denom: "uusd".to_string(),
+        init(deps.as_mut());
+
+        let msg = execute_msg(CosmosMsg::Bank(BankMsg::Send {
+            to_address: OWNER.to_string(),
+            amount: coins(100, "uusd"),
+        }));
+        let res = execute(deps.as_mut(), mock_env(), mock_info(OWNER, &[]), msg);
+        assert_eq!(
+            res.unwrap_err(),
+            ContractError::ExecuteNotAllowed("bank".to_string())
+        );
+
+        let msg = execute_msg(CosmosMsg::Wasm(WasmMsg::Instantiate {
+            admin: None,
+            code_id: CODE_ID,
+            msg: to_binary(&Cw20ExecuteMsg::Burn {
+                amount: Uint128::zero(),
+            })
+            .unwrap(),
+            funds: vec![],
+            label: String::default(),
+        }));
+        let res = execute(deps.as_mut(), mock_env(), mock_info(OWNER, &[]), msg);
+        assert!(matches!(
+            res.unwrap_err(),
+            ContractError::ExecuteNotAllowed(_)
+        ));
+    }
+
+    #[test]
+    fn allowlisted_targets() {
+        let mut deps = mock_dependencies(&[]);
+        init(deps.as_mut());
+
+        let burn = to_binary(&Cw20ExecuteMsg::Burn {
+            amount: Uint128::zero(),
+        })
+        .unwrap();
+        let transfer = to_binary(&Cw20ExecuteMsg::Transfer {
+            recipient: OWNER.to_string(),
+            amount: Uint128::zero(),
+        })
+        .unwrap();
+
+        let msg = execute_msg(wasm_execute(TARGET, burn.clone()));
+        let res = execute(deps.as_mut(), mock_env(), mock_info(OWNER, &[]), msg);
+        assert_eq!(
+            res.unwrap_err(),
+            ContractError::ExecuteNotAllowed(TARGET.to_string())
+        );
+
+        let msg = ExecuteMsg::UpdateExecuteTargets {
+            add: vec![ExecuteTarget {
+                contract_addr: TARGET.to_string(),
+                messages: Some(vec!["burn".to_string()]),
+                allow_funds: None,
+            }],
+            remove: vec![],
         };
-        let msg = InstantiateMsg {
-            commission_rate: Decimal256::from_str(COMMISSION_RATE).unwrap(),
-            asset_info: asset_info.clone(),
-            token_code_id: CODE_ID,
-            symbol: SYMBOL.to_string(),
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(TARGET, &[]),
+            msg.clone(),
+        );
+        assert_eq!(res.unwrap_err(), ContractError::Unauthorized {});
+
+        let res = execute(deps.as_mut(), mock_env(), mock_info(OWNER, &[]), msg).unwrap();
+        assert_eq!(
+            res.attributes,
+            vec![
+                attr("action", "update_execute_targets"),
+                attr("add", TARGET)
+            ]
+        );
+
+        let res = query_execute_target(deps.as_ref(), TARGET.to_string()).unwrap();
+        assert_eq!(
+            res,
+            ExecuteTargetResponse {
+                allowed: true,
+                messages: Some(vec!["burn".to_string()]),
+                allow_funds: false,
+            }
+        );
+
+        let msg = execute_msg(wasm_execute(TARGET, burn));
+        let res = execute(deps.as_mut(), mock_env(), mock_info(OWNER, &[]), msg).unwrap();
+        assert_eq!(res.messages.len(), 1);
+
+        let msg = execute_msg(wasm_execute(TARGET, transfer));
+        let res = execute(deps.as_mut(), mock_env(), mock_info(OWNER, &[]), msg);
+        assert_eq!(
+            res.unwrap_err(),
+            ContractError::ExecuteNotAllowed(format!("transfer on {}", TARGET))
+        );
+
+        let msg = ExecuteMsg::UpdateExecuteTargets {
+            add: vec![],
+            remove: vec![TARGET.to_string()],
         };
+        execute(deps.as_mut(), mock_env(), mock_info(OWNER, &[]), msg).unwrap();
 
-        let info = mock_info(OWNER, &[]);
+        let res = query_execute_target(deps.as_ref(), TARGET.to_string()).unwrap();
+        assert!(!res.allowed);
+    }
 
-        let res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+    #[test]
+    fn reject_funds() {
+        let mut deps = mock_dependencies(&[]);
+        init(deps.as_mut());
+        set_execute_target(deps.as_mut(), &Addr::unchecked(TARGET), vec![]).unwrap();
+
+        let deposit = CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: TARGET.to_string(),
+            funds: coins(100, "uusd"),
+            msg: to_binary(&Cw20ExecuteMsg::Burn {
+                amount: Uint128::zero(),
+            })
+            .unwrap(),
+        });
+
+        let msg = execute_msg(deposit.clone());
+        let res = execute(deps.as_mut(), mock_env(), mock_info(OWNER, &[]), msg);
+        assert_eq!(
+            res.unwrap_err(),
+            ContractError::ExecuteNotAllowed(format!("funds to {}", TARGET))
+        );
+
+        let msg = ExecuteMsg::UpdateExecuteTargets {
+            add: vec![ExecuteTarget {
+                contract_addr: TARGET.to_string(),
+                messages: None,
+                allow_funds: Some(true),
+            }],
+            remove: vec![],
+        };
+        execute(deps.as_mut(), mock_env(), mock_info(OWNER, &[]), msg).unwrap();
+        assert!(
+            query_execute_target(deps.as_ref(), TARGET.to_string())
+                .unwrap()
+                .allow_funds
+        );
+
+        let msg = execute_msg(deposit);
+        let res = execute(deps.as_mut(), mock_env(), mock_info(OWNER, &[]), msg).unwrap();
+        assert_eq!(res.messages.len(), 1);
+    }
 
+    #[test]
+    fn timelocked_targets() {
+        let mut deps = mock_dependencies(&[]);
+        init(deps.as_mut());
+        set_execute_target(deps.as_mut(), &Addr::unchecked(OWNER), vec![]).unwrap();
+
+        let mut config = get_config(deps.as_ref()).unwrap();
+        config.config_delay = 100;
+        set_config(deps.as_mut(), config).unwrap();
+
+        // the addition waits for the delay, the removal does not
+        let env = mock_env();
+        let target = ExecuteTarget {
+            contract_addr: TARGET.to_string(),
+            messages: Some(vec!["burn".to_string()]),
+            allow_funds: None,
+        };
+        let msg = ExecuteMsg::UpdateExecuteTargets {
+            add: vec![target.clone()],
+            remove: vec![OWNER.to_string()],
+        };
+        let res = execute(deps.as_mut(), env.clone(), mock_info(OWNER, &[]), msg).unwrap();
+        let release_at = env.block.time.seconds() + 100;
         assert_eq!(
             res.attributes,
             vec![
-                attr("action", "instantiate"),
-                attr("owner", OWNER.to_string()),
-                attr("commission_rate", COMMISSION_RATE.to_string()),
+                attr("action", "update_execute_targets"),
+                attr("pending_add", TARGET),
+                attr("remove", OWNER),
+                attr("pending_release_at", release_at.to_string()),
             ]
         );
+        assert!(
+            !query_execute_target(deps.as_ref(), OWNER.to_string())
+                .unwrap()
+                .allowed
+        );
+        assert!(
+            !query_execute_target(deps.as_ref(), TARGET.to_string())
+                .unwrap()
+                .allowed
+        );
+
+        // a config change scheduled afterwards keeps the target
+        let msg = ExecuteMsg::UpdateConfig {
+            owner: None,
+            commission_rate: Some(Decimal256::percent(20)),
+            code_id: None,
+            unbonding_period: None,
+            max_total_deposit: None,
+            max_deposit_per_user: None,
+            guardian: None,
+            max_rate_change: None,
+            config_delay: None,
+        };
+        execute(deps.as_mut(), env.clone(), mock_info(OWNER, &[]), msg).unwrap();
+        let pending = query_pending_config(deps.as_ref()).unwrap();
+        assert_eq!(pending.execute_targets, vec![target]);
+        assert_eq!(pending.commission_rate, Some(Decimal256::percent(20)));
 
+        let res = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(OWNER, &[]),
+            ExecuteMsg::ApplyConfig {},
+        );
         assert_eq!(
-            res.messages,
-            vec![SubMsg::reply_on_success(
-                CosmosMsg::Wasm(WasmMsg::Instantiate {
-                    admin: Some(MOCK_CONTRACT_ADDR.to_string()),
-                    code_id: CODE_ID,
-                    funds: vec![],
-                    label: "".to_string(),
-                    msg: to_binary(&TokenInstantiateMsg {
-                        name: format!("{} vaults", SYMBOL.to_string()),
-                        symbol: format!("v{}", SYMBOL),
-                        decimals: 6u8,
-                        initial_balances: vec![],
-                        mint: Some(MinterResponse {
-                            minter: MOCK_CONTRACT_ADDR.to_string(),
-                            cap: None,
-                        }),
+            res.unwrap_err(),
+            ContractError::ConfigTimelocked { release_at }
+        );
+
+        let mut env = env;
+        env.block.time = env.block.time.plus_seconds(100);
+        let res = execute(
+            deps.as_mut(),
+            env,
+            mock_info(OWNER, &[]),
+            ExecuteMsg::ApplyConfig {},
+        )
+        .unwrap();
+        assert_eq!(
+            res.attributes,
+            vec![
+                attr("action", "apply_config"),
+                attr("add", TARGET),
+                attr("commission_rate", "0.2"),
+            ]
+        );
+        assert_eq!(
+            query_execute_target(deps.as_ref(), TARGET.to_string()).unwrap(),
+            ExecuteTargetResponse {
+                allowed: true,
+                messages: Some(vec!["burn".to_string()]),
+                allow_funds: false,
+            }
+        );
+    }
+
+    #[test]
+    fn batch_execute() {
+        let mut deps = mock_dependencies(&[]);
+        init(deps.as_mut());
+        set_execute_target(deps.as_mut(), &Addr::unchecked(TARGET), vec![]).unwrap();
+
+        let steps: Vec<CosmosMsg<TerraMsgWrapper>> = (1u128..4u128)
+            .map(|amount| {
+                wasm_execute(
+                    TARGET,
+                    to_binary(&Cw20ExecuteMsg::Burn {
+                        amount: Uint128::from(amount),
                     })
                     .unwrap(),
-                }),
-                MSG_REPLY_ID_TOKEN_INSTANT
-            )]
+                )
+            })
+            .collect();
+
+        let msg = ExecuteMsg::Execute {
+            msgs: vec![],
+            is_distribute: true,
+            max_loss: None,
+            min_profit: None,
+        };
+        let res = execute(deps.as_mut(), mock_env(), mock_info(OWNER, &[]), msg);
+        assert_eq!(res.unwrap_err(), ContractError::EmptyExecute {});
+
+        let msg = ExecuteMsg::Execute {
+            msgs: steps.iter().map(|step| to_binary(step).unwrap()).collect(),
+            is_distribute: true,
+            max_loss: None,
+            min_profit: None,
+        };
+        let res = execute(deps.as_mut(), mock_env(), mock_info(OWNER, &[]), msg).unwrap();
+        assert_eq!(
+            res.messages,
+            vec![
+                SubMsg::new(steps[0].clone()),
+                SubMsg::new(steps[1].clone()),
+                SubMsg::reply_on_success(steps[2].clone(), MSG_REPLY_ID_EXECUTE),
+            ]
         );
 
-        let res_config = query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap();
-        let config: ConfigResponse = from_binary(&res_config).unwrap();
-        assert_eq!(OWNER.to_string(), config.owner);
+        // a single disallowed step rejects the whole batch
+        let msg = ExecuteMsg::Execute {
+            msgs: vec![
+                to_binary(&steps[0]).unwrap(),
+                to_binary(&CosmosMsg::<TerraMsgWrapper>::Bank(BankMsg::Send {
+                    to_address: OWNER.to_string(),
+                    amount: coins(100, "uusd"),
+                }))
+                .unwrap(),
+            ],
+            is_distribute: true,
+            max_loss: None,
+            min_profit: None,
+        };
+        let res = execute(deps.as_mut(), mock_env(), mock_info(OWNER, &[]), msg);
         assert_eq!(
-            COMMISSION_RATE.to_string(),
-            config.commission_rate.to_string()
+            res.unwrap_err(),
+            ContractError::ExecuteNotAllowed("bank".to_string())
         );
-        assert_eq!(asset_info, config.asset_info);
-        assert_eq!(CODE_ID, config.token_code_id);
     }
 }
 
 #[cfg(test)]
-mod config {
+mod access {
     use super::*;
-    use cosmwasm_std::testing::{mock_env, mock_info};
+    use crate::contract::testing::mock_instantiate_msg;
+    use cosmwasm_std::testing::{mock_env, mock_info, MOCK_CONTRACT_ADDR};
+    use cosmwasm_std::{attr, coins};
+    use sha2::{Digest, Sha256};
     use starflet_protocol::mock_querier::mock_dependencies;
-    use terraswap::asset::AssetInfo::NativeToken;
-
-    use std::str::FromStr;
+    use terraswap::asset::AssetInfo::{NativeToken, Token};
 
     static OWNER: &str = "owner0000";
-    static COMMISSION_RATE: &str = "0.1";
-    static SYMBOL: &str = "TTN";
-    static CODE_ID: u64 = 123u64;
 
-    static CHANGE_OWNER: &str = "owner0001";
-    static CHANGE_COMMISSION_RATE: &str = "0.5";
-    static CHANGE_CODE_ID: u64 = 456u64;
+    static BONDER1: &str = "bonder0000";
+    static BONDER2: &str = "bonder0001";
+    static BONDER_AMOUNT: u128 = 100u128;
 
-    fn init(mut deps: DepsMut) {
-        let asset_info = NativeToken {
-            denom: "uusd".to_string(),
-        };
+    static VAULTS_ASSET_TOKEN: &str = "vaultsassettoken";
+
+    fn init(mut deps: DepsMut, asset_info: AssetInfo, access_mode: AccessMode) {
         let msg = InstantiateMsg {
-            commission_rate: Decimal256::from_str(COMMISSION_RATE).unwrap(),
             asset_info,
-            token_code_id: CODE_ID,
-            symbol: SYMBOL.to_string(),
+            access_mode: Some(access_mode),
+            ..mock_instantiate_msg()
         };
 
-        let info = mock_info(OWNER, &[]);
+        instantiate(deps.branch(), mock_env(), mock_info(OWNER, &[]), msg).unwrap();
 
-        instantiate(deps.branch(), mock_env(), info, msg).unwrap();
+        let mut config = get_config(deps.as_ref()).unwrap();
+        config.token_address = Some(Addr::unchecked(MOCK_CONTRACT_ADDR));
+        set_config(deps, config).unwrap();
+    }
+
+    fn bond_msg() -> ExecuteMsg {
+        ExecuteMsg::Bond {
+            asset: Asset {
+                amount: Uint128::from(BONDER_AMOUNT),
+                info: NativeToken {
+                    denom: "uusd".to_string(),
+                },
+            },
+            proof: None,
+            recipient: None,
+            min_shares_out: None,
+        }
+    }
+
+    fn hash(data: &[u8]) -> [u8; 32] {
+        Sha256::digest(data).into()
     }
 
     #[test]
-    fn update_config_owner() {
+    fn allowlist_bond() {
         let mut deps = mock_dependencies(&[]);
-        init(deps.as_mut());
+        deps.querier.with_token_balances(&[(
+            &MOCK_CONTRACT_ADDR.to_string(),
+            &[(&BONDER1.to_string(), &Uint128::from(0u128))],
+        )]);
 
-        let asset_info = NativeToken {
-            denom: "uusd".to_string(),
-        };
+        init(
+            deps.as_mut(),
+            NativeToken {
+                denom: "uusd".to_string(),
+            },
+            AccessMode::Allowlist,
+        );
 
-        let msg = ExecuteMsg::UpdateConfig {
-            owner: Some(CHANGE_OWNER.to_string()),
-            commission_rate: None,
-            code_id: None,
-        };
+        let info = mock_info(BONDER1, &coins(BONDER_AMOUNT, "uusd"));
+        let res = execute(deps.as_mut(), mock_env(), info, bond_msg());
+        assert_eq!(
+            res.unwrap_err(),
+            ContractError::NotAllowed(BONDER1.to_string())
+        );
 
-        let info = mock_info(OWNER, &[]);
-        execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        let msg = ExecuteMsg::UpdateAllowlist {
+            add: vec![BONDER1.to_string(), BONDER2.to_string()],
+            remove: vec![],
+        };
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(BONDER1, &[]),
+            msg.clone(),
+        );
+        assert_eq!(res.unwrap_err(), ContractError::Unauthorized {});
 
-        let res_config = query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap();
-        let config: ConfigResponse = from_binary(&res_config).unwrap();
-        assert_eq!(CHANGE_OWNER.to_string(), config.owner);
+        let res = execute(deps.as_mut(), mock_env(), mock_info(OWNER, &[]), msg).unwrap();
         assert_eq!(
-            COMMISSION_RATE.to_string(),
-            config.commission_rate.to_string()
+            res.attributes,
+            vec![
+                attr("action", "update_allowlist"),
+                attr("add", BONDER1),
+                attr("add", BONDER2),
+            ]
         );
-        assert_eq!(asset_info, config.asset_info);
-        assert_eq!(CODE_ID, config.token_code_id);
-    }
 
-    #[test]
-    #[should_panic]
-    fn update_config_diffrent_owner_will_panic() {
-        let mut deps = mock_dependencies(&[]);
-        init(deps.as_mut());
+        let info = mock_info(BONDER1, &coins(BONDER_AMOUNT, "uusd"));
+        execute(deps.as_mut(), mock_env(), info, bond_msg()).unwrap();
 
-        let msg = ExecuteMsg::UpdateConfig {
-            owner: Some(CHANGE_OWNER.to_string()),
-            commission_rate: None,
-            code_id: None,
+        let msg = ExecuteMsg::UpdateAllowlist {
+            add: vec![],
+            remove: vec![BONDER2.to_string()],
         };
+        execute(deps.as_mut(), mock_env(), mock_info(OWNER, &[]), msg).unwrap();
 
-        let info = mock_info(CHANGE_OWNER, &[]);
-        execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        let res = query_is_allowed(deps.as_ref(), BONDER1.to_string(), None).unwrap();
+        assert!(res.allowed);
+        let res = query_is_allowed(deps.as_ref(), BONDER2.to_string(), None).unwrap();
+        assert!(!res.allowed);
     }
 
     #[test]
-    fn update_config_multiple_commission_rate_and_owner() {
+    fn merkle_root_bond_with_received() {
         let mut deps = mock_dependencies(&[]);
-        init(deps.as_mut());
-
-        let change_asset_info = NativeToken {
-            denom: "uusd".to_string(),
-        };
+        deps.querier.with_token_balances(&[
+            (&VAULTS_ASSET_TOKEN.to_string(), &[]),
+            (
+                &MOCK_CONTRACT_ADDR.to_string(),
+                &[(&BONDER1.to_string(), &Uint128::from(0u128))],
+            ),
+        ]);
+
+        let leaf1 = hash(BONDER1.as_bytes());
+        let leaf2 = hash(BONDER2.as_bytes());
+        let mut pair = [leaf1, leaf2];
+        pair.sort_unstable();
+        let root = hex::encode(hash(&pair.concat()));
+
+        init(
+            deps.as_mut(),
+            Token {
+                contract_addr: VAULTS_ASSET_TOKEN.to_string(),
+            },
+            AccessMode::MerkleRoot { root },
+        );
 
-        let msg = ExecuteMsg::UpdateConfig {
-            owner: Some(CHANGE_OWNER.to_string()),
-            commission_rate: Some(Decimal256::from_str(CHANGE_COMMISSION_RATE).unwrap()),
-            code_id: None,
+        let bond_msg = |proof: Option<Vec<String>>| {
+            ExecuteMsg::Receive(Cw20ReceiveMsg {
+                sender: BONDER1.to_string(),
+                amount: Uint128::from(BONDER_AMOUNT),
+                msg: to_binary(&Cw20HookMsg::Bond {
+                    proof,
+                    recipient: None,
+                    min_shares_out: None,
+                })
+                .unwrap(),
+            })
         };
 
-        let info = mock_info(OWNER, &[]);
-        execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        let info = mock_info(VAULTS_ASSET_TOKEN, &[]);
+        let res = execute(deps.as_mut(), mock_env(), info.clone(), bond_msg(None));
+        assert_eq!(
+            res.unwrap_err(),
+            ContractError::NotAllowed(BONDER1.to_string())
+        );
 
-        let res_config = query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap();
-        let config: ConfigResponse = from_binary(&res_config).unwrap();
-        assert_eq!(CHANGE_OWNER.to_string(), config.owner);
+        let wrong_proof = Some(vec![hex::encode(hash(b"bonder0002"))]);
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            info.clone(),
+            bond_msg(wrong_proof),
+        );
         assert_eq!(
-            CHANGE_COMMISSION_RATE.to_string(),
-            config.commission_rate.to_string()
+            res.unwrap_err(),
+            ContractError::NotAllowed(BONDER1.to_string())
         );
-        assert_eq!(change_asset_info, config.asset_info);
-        assert_eq!(CODE_ID, config.token_code_id);
+
+        let proof = Some(vec![hex::encode(leaf2)]);
+        let res = query_is_allowed(deps.as_ref(), BONDER1.to_string(), proof.clone()).unwrap();
+        assert!(res.allowed);
+
+        execute(deps.as_mut(), mock_env(), info, bond_msg(proof)).unwrap();
     }
 
     #[test]
-    fn update_config_code_id() {
+    fn update_access_mode() {
         let mut deps = mock_dependencies(&[]);
-        init(deps.as_mut());
 
-        let asset_info = NativeToken {
-            denom: "uusd".to_string(),
+        init(
+            deps.as_mut(),
+            NativeToken {
+                denom: "uusd".to_string(),
+            },
+            AccessMode::Open,
+        );
+
+        let msg = ExecuteMsg::UpdateAccessMode {
+            access_mode: AccessMode::MerkleRoot {
+                root: "invalid".to_string(),
+            },
         };
+        let res = execute(deps.as_mut(), mock_env(), mock_info(OWNER, &[]), msg);
+        assert_eq!(
+            res.unwrap_err(),
+            ContractError::InvalidMerkleRoot("invalid".to_string())
+        );
 
-        let msg = ExecuteMsg::UpdateConfig {
-            owner: None,
-            commission_rate: None,
-            code_id: Some(CHANGE_CODE_ID),
+        let msg = ExecuteMsg::UpdateAccessMode {
+            access_mode: AccessMode::Allowlist,
         };
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(BONDER1, &[]),
+            msg.clone(),
+        );
+        assert_eq!(res.unwrap_err(), ContractError::Unauthorized {});
 
-        let info = mock_info(OWNER, &[]);
-        execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        let res = execute(deps.as_mut(), mock_env(), mock_info(OWNER, &[]), msg).unwrap();
+        assert_eq!(
+            res.attributes,
+            vec![
+                attr("action", "update_access_mode"),
+                attr("access_mode", "allowlist"),
+            ]
+        );
 
-        let res_config = query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap();
-        let config: ConfigResponse = from_binary(&res_config).unwrap();
-        assert_eq!(OWNER.to_string(), config.owner);
         assert_eq!(
-            COMMISSION_RATE.to_string(),
-            config.commission_rate.to_string()
+            query_config(deps.as_ref()).unwrap().access_mode,
+            AccessMode::Allowlist
         );
-        assert_eq!(asset_info, config.asset_info);
-        assert_eq!(CHANGE_CODE_ID, config.token_code_id);
     }
 }
 
 #[cfg(test)]
-mod reply {
+mod preview {
     use super::*;
-    use cosmwasm_std::testing::{mock_env, mock_info, MOCK_CONTRACT_ADDR};
-    use cosmwasm_std::{attr, coins, ContractResult, SubMsgExecutionResponse};
+    use crate::contract::testing::mock_instantiate_msg;
+    use cosmwasm_std::testing::{mock_env, mock_info};
+    use cosmwasm_std::{from_binary, Decimal};
+    use sha2::{Digest, Sha256};
     use starflet_protocol::mock_querier::mock_dependencies;
     use terraswap::asset::AssetInfo::NativeToken;
 
-    use crate::response::MsgExecuteContractResponse;
+    static OWNER: &str = "owner0000";
+    static BONDER0: &str = "bonder0000";
+    static BONDER1: &str = "bonder0001";
 
-    use std::str::FromStr;
+    static VAULTS_TOKEN_CONTRACT: &str = "vaults0000";
 
-    static OWNER: &str = "owner0000";
-    static COMMISSION_RATE: &str = "0.1";
-    static SYMBOL: &str = "TTN";
-    static CODE_ID: u64 = 123u64;
+    fn uusd() -> AssetInfo {
+        NativeToken {
+            denom: "uusd".to_string(),
+        }
+    }
 
+    // 199 shares backed by 200 uusd after commission
     fn init(mut deps: DepsMut) {
-        let asset_info = NativeToken {
-            denom: "uusd".to_string(),
-        };
-        let msg = InstantiateMsg {
-            commission_rate: Decimal256::from_str(COMMISSION_RATE).unwrap(),
-            asset_info,
-            token_code_id: CODE_ID,
-            symbol: SYMBOL.to_string(),
-        };
+        let msg = mock_instantiate_msg();
 
-        let info = mock_info(OWNER, &[]);
+        instantiate(deps.branch(), mock_env(), mock_info(OWNER, &[]), msg).unwrap();
 
-        instantiate(deps.branch(), mock_env(), info, msg).unwrap();
+        let mut config = get_config(deps.as_ref()).unwrap();
+        config.token_address = Some(Addr::unchecked(VAULTS_TOKEN_CONTRACT));
+        set_config(deps.branch(), config).unwrap();
+
+        set_vaults(deps.branch(), Decimal256::from_uint256(210u64)).unwrap();
+        add_commission(deps, Decimal256::from_uint256(10u64)).unwrap();
+    }
+
+    fn query_as<T: serde::de::DeserializeOwned>(deps: Deps, msg: QueryMsg) -> T {
+        from_binary(&query(deps, mock_env(), msg).unwrap()).unwrap()
     }
 
     #[test]
-    #[should_panic]
-    fn unknown_reply_id_will_panic() {
+    fn conversions() {
         let mut deps = mock_dependencies(&[]);
+        deps.querier.with_token_balances(&[(
+            &VAULTS_TOKEN_CONTRACT.to_string(),
+            &[
+                (&BONDER0.to_string(), &Uint128::from(100u128)),
+                (&BONDER1.to_string(), &Uint128::from(99u128)),
+            ],
+        )]);
         init(deps.as_mut());
 
-        let mut res = MsgInstantiateContractResponse::new();
-        res.set_contract_address(MOCK_CONTRACT_ADDR.to_string());
+        // 1000 * (199 + 1000) / (200 + 1000), rounded down
+        let res: ConvertToSharesResponse = query_as(
+            deps.as_ref(),
+            QueryMsg::ConvertToShares {
+                amount: Uint128::from(1000u128),
+            },
+        );
+        assert_eq!(res.shares, Uint128::from(999u128));
 
-        let reply_msg = Reply {
-            id: 123u64,
-            result: ContractResult::Ok(SubMsgExecutionResponse {
-                events: vec![],
-                data: Some(res.write_to_bytes().unwrap().into()),
-            }),
-        };
+        // 100 * (200 + 1000) / (199 + 1000), rounded down
+        let res: ConvertToAssetsResponse = query_as(
+            deps.as_ref(),
+            QueryMsg::ConvertToAssets {
+                shares: Uint128::from(100u128),
+            },
+        );
+        assert_eq!(
+            res.asset,
+            Asset {
+                info: uusd(),
+                amount: Uint128::from(100u128),
+            }
+        );
+
+        let res: TotalAssetsResponse = query_as(deps.as_ref(), QueryMsg::TotalAssets {});
+        assert_eq!(res.asset.amount, Uint128::from(200u128));
+    }
+
+    #[test]
+    fn previews() {
+        let mut deps = mock_dependencies(&[]);
+        deps.querier.with_token_balances(&[(
+            &VAULTS_TOKEN_CONTRACT.to_string(),
+            &[
+                (&BONDER0.to_string(), &Uint128::from(100u128)),
+                (&BONDER1.to_string(), &Uint128::from(99u128)),
+            ],
+        )]);
+        deps.querier.with_tax(
+            Decimal::percent(1),
+            &[(&"uusd".to_string(), &Uint128::from(1_000_000u128))],
+        );
+        init(deps.as_mut());
+
+        let res: PreviewBondResponse = query_as(
+            deps.as_ref(),
+            QueryMsg::PreviewBond {
+                asset: Asset {
+                    info: uusd(),
+                    amount: Uint128::from(1000u128),
+                },
+            },
+        );
+        assert_eq!(
+            res,
+            PreviewBondResponse {
+                shares: Uint128::from(999u128),
+                tax: Uint128::zero(),
+            }
+        );
+
+        // 1000 * (200 + 1000) / (199 + 1000) = 1000 redeemed, the payout pays 10 tax
+        let res: PreviewUnbondResponse = query_as(
+            deps.as_ref(),
+            QueryMsg::PreviewUnbond {
+                shares: Uint128::from(1000u128),
+            },
+        );
+        assert_eq!(
+            res,
+            PreviewUnbondResponse {
+                asset: Asset {
+                    info: uusd(),
+                    amount: Uint128::from(990u128),
+                },
+                tax: Uint128::from(10u128),
+            }
+        );
+
+        let res = query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::PreviewBond {
+                asset: Asset {
+                    info: NativeToken {
+                        denom: "uluna".to_string(),
+                    },
+                    amount: Uint128::from(1000u128),
+                },
+            },
+        );
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn max_bond_and_unbond() {
+        let mut deps = mock_dependencies(&[]);
+        deps.querier.with_token_balances(&[(
+            &VAULTS_TOKEN_CONTRACT.to_string(),
+            &[(&BONDER0.to_string(), &Uint128::from(100u128))],
+        )]);
+        init(deps.as_mut());
+
+        let res: MaxBondResponse = query_as(
+            deps.as_ref(),
+            QueryMsg::MaxBond {
+                address: BONDER0.to_string(),
+                proof: None,
+            },
+        );
+        assert_eq!(res.amount, None);
+
+        let res: MaxUnbondResponse = query_as(
+            deps.as_ref(),
+            QueryMsg::MaxUnbond {
+                address: BONDER0.to_string(),
+            },
+        );
+        assert_eq!(res.shares, Uint128::from(100u128));
+
+        let mut config = get_config(deps.as_ref()).unwrap();
+        config.max_total_deposit = Some(Uint128::from(500u128));
+        config.max_deposit_per_user = Some(Uint128::from(300u128));
+        set_config(deps.as_mut(), config).unwrap();
+
+        // per user 300 minus the 100 shares worth 100 * (200 + 1000) / (100 + 1000) = 109
+        let res: MaxBondResponse = query_as(
+            deps.as_ref(),
+            QueryMsg::MaxBond {
+                address: BONDER0.to_string(),
+                proof: None,
+            },
+        );
+        assert_eq!(res.amount, Some(Uint128::from(191u128)));
+
+        set_pause_state(
+            deps.as_mut(),
+            crate::state::PauseState {
+                bond: true,
+                unbond: true,
+                execute: false,
+                claim: false,
+            },
+        )
+        .unwrap();
+
+        let res: MaxBondResponse = query_as(
+            deps.as_ref(),
+            QueryMsg::MaxBond {
+                address: BONDER0.to_string(),
+                proof: None,
+            },
+        );
+        assert_eq!(res.amount, Some(Uint128::zero()));
 
-        reply(deps.as_mut(), mock_env(), reply_msg).unwrap();
+        let res: MaxUnbondResponse = query_as(
+            deps.as_ref(),
+            QueryMsg::MaxUnbond {
+                address: BONDER0.to_string(),
+            },
+        );
+        assert_eq!(res.shares, Uint128::zero());
     }
 
     #[test]
-    fn token_instant_reply() {
-        let mut deps = mock_dependencies(&coins(100u128, "uusd"));
+    fn max_bond_needs_access() {
+        let mut deps = mock_dependencies(&[]);
+        deps.querier
+            .with_token_balances(&[(&VAULTS_TOKEN_CONTRACT.to_string(), &[])]);
         init(deps.as_mut());
 
-        let mut res = MsgInstantiateContractResponse::new();
-        res.set_contract_address(MOCK_CONTRACT_ADDR.to_string());
+        let mut config = get_config(deps.as_ref()).unwrap();
+        config.access_mode = AccessMode::Allowlist;
+        set_config(deps.as_mut(), config).unwrap();
 
-        let reply_msg = Reply {
-            id: MSG_REPLY_ID_TOKEN_INSTANT,
-            result: ContractResult::Ok(SubMsgExecutionResponse {
-                events: vec![],
-                data: Some(res.write_to_bytes().unwrap().into()),
-            }),
+        let max_bond = |deps: Deps, proof: Option<Vec<String>>| -> MaxBondResponse {
+            query_as(
+                deps,
+                QueryMsg::MaxBond {
+                    address: BONDER0.to_string(),
+                    proof,
+                },
+            )
         };
+        assert_eq!(max_bond(deps.as_ref(), None).amount, Some(Uint128::zero()));
 
-        let res = reply(deps.as_mut(), mock_env(), reply_msg).unwrap();
+        add_allowlist(deps.as_mut(), &Addr::unchecked(BONDER0)).unwrap();
+        assert_eq!(max_bond(deps.as_ref(), None).amount, None);
 
-        assert_eq!(
-            res.attributes,
-            vec![
-                attr("reply", "token_instant"),
-                attr("token_address", MOCK_CONTRACT_ADDR),
-            ]
-        );
+        // a tree with the single leaf of the bonder, its root is the leaf hash
+        let mut config = get_config(deps.as_ref()).unwrap();
+        config.access_mode = AccessMode::MerkleRoot {
+            root: hex::encode(Sha256::digest(BONDER0.as_bytes())),
+        };
+        set_config(deps.as_mut(), config).unwrap();
 
-        let config = get_config(deps.as_ref()).unwrap();
-        assert_eq!(
-            config.token_address.unwrap().to_string(),
-            MOCK_CONTRACT_ADDR
-        )
+        assert_eq!(max_bond(deps.as_ref(), None).amount, Some(Uint128::zero()));
+        assert_eq!(max_bond(deps.as_ref(), Some(vec![])).amount, None);
     }
+}
 
-    #[test]
-    fn revenue_execute_reply() {
-        let mut deps = mock_dependencies(&coins(100u128, "uusd"));
-        init(deps.as_mut());
-
-        let mut res = MsgInstantiateContractResponse::new();
-        res.set_contract_address(MOCK_CONTRACT_ADDR.to_string());
+#[cfg(test)]
+mod errors {
+    use super::*;
+    use crate::contract::testing::mock_instantiate_msg;
+    use cosmwasm_std::testing::{mock_env, mock_info, MOCK_CONTRACT_ADDR};
+    use cosmwasm_std::{coins, from_binary};
+    use starflet_protocol::mock_querier::mock_dependencies;
+    use terraswap::asset::AssetInfo::NativeToken;
 
-        let reply_msg = Reply {
-            id: MSG_REPLY_ID_TOKEN_INSTANT,
-            result: ContractResult::Ok(SubMsgExecutionResponse {
-                events: vec![],
-                data: Some(res.write_to_bytes().unwrap().into()),
-            }),
-        };
-        reply(deps.as_mut(), mock_env(), reply_msg).unwrap();
+    static OWNER: &str = "owner0000";
+    static BONDER1: &str = "bonder0000";
 
-        let res = MsgExecuteContractResponse::new();
+    fn init(deps: DepsMut) {
+        let msg = mock_instantiate_msg();
 
-        let reply_msg = Reply {
-            id: MSG_REPLY_ID_EXECUTE,
-            result: ContractResult::Ok(SubMsgExecutionResponse {
-                events: vec![],
-                data: Some(res.write_to_bytes().unwrap().into()),
-            }),
-        };
+        instantiate(deps, mock_env(), mock_info(OWNER, &[]), msg).unwrap();
+    }
 
-        assert_eq!(Decimal256::zero(), get_vaults(deps.as_ref()).unwrap());
+    fn bond_msg() -> ExecuteMsg {
+        ExecuteMsg::Bond {
+            asset: Asset {
+                amount: Uint128::from(100u128),
+                info: NativeToken {
+                    denom: "uusd".to_string(),
+                },
+            },
+            proof: None,
+            recipient: None,
+            min_shares_out: None,
+        }
+    }
 
-        let res = reply(deps.as_mut(), mock_env(), reply_msg).unwrap();
+    #[test]
+    fn not_initialized() {
+        let mut deps = mock_dependencies(&[]);
 
-        assert_eq!(
-            res.attributes,
-            vec![
-                attr("reply", "execute"),
-                attr("result", "success"),
-                attr(
-                    "revenue",
-                    Decimal256::from_uint256(Uint256::from(100u128)).to_string()
-                ),
-                attr(
-                    "add_commission",
-                    Decimal256::from_uint256(Uint256::from(10u128)).to_string()
-                ),
-            ]
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(OWNER, &[]),
+            ExecuteMsg::Claim {},
         );
+        assert_eq!(res, Err(ContractError::NotInitialized {}));
 
-        assert_eq!(
-            Decimal256::from_uint256(Uint256::from(100u128)),
-            get_vaults(deps.as_ref()).unwrap()
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::Config {});
+        assert_eq!(res, Err(StdError::generic_err("Planet is not initialized")));
+    }
+
+    #[test]
+    fn token_not_instantiated() {
+        let mut deps = mock_dependencies(&[]);
+        init(deps.as_mut());
+
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(BONDER1, &coins(100, "uusd")),
+            bond_msg(),
         );
+        assert_eq!(res, Err(ContractError::TokenNotInstantiated {}));
 
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::Rate {});
         assert_eq!(
-            Decimal256::from_uint256(Uint256::from(10u128)),
-            get_commission(deps.as_ref()).unwrap()
+            res,
+            Err(StdError::generic_err("Vaults token is not instantiated"))
         );
     }
 
     #[test]
-    fn loss_execute_reply() {
-        let mut deps = mock_dependencies(&coins(100u128, "uusd"));
+    fn query_failed() {
+        let mut deps = mock_dependencies(&[]);
         init(deps.as_mut());
 
-        let mut res = MsgInstantiateContractResponse::new();
-        res.set_contract_address(MOCK_CONTRACT_ADDR.to_string());
-
-        let res = MsgExecuteContractResponse::new();
-        let reply_msg = Reply {
-            id: MSG_REPLY_ID_MUST_EXECUTE,
-            result: ContractResult::Ok(SubMsgExecutionResponse {
-                events: vec![],
-                data: Some(res.write_to_bytes().unwrap().into()),
-            }),
-        };
+        // the vaults token has no balances, so its supply query fails
+        let mut config = get_config(deps.as_ref()).unwrap();
+        config.token_address = Some(Addr::unchecked(MOCK_CONTRACT_ADDR));
+        set_config(deps.as_mut(), config).unwrap();
 
-        set_vaults(
-            deps.as_mut(),
-            Decimal256::from_uint256(Uint256::from(110u128)),
-        )
-        .unwrap();
-        add_commission(
+        let res = execute(
             deps.as_mut(),
-            Decimal256::from_uint256(Uint256::from(10u128)),
-        )
-        .unwrap();
-
-        let res = reply(deps.as_mut(), mock_env(), reply_msg).unwrap();
-
-        assert_eq!(
-            res.attributes,
-            vec![
-                attr("reply", "execute"),
-                attr("result", "fail"),
-                attr(
-                    "loss",
-                    Decimal256::from_uint256(Uint256::from(10u128)).to_string()
-                ),
-            ]
-        );
-
-        assert_eq!(
-            Decimal256::from_uint256(Uint256::from(100u128)),
-            get_vaults(deps.as_ref()).unwrap()
+            mock_env(),
+            mock_info(BONDER1, &coins(100, "uusd")),
+            bond_msg(),
         );
+        match res {
+            Err(ContractError::QueryFailed { contract, .. }) => {
+                assert_eq!(contract, MOCK_CONTRACT_ADDR)
+            }
+            res => panic!("unexpected {:?}", res),
+        }
 
-        assert_eq!(
-            Decimal256::from_uint256(Uint256::from(0u128)),
-            get_commission(deps.as_ref()).unwrap()
+        let res = query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::StakerInfo {
+                staker_addr: BONDER1.to_string(),
+            },
         );
+        assert!(res.is_err());
     }
 
     #[test]
-    fn skip_execute_reply() {
-        let mut deps = mock_dependencies(&coins(100u128, "uusd"));
+    fn insufficient_funds_is_an_error() {
+        let mut deps = mock_dependencies(&[]);
         init(deps.as_mut());
 
-        let mut res = MsgInstantiateContractResponse::new();
-        res.set_contract_address(MOCK_CONTRACT_ADDR.to_string());
-
-        let res = MsgExecuteContractResponse::new();
-
-        let reply_msg = Reply {
-            id: MSG_REPLY_ID_EXECUTE_SKIP,
-            result: ContractResult::Ok(SubMsgExecutionResponse {
-                events: vec![],
-                data: Some(res.write_to_bytes().unwrap().into()),
-            }),
-        };
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(BONDER1, &coins(99, "uusd")),
+            bond_msg(),
+        );
+        assert!(matches!(res, Err(ContractError::Std(_))));
+    }
 
-        let res = reply(deps.as_mut(), mock_env(), reply_msg).unwrap();
+    #[test]
+    fn to_uint128_overflow() {
+        let max = Uint256::from(u128::MAX);
+        assert_eq!(to_uint128(max).unwrap(), Uint128::new(u128::MAX));
 
-        assert_eq!(res.attributes, vec![attr("reply", "execute_skip"),]);
+        let res = to_uint128(max + Uint256::one());
+        assert!(matches!(res, Err(ContractError::Overflow(_))));
+    }
 
-        assert_eq!(
-            Decimal256::from_uint256(Uint256::from(0u128)),
-            get_vaults(deps.as_ref()).unwrap()
-        );
+    #[test]
+    fn config_query() {
+        let mut deps = mock_dependencies(&[]);
+        init(deps.as_mut());
 
-        assert_eq!(
-            Decimal256::from_uint256(Uint256::from(0u128)),
-            get_commission(deps.as_ref()).unwrap()
-        );
+        let res: ConfigResponse =
+            from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap()).unwrap();
+        assert_eq!(res.owner, OWNER);
     }
 }
 
 #[cfg(test)]
-mod bond {
+mod cost_basis {
     use super::*;
-    use cosmwasm_std::testing::{mock_env, mock_info, MOCK_CONTRACT_ADDR};
-    use cosmwasm_std::{attr, coins, ContractResult, SubMsgExecutionResponse};
-    use starflet_protocol::mock_querier::mock_dependencies;
-    use terraswap::asset::AssetInfo::{NativeToken, Token};
-
-    use crate::response::MsgInstantiateContractResponse;
-
-    use std::str::FromStr;
+    use crate::contract::testing::mock_instantiate_msg;
+    use cosmwasm_std::coins;
+    use cosmwasm_std::testing::{mock_env, mock_info};
+    use starflet_protocol::mock_querier::{mock_dependencies, WasmMockQuerier};
+    use terraswap::asset::AssetInfo::NativeToken;
 
     static OWNER: &str = "owner0000";
-    static COMMISSION_RATE: &str = "0.1";
-    static SYMBOL: &str = "TTN";
-    static CODE_ID: u64 = 123u64;
-
-    static BONDER1: &str = "bonder0000";
-    static BONDER1_AMOUNT: u128 = 100u128;
+    static BONDER0: &str = "bonder0000";
+    static BONDER1: &str = "bonder0001";
 
-    static VAULTS_ASSET_TOKEN: &str = "vaultsassettoken";
+    static VAULTS_TOKEN_CONTRACT: &str = "vaults0000";
 
     fn init(mut deps: DepsMut) {
-        let asset_info = NativeToken {
-            denom: "uusd".to_string(),
-        };
-        let msg = InstantiateMsg {
-            commission_rate: Decimal256::from_str(COMMISSION_RATE).unwrap(),
-            asset_info,
-            token_code_id: CODE_ID,
-            symbol: SYMBOL.to_string(),
-        };
-
-        let info = mock_info(OWNER, &[]);
-
-        instantiate(deps.branch(), mock_env(), info, msg).unwrap();
-
-        let mut res = MsgInstantiateContractResponse::new();
-        res.set_contract_address(MOCK_CONTRACT_ADDR.to_string());
+        let msg = mock_instantiate_msg();
 
-        let reply_msg = Reply {
-            id: MSG_REPLY_ID_TOKEN_INSTANT,
-            result: ContractResult::Ok(SubMsgExecutionResponse {
-                events: vec![],
-                data: Some(res.write_to_bytes().unwrap().into()),
-            }),
-        };
+        instantiate(deps.branch(), mock_env(), mock_info(OWNER, &[]), msg).unwrap();
 
-        reply(deps.branch(), mock_env(), reply_msg).unwrap();
+        let mut config = get_config(deps.as_ref()).unwrap();
+        config.token_address = Some(Addr::unchecked(VAULTS_TOKEN_CONTRACT));
+        set_config(deps, config).unwrap();
     }
 
-    fn init_cw20(mut deps: DepsMut) {
-        let asset_info = Token {
-            contract_addr: VAULTS_ASSET_TOKEN.to_string(),
-        };
-        let msg = InstantiateMsg {
-            commission_rate: Decimal256::from_str(COMMISSION_RATE).unwrap(),
-            asset_info,
-            token_code_id: CODE_ID,
-            symbol: SYMBOL.to_string(),
-        };
+    fn set_shares(querier: &mut WasmMockQuerier, shares: &[(&str, u128)]) {
+        let shares: Vec<(String, Uint128)> = shares
+            .iter()
+            .map(|(addr, amount)| (addr.to_string(), Uint128::from(*amount)))
+            .collect();
+        let balances: Vec<(&String, &Uint128)> =
+            shares.iter().map(|(addr, amount)| (addr, amount)).collect();
+        querier.with_token_balances(&[(&VAULTS_TOKEN_CONTRACT.to_string(), &balances)]);
+    }
 
-        let info = mock_info(OWNER, &[]);
+    fn unbond(deps: DepsMut, sender: &str, shares: u128) {
+        let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+            sender: sender.to_string(),
+            amount: Uint128::from(shares),
+            msg: to_binary(&Cw20HookMsg::Unbond {
+                recipient: None,
+                min_assets_out: None,
+            })
+            .unwrap(),
+        });
+        execute(deps, mock_env(), mock_info(VAULTS_TOKEN_CONTRACT, &[]), msg).unwrap();
+    }
 
-        instantiate(deps.branch(), mock_env(), info, msg).unwrap();
+    fn pnl(profit: u128, loss: u128) -> Pnl {
+        Pnl {
+            profit: Uint128::from(profit),
+            loss: Uint128::from(loss),
+        }
+    }
 
-        let mut res = MsgInstantiateContractResponse::new();
-        res.set_contract_address(MOCK_CONTRACT_ADDR.to_string());
+    #[test]
+    fn bond_unbond_and_transfer() {
+        let mut deps = mock_dependencies(&[]);
+        set_shares(&mut deps.querier, &[]);
+        init(deps.as_mut());
 
-        let reply_msg = Reply {
-            id: MSG_REPLY_ID_TOKEN_INSTANT,
-            result: ContractResult::Ok(SubMsgExecutionResponse {
-                events: vec![],
-                data: Some(res.write_to_bytes().unwrap().into()),
-            }),
+        let msg = ExecuteMsg::Bond {
+            asset: Asset {
+                info: NativeToken {
+                    denom: "uusd".to_string(),
+                },
+                amount: Uint128::from(100u128),
+            },
+            proof: None,
+            recipient: None,
+            min_shares_out: None,
         };
+        let info = mock_info(BONDER0, &coins(100, "uusd"));
+        execute(deps.as_mut(), mock_env(), info, msg).unwrap();
 
-        reply(deps.branch(), mock_env(), reply_msg).unwrap();
+        // revenue doubles the vaults, the virtual shares take most of it at this size
+        set_shares(&mut deps.querier, &[(BONDER0, 100)]);
+        set_vaults(deps.as_mut(), Decimal256::from_uint256(200u64)).unwrap();
+
+        // 100 * (200 + 1000) / (100 + 1000)
+        let res = query_stake_info(deps.as_ref(), BONDER0.to_string()).unwrap();
+        assert_eq!(res.asset.amount, Uint128::from(109u128));
+        assert_eq!(res.deposited, Uint128::from(100u128));
+        assert_eq!(res.cost_basis, Uint128::from(100u128));
+        assert_eq!(res.realized_pnl, pnl(0, 0));
+        assert_eq!(res.unrealized_pnl, pnl(9, 0));
+
+        // 20 shares sent away take their part of the cost with them
+        set_shares(&mut deps.querier, &[(BONDER0, 80), (BONDER1, 20)]);
+
+        let res = query_stake_info(deps.as_ref(), BONDER0.to_string()).unwrap();
+        assert_eq!(res.cost_basis, Uint128::from(80u128));
+        assert_eq!(res.unrealized_pnl, pnl(7, 0));
+
+        // and arrive without any cost
+        let res = query_stake_info(deps.as_ref(), BONDER1.to_string()).unwrap();
+        assert_eq!(res.deposited, Uint128::zero());
+        assert_eq!(res.cost_basis, Uint128::zero());
+        assert_eq!(res.unrealized_pnl, pnl(21, 0));
+
+        unbond(deps.as_mut(), BONDER1, 20);
+        set_shares(&mut deps.querier, &[(BONDER0, 80)]);
+
+        let res = query_stake_info(deps.as_ref(), BONDER1.to_string()).unwrap();
+        assert_eq!(res.withdrawn, Uint128::from(21u128));
+        assert_eq!(res.realized_pnl, pnl(21, 0));
+
+        // 40 of 100 tracked shares, 40 * (179 + 1000) / (80 + 1000) uusd for a cost of 40
+        unbond(deps.as_mut(), BONDER0, 40);
+        set_shares(&mut deps.querier, &[(BONDER0, 40)]);
+
+        let res = query_stake_info(deps.as_ref(), BONDER0.to_string()).unwrap();
+        assert_eq!(res.asset.amount, Uint128::from(43u128));
+        assert_eq!(res.deposited, Uint128::from(100u128));
+        assert_eq!(res.withdrawn, Uint128::from(43u128));
+        assert_eq!(res.cost_basis, Uint128::from(40u128));
+        assert_eq!(res.realized_pnl, pnl(3, 0));
+        assert_eq!(res.unrealized_pnl, pnl(3, 0));
     }
 
     #[test]
-    fn normal_bond() {
+    fn loss() {
         let mut deps = mock_dependencies(&[]);
-        deps.querier.with_token_balances(&[(
-            &MOCK_CONTRACT_ADDR.to_string(),
-            &[(&BONDER1.to_string(), &Uint128::from(0u128))],
-        )]);
-
+        set_shares(&mut deps.querier, &[]);
         init(deps.as_mut());
 
-        let bond_asset = Asset {
-            amount: Uint128::from(BONDER1_AMOUNT),
-            info: NativeToken {
-                denom: "uusd".to_string(),
+        let msg = ExecuteMsg::Bond {
+            asset: Asset {
+                info: NativeToken {
+                    denom: "uusd".to_string(),
+                },
+                amount: Uint128::from(100u128),
             },
+            proof: None,
+            recipient: None,
+            min_shares_out: None,
         };
+        let info = mock_info(BONDER0, &coins(100, "uusd"));
+        execute(deps.as_mut(), mock_env(), info, msg).unwrap();
 
-        let bond_msg = ExecuteMsg::Bond {
-            asset: bond_asset.clone(),
-        };
-
-        let info = mock_info(BONDER1, &coins(100, "uusd"));
+        set_shares(&mut deps.querier, &[(BONDER0, 100)]);
+        set_vaults(deps.as_mut(), Decimal256::from_uint256(50u64)).unwrap();
 
-        let res = execute(deps.as_mut(), mock_env(), info, bond_msg).unwrap();
+        // 50 shares redeem 25, half of the cost
+        unbond(deps.as_mut(), BONDER0, 50);
+        set_shares(&mut deps.querier, &[(BONDER0, 50)]);
 
-        assert_eq!(
-            res.attributes,
-            vec![
-                attr("action", "bond"),
-                attr("bonder", BONDER1.to_string()),
-                attr("asset", bond_asset.to_string()),
-                attr("mint_amount", bond_asset.amount.to_string()),
-            ]
-        );
+        let res = query_stake_info(deps.as_ref(), BONDER0.to_string()).unwrap();
+        assert_eq!(res.withdrawn, Uint128::from(25u128));
+        assert_eq!(res.cost_basis, Uint128::from(50u128));
+        assert_eq!(res.realized_pnl, pnl(0, 25));
+        assert_eq!(res.unrealized_pnl, pnl(0, 25));
+    }
+}
 
-        assert_eq!(
-            res.messages,
-            vec![SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
-                contract_addr: MOCK_CONTRACT_ADDR.to_string(),
-                funds: vec![],
-                msg: to_binary(&Cw20ExecuteMsg::Mint {
-                    recipient: BONDER1.to_string(),
-                    amount: bond_asset.amount,
-                })
-                .unwrap(),
-            }))]
-        );
+#[cfg(test)]
+mod stats {
+    use super::*;
+    use crate::contract::testing::mock_instantiate_msg;
+    use cosmwasm_std::testing::{mock_env, mock_info, MOCK_CONTRACT_ADDR};
+    use cosmwasm_std::{coins, ContractResult, SubMsgExecutionResponse};
+    use starflet_protocol::mock_querier::mock_dependencies;
+    use terraswap::asset::AssetInfo::NativeToken;
 
-        assert_eq!(
-            Decimal256::from_uint256(Uint256::from(100u128)),
-            get_vaults(deps.as_ref()).unwrap()
-        );
-    }
+    static OWNER: &str = "owner0000";
+    static BONDER0: &str = "bonder0000";
+    static BONDER1: &str = "bonder0001";
 
-    #[test]
-    fn normal_bond_with_received() {
-        let mut deps = mock_dependencies(&[]);
-        deps.querier.with_token_balances(&[(
-            &MOCK_CONTRACT_ADDR.to_string(),
-            &[(&BONDER1.to_string(), &Uint128::from(0u128))],
-        )]);
+    static VAULTS_TOKEN_CONTRACT: &str = "vaults0000";
 
-        init_cw20(deps.as_mut());
+    fn init(mut deps: DepsMut) {
+        let msg = mock_instantiate_msg();
 
-        let bond_msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
-            sender: BONDER1.to_string(),
-            amount: Uint128::from(BONDER1_AMOUNT),
-            msg: to_binary(&Cw20HookMsg::Bond {}).unwrap(),
-        });
+        instantiate(deps.branch(), mock_env(), mock_info(OWNER, &[]), msg).unwrap();
 
-        let info = mock_info(VAULTS_ASSET_TOKEN, &[]);
+        let mut config = get_config(deps.as_ref()).unwrap();
+        config.token_address = Some(Addr::unchecked(VAULTS_TOKEN_CONTRACT));
+        set_config(deps, config).unwrap();
+    }
 
-        let res = execute(deps.as_mut(), mock_env(), info, bond_msg).unwrap();
+    fn bond(deps: DepsMut, bonder: &str, amount: u128) {
+        let msg = ExecuteMsg::Bond {
+            asset: Asset {
+                info: NativeToken {
+                    denom: "uusd".to_string(),
+                },
+                amount: Uint128::from(amount),
+            },
+            proof: None,
+            recipient: None,
+            min_shares_out: None,
+        };
+        let info = mock_info(bonder, &coins(amount, "uusd"));
+        execute(deps, mock_env(), info, msg).unwrap();
+    }
 
-        assert_eq!(
-            res.attributes,
-            vec![
-                attr("action", "bond"),
-                attr("bonder", BONDER1.to_string()),
-                attr(
-                    "asset",
-                    BONDER1_AMOUNT.to_string() + &VAULTS_ASSET_TOKEN.to_string()
-                ),
-                attr("mint_amount", BONDER1_AMOUNT.to_string()),
-            ]
-        );
+    fn execute_reply(deps: DepsMut, height: u64) {
+        let mut env = mock_env();
+        env.block.height = height;
 
-        assert_eq!(
-            Decimal256::from_uint256(Uint256::from(BONDER1_AMOUNT)),
-            get_vaults(deps.as_ref()).unwrap()
-        );
+        let reply_msg = Reply {
+            id: MSG_REPLY_ID_EXECUTE,
+            result: ContractResult::Ok(SubMsgExecutionResponse {
+                events: vec![],
+                data: None,
+            }),
+        };
+        reply(deps, env, reply_msg).unwrap();
     }
 
     #[test]
-    #[should_panic]
-    fn insufficient_bond_will_panic() {
+    fn counters() {
         let mut deps = mock_dependencies(&[]);
+        deps.querier
+            .with_token_balances(&[(&VAULTS_TOKEN_CONTRACT.to_string(), &[])]);
+        init(deps.as_mut());
+
+        let res = query_stats(deps.as_ref()).unwrap();
+        assert_eq!(res.execute_count, 0);
+        assert_eq!(res.first_execution_height, None);
+
+        // bonding twice still counts one depositor
+        bond(deps.as_mut(), BONDER0, 100);
+        bond(deps.as_mut(), BONDER0, 50);
+        bond(deps.as_mut(), BONDER1, 50);
         deps.querier.with_token_balances(&[(
-            &MOCK_CONTRACT_ADDR.to_string(),
-            &[(&BONDER1.to_string(), &Uint128::from(0u128))],
+            &VAULTS_TOKEN_CONTRACT.to_string(),
+            &[
+                (&BONDER0.to_string(), &Uint128::from(150u128)),
+                (&BONDER1.to_string(), &Uint128::from(50u128)),
+            ],
         )]);
 
-        let bond_asset = Asset {
-            amount: Uint128::from(BONDER1_AMOUNT),
-            info: NativeToken {
-                denom: "uusd".to_string(),
-            },
-        };
+        deps.querier
+            .with_balance(&[(&MOCK_CONTRACT_ADDR.to_string(), coins(220, "uusd"))]);
+        execute_reply(deps.as_mut(), 100);
+        deps.querier
+            .with_balance(&[(&MOCK_CONTRACT_ADDR.to_string(), coins(240, "uusd"))]);
+        execute_reply(deps.as_mut(), 200);
 
-        let bond_msg = ExecuteMsg::Bond { asset: bond_asset };
+        let unbond_msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+            sender: BONDER1.to_string(),
+            amount: Uint128::from(50u128),
+            msg: to_binary(&Cw20HookMsg::Unbond {
+                recipient: None,
+                min_assets_out: None,
+            })
+            .unwrap(),
+        });
+        let info = mock_info(VAULTS_TOKEN_CONTRACT, &[]);
+        execute(deps.as_mut(), mock_env(), info, unbond_msg).unwrap();
 
-        let info = mock_info(BONDER1, &coins(99, "uusd"));
+        try_claim(deps.as_mut(), mock_info(OWNER, &[])).unwrap();
 
-        execute(deps.as_mut(), mock_env(), info, bond_msg).unwrap();
+        // 40 revenue with 4 commission, 50 * (236 + 1000) / (200 + 1000) unbonded
+        assert_eq!(
+            query_stats(deps.as_ref()).unwrap(),
+            StatsResponse {
+                total_bonded: Uint128::from(200u128),
+                total_unbonded: Uint128::from(51u128),
+                total_revenue: Decimal256::from_uint256(40u64),
+                total_commission: Decimal256::from_uint256(4u64),
+                total_commission_claimed: Uint128::from(4u128),
+                total_loss: Decimal256::zero(),
+                execute_count: 2,
+                sync_count: 0,
+                synced_revenue: Decimal256::zero(),
+                synced_commission: Decimal256::zero(),
+                synced_loss: Decimal256::zero(),
+                depositor_count: 2,
+                first_execution_height: Some(100),
+                last_execution_height: Some(200),
+            }
+        );
     }
 }
 
 #[cfg(test)]
-mod unbond {
+mod sync {
     use super::*;
-    use crate::response::MsgInstantiateContractResponse;
+    use crate::contract::testing::mock_instantiate_msg;
     use cosmwasm_std::testing::{mock_env, mock_info, MOCK_CONTRACT_ADDR};
-    use cosmwasm_std::{attr, coins, ContractResult, SubMsgExecutionResponse};
+    use cosmwasm_std::{coins, ContractResult, SubMsgExecutionResponse};
     use starflet_protocol::mock_querier::mock_dependencies;
     use terraswap::asset::AssetInfo::NativeToken;
 
-    use std::str::FromStr;
-
     static OWNER: &str = "owner0000";
-    static COMMISSION_RATE: &str = "0.1";
-    static SYMBOL: &str = "TTN";
-    static CODE_ID: u64 = 123u64;
+    static BONDER: &str = "bonder0000";
+    static STRANGER: &str = "stranger0000";
 
-    static BONDER1: &str = "bonder0000";
-    static BONDER1_AMOUNT: u128 = 100u128;
-    static UNBONDER1_AMOUNT: u128 = 10u128;
+    static VAULTS_TOKEN_CONTRACT: &str = "vaults0000";
 
     fn init(mut deps: DepsMut) {
-        let asset_info = NativeToken {
-            denom: "uusd".to_string(),
-        };
-        let msg = InstantiateMsg {
-            commission_rate: Decimal256::from_str(COMMISSION_RATE).unwrap(),
-            asset_info,
-            token_code_id: CODE_ID,
-            symbol: SYMBOL.to_string(),
-        };
-
-        let info = mock_info(OWNER, &[]);
+        let msg = mock_instantiate_msg();
 
-        instantiate(deps.branch(), mock_env(), info, msg).unwrap();
+        instantiate(deps.branch(), mock_env(), mock_info(OWNER, &[]), msg).unwrap();
 
-        let mut res = MsgInstantiateContractResponse::new();
-        res.set_contract_address(MOCK_CONTRACT_ADDR.to_string());
+        let mut config = get_config(deps.as_ref()).unwrap();
+        config.token_address = Some(Addr::unchecked(VAULTS_TOKEN_CONTRACT));
+        set_config(deps.branch(), config).unwrap();
 
-        let reply_msg = Reply {
-            id: MSG_REPLY_ID_TOKEN_INSTANT,
-            result: ContractResult::Ok(SubMsgExecutionResponse {
-                events: vec![],
-                data: Some(res.write_to_bytes().unwrap().into()),
-            }),
+        let msg = ExecuteMsg::Bond {
+            asset: Asset {
+                info: NativeToken {
+                    denom: "uusd".to_string(),
+                },
+                amount: Uint128::from(100u128),
+            },
+            proof: None,
+            recipient: None,
+            min_shares_out: None,
         };
+        let info = mock_info(BONDER, &coins(100, "uusd"));
+        execute(deps, mock_env(), info, msg).unwrap();
+    }
 
-        reply(deps.branch(), mock_env(), reply_msg).unwrap();
+    fn sync(
+        deps: DepsMut,
+        sender: &str,
+        absorb_shortfall: Option<bool>,
+    ) -> Result<Response<TerraMsgWrapper>, ContractError> {
+        let msg = ExecuteMsg::Sync { absorb_shortfall };
+        execute(deps, mock_env(), mock_info(sender, &[]), msg)
+    }
 
-        let bond_asset = Asset {
-            amount: Uint128::from(BONDER1_AMOUNT),
-            info: NativeToken {
-                denom: "uusd".to_string(),
-            },
-        };
+    #[test]
+    fn surplus() {
+        let mut deps = mock_dependencies(&[]);
+        deps.querier
+            .with_token_balances(&[(&VAULTS_TOKEN_CONTRACT.to_string(), &[])]);
+        init(deps.as_mut());
+
+        deps.querier
+            .with_balance(&[(&MOCK_CONTRACT_ADDR.to_string(), coins(110, "uusd"))]);
+        assert_eq!(
+            query_health(&Hold, deps.as_ref(), mock_env()).unwrap(),
+            HealthResponse {
+                tracked: Decimal256::from_uint256(100u64),
+                actual: Decimal256::from_uint256(110u64),
+                surplus: Decimal256::from_uint256(10u64),
+                shortfall: Decimal256::zero(),
+                synced_revenue: Decimal256::zero(),
+                synced_loss: Decimal256::zero(),
+            }
+        );
 
-        let bond_msg = ExecuteMsg::Bond { asset: bond_asset };
+        let res = sync(deps.as_mut(), STRANGER, None).unwrap_err();
+        assert_eq!(res, ContractError::Unauthorized {});
 
-        let info = mock_info(BONDER1, &coins(100, "uusd"));
+        let res = sync(deps.as_mut(), OWNER, None).unwrap();
+        assert_eq!(
+            res.attributes,
+            vec![
+                attr("action", "sync"),
+                attr("result", "surplus"),
+                attr("revenue", "10"),
+                attr("add_commission", "1"),
+            ]
+        );
 
-        execute(deps, mock_env(), info, bond_msg).unwrap();
+        assert_eq!(
+            get_vaults(deps.as_ref()).unwrap(),
+            Decimal256::from_uint256(110u64)
+        );
+        assert_eq!(get_commission(deps.as_ref()).unwrap(), Decimal256::one());
+
+        // booked as revenue but not as an execution
+        let stats = query_stats(deps.as_ref()).unwrap();
+        assert_eq!(stats.total_revenue, Decimal256::zero());
+        assert_eq!(stats.execute_count, 0);
+        assert_eq!(stats.sync_count, 1);
+        assert_eq!(stats.synced_revenue, Decimal256::from_uint256(10u64));
+        assert_eq!(stats.synced_commission, Decimal256::one());
+        assert_eq!(
+            query_health(&Hold, deps.as_ref(), mock_env())
+                .unwrap()
+                .synced_revenue,
+            Decimal256::from_uint256(10u64)
+        );
+
+        let res = sync(deps.as_mut(), OWNER, None).unwrap();
+        assert_eq!(
+            res.attributes,
+            vec![attr("action", "sync"), attr("result", "in_sync")]
+        );
     }
 
     #[test]
-    fn normal_unbond() {
+    fn claim_stays_in_sync() {
         let mut deps = mock_dependencies(&[]);
-        deps.querier.with_token_balances(&[(
-            &MOCK_CONTRACT_ADDR.to_string(),
-            &[(&BONDER1.to_string(), &Uint128::from(0u128))],
-        )]);
+        deps.querier
+            .with_token_balances(&[(&VAULTS_TOKEN_CONTRACT.to_string(), &[])]);
+        init(deps.as_mut());
+
+        deps.querier
+            .with_balance(&[(&MOCK_CONTRACT_ADDR.to_string(), coins(120, "uusd"))]);
+        sync(deps.as_mut(), OWNER, None).unwrap();
+        assert_eq!(
+            get_commission(deps.as_ref()).unwrap(),
+            Decimal256::from_uint256(2u64)
+        );
+
+        let msg = ExecuteMsg::Claim {};
+        execute(deps.as_mut(), mock_env(), mock_info(OWNER, &[]), msg).unwrap();
+
+        // the claim left the planet
+        deps.querier
+            .with_balance(&[(&MOCK_CONTRACT_ADDR.to_string(), coins(118, "uusd"))]);
+        assert_eq!(
+            query_health(&Hold, deps.as_ref(), mock_env()).unwrap(),
+            HealthResponse {
+                tracked: Decimal256::from_uint256(118u64),
+                actual: Decimal256::from_uint256(118u64),
+                surplus: Decimal256::zero(),
+                shortfall: Decimal256::zero(),
+                synced_revenue: Decimal256::from_uint256(20u64),
+                synced_loss: Decimal256::zero(),
+            }
+        );
+    }
 
+    #[test]
+    fn shortfall() {
+        let mut deps = mock_dependencies(&[]);
+        deps.querier
+            .with_token_balances(&[(&VAULTS_TOKEN_CONTRACT.to_string(), &[])]);
         init(deps.as_mut());
 
-        let unbond_msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
-            sender: BONDER1.to_string(),
-            amount: Uint128::from(UNBONDER1_AMOUNT),
-            msg: to_binary(&Cw20HookMsg::Unbond {}).unwrap(),
-        });
+        deps.querier
+            .with_balance(&[(&MOCK_CONTRACT_ADDR.to_string(), coins(110, "uusd"))]);
+        sync(deps.as_mut(), OWNER, None).unwrap();
 
-        let info = mock_info(MOCK_CONTRACT_ADDR, &[]);
+        deps.querier
+            .with_balance(&[(&MOCK_CONTRACT_ADDR.to_string(), coins(100, "uusd"))]);
+        assert_eq!(
+            query_health(&Hold, deps.as_ref(), mock_env())
+                .unwrap()
+                .shortfall,
+            Decimal256::from_uint256(10u64)
+        );
 
-        let res = execute(deps.as_mut(), mock_env(), info, unbond_msg).unwrap();
+        // only reported by default
+        let res = sync(deps.as_mut(), OWNER, None).unwrap();
+        assert_eq!(
+            res.attributes,
+            vec![
+                attr("action", "sync"),
+                attr("result", "shortfall"),
+                attr("shortfall", "10"),
+            ]
+        );
+        assert_eq!(
+            get_vaults(deps.as_ref()).unwrap(),
+            Decimal256::from_uint256(110u64)
+        );
+
+        let msg = ExecuteMsg::UpdateExecutors {
+            add: vec![STRANGER.to_string()],
+            remove: vec![],
+        };
+        execute(deps.as_mut(), mock_env(), mock_info(OWNER, &[]), msg).unwrap();
+        let res = sync(deps.as_mut(), STRANGER, Some(true)).unwrap_err();
+        assert_eq!(res, ContractError::Unauthorized {});
 
+        let res = sync(deps.as_mut(), OWNER, Some(true)).unwrap();
         assert_eq!(
             res.attributes,
             vec![
-                attr("action", "unbond"),
-                attr("unbonder", BONDER1.to_string()),
-                attr("asset", UNBONDER1_AMOUNT.to_string() + &"uusd".to_string()),
-                attr("burn_amount", UNBONDER1_AMOUNT.to_string()),
+                attr("action", "sync"),
+                attr("result", "absorbed"),
+                attr("loss", "10"),
+                attr("commission_loss", "1"),
+                attr("staker_loss", "9"),
             ]
         );
 
-        assert_eq!(
-            Decimal256::from_uint256(Uint256::from(90u128)),
-            get_vaults(deps.as_ref()).unwrap()
-        );
+        assert_eq!(
+            get_vaults(deps.as_ref()).unwrap(),
+            Decimal256::from_uint256(100u64)
+        );
+        assert_eq!(get_commission(deps.as_ref()).unwrap(), Decimal256::zero());
+        let stats = query_stats(deps.as_ref()).unwrap();
+        assert_eq!(stats.total_loss, Decimal256::zero());
+        assert_eq!(stats.sync_count, 2);
+        assert_eq!(stats.synced_loss, Decimal256::from_uint256(10u64));
+        assert_eq!(
+            query_health(&Hold, deps.as_ref(), mock_env())
+                .unwrap()
+                .shortfall,
+            Decimal256::zero()
+        );
+    }
+
+    #[test]
+    fn executions_match_their_records() {
+        let mut deps = mock_dependencies(&[]);
+        deps.querier
+            .with_token_balances(&[(&VAULTS_TOKEN_CONTRACT.to_string(), &[])]);
+        init(deps.as_mut());
+
+        deps.querier
+            .with_balance(&[(&MOCK_CONTRACT_ADDR.to_string(), coins(110, "uusd"))]);
+        sync(deps.as_mut(), OWNER, None).unwrap();
+
+        deps.querier
+            .with_balance(&[(&MOCK_CONTRACT_ADDR.to_string(), coins(130, "uusd"))]);
+        let reply_msg = Reply {
+            id: MSG_REPLY_ID_EXECUTE,
+            result: ContractResult::Ok(SubMsgExecutionResponse {
+                events: vec![],
+                data: None,
+            }),
+        };
+        reply(deps.as_mut(), mock_env(), reply_msg).unwrap();
+
+        let res = query_executions(deps.as_ref(), None, None).unwrap();
+        assert_eq!(res.executions.len(), 1);
+        assert_eq!(res.total_count, 1);
+        assert_eq!(res.total_revenue, res.executions[0].revenue);
+        assert_eq!(res.total_commission, res.executions[0].commission);
+        assert_eq!(res.total_revenue, Decimal256::from_uint256(20u64));
+
+        let stats = query_stats(deps.as_ref()).unwrap();
+        assert_eq!(stats.total_revenue, Decimal256::from_uint256(20u64));
+        assert_eq!(stats.synced_revenue, Decimal256::from_uint256(10u64));
     }
 }
 
 #[cfg(test)]
-mod claim {
+mod shutdown {
     use super::*;
+    use crate::contract::testing::mock_instantiate_msg;
     use cosmwasm_std::testing::{mock_env, mock_info, MOCK_CONTRACT_ADDR};
-    use cosmwasm_std::{attr, coins, BankMsg};
+    use cosmwasm_std::{coin, coins, BankMsg, ContractResult, SubMsgExecutionResponse};
     use starflet_protocol::mock_querier::mock_dependencies;
     use terraswap::asset::AssetInfo::NativeToken;
 
-    use crate::response::MsgInstantiateContractResponse;
-
-    use std::str::FromStr;
-
     static OWNER: &str = "owner0000";
-    static COMMISSION_RATE: &str = "0.1";
-    static SYMBOL: &str = "TTN";
-    static CODE_ID: u64 = 123u64;
+    static GUARDIAN: &str = "guardian0000";
+    static BONDER0: &str = "bonder0000";
+    static BONDER1: &str = "bonder0001";
 
-    static BONDER1: &str = "bonder0000";
-    static CLAIM_BALANCE: Decimal256 = Decimal256::one();
+    static VAULTS_TOKEN_CONTRACT: &str = "vaults0000";
 
     fn init(mut deps: DepsMut) {
-        let asset_info = NativeToken {
-            denom: "uusd".to_string(),
-        };
         let msg = InstantiateMsg {
-            commission_rate: Decimal256::from_str(COMMISSION_RATE).unwrap(),
-            asset_info,
-            token_code_id: CODE_ID,
-            symbol: SYMBOL.to_string(),
+            guardian: Some(GUARDIAN.to_string()),
+            ..mock_instantiate_msg()
         };
 
-        let info = mock_info(OWNER, &[]);
+        instantiate(deps.branch(), mock_env(), mock_info(OWNER, &[]), msg).unwrap();
 
-        instantiate(deps.branch(), mock_env(), info, msg).unwrap();
+        let mut config = get_config(deps.as_ref()).unwrap();
+        config.token_address = Some(Addr::unchecked(VAULTS_TOKEN_CONTRACT));
+        set_config(deps, config).unwrap();
+    }
 
-        let mut res = MsgInstantiateContractResponse::new();
-        res.set_contract_address(MOCK_CONTRACT_ADDR.to_string());
+    fn bond_msg(amount: u128) -> ExecuteMsg {
+        ExecuteMsg::Bond {
+            asset: Asset {
+                info: NativeToken {
+                    denom: "uusd".to_string(),
+                },
+                amount: Uint128::from(amount),
+            },
+            proof: None,
+            recipient: None,
+            min_shares_out: None,
+        }
+    }
 
-        add_commission(deps.branch(), CLAIM_BALANCE).unwrap();
+    fn hook_msg(sender: &str, amount: u128, msg: Cw20HookMsg) -> ExecuteMsg {
+        ExecuteMsg::Receive(Cw20ReceiveMsg {
+            sender: sender.to_string(),
+            amount: Uint128::from(amount),
+            msg: to_binary(&msg).unwrap(),
+        })
+    }
+
+    fn shutdown(deps: DepsMut, sender: &str) -> Result<Response<TerraMsgWrapper>, ContractError> {
+        let msg = ExecuteMsg::Shutdown { unwind_msgs: None };
+        execute(deps, mock_env(), mock_info(sender, &[]), msg)
     }
 
     #[test]
-    fn normal_claim() {
+    fn disables_bond_unbond_and_execute() {
         let mut deps = mock_dependencies(&[]);
+        deps.querier
+            .with_token_balances(&[(&VAULTS_TOKEN_CONTRACT.to_string(), &[])]);
+        init(deps.as_mut());
+
+        let res = shutdown(deps.as_mut(), BONDER0).unwrap_err();
+        assert_eq!(res, ContractError::Unauthorized {});
+
+        let res = shutdown(deps.as_mut(), GUARDIAN).unwrap();
+        assert_eq!(
+            res.attributes,
+            vec![attr("action", "shutdown"), attr("sender", GUARDIAN)]
+        );
+        assert_eq!(
+            query_pause_state(deps.as_ref()).unwrap(),
+            PauseStateResponse {
+                bond: true,
+                unbond: true,
+                execute: true,
+                claim: false,
+                shutdown: true,
+            }
+        );
+
+        let info = mock_info(BONDER0, &coins(100, "uusd"));
+        let res = execute(deps.as_mut(), mock_env(), info, bond_msg(100)).unwrap_err();
+        assert_eq!(res, ContractError::Shutdown {});
+
+        let msg = hook_msg(
+            BONDER0,
+            100,
+            Cw20HookMsg::Unbond {
+                recipient: None,
+                min_assets_out: None,
+            },
+        );
+        let info = mock_info(VAULTS_TOKEN_CONTRACT, &[]);
+        let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+        assert_eq!(res, ContractError::Shutdown {});
+
+        let msg = ExecuteMsg::Execute {
+            msgs: vec![Binary::default()],
+            is_distribute: true,
+            max_loss: None,
+            min_profit: None,
+        };
+        let res = execute(deps.as_mut(), mock_env(), mock_info(OWNER, &[]), msg).unwrap_err();
+        assert_eq!(res, ContractError::Shutdown {});
+
+        // final, it can not be paused or shut down again
+        let msg = ExecuteMsg::SetPause {
+            bond: Some(false),
+            unbond: None,
+            execute: None,
+            claim: None,
+        };
+        let res = execute(deps.as_mut(), mock_env(), mock_info(OWNER, &[]), msg).unwrap_err();
+        assert_eq!(res, ContractError::Shutdown {});
 
+        let res = shutdown(deps.as_mut(), OWNER).unwrap_err();
+        assert_eq!(res, ContractError::Shutdown {});
+    }
+
+    #[test]
+    fn emergency_withdraw() {
+        let mut deps = mock_dependencies(&[]);
+        deps.querier
+            .with_token_balances(&[(&VAULTS_TOKEN_CONTRACT.to_string(), &[])]);
         init(deps.as_mut());
 
-        let res_commission = query_commission(deps.as_ref());
+        for bonder in [BONDER0, BONDER1] {
+            let info = mock_info(bonder, &coins(100, "uusd"));
+            execute(deps.as_mut(), mock_env(), info, bond_msg(100)).unwrap();
+        }
+        deps.querier.with_token_balances(&[(
+            &VAULTS_TOKEN_CONTRACT.to_string(),
+            &[
+                (&BONDER0.to_string(), &Uint128::from(100u128)),
+                (&BONDER1.to_string(), &Uint128::from(100u128)),
+            ],
+        )]);
 
-        let msg = ExecuteMsg::Claim {};
+        // 20 revenue with 2 commission
+        deps.querier
+            .with_balance(&[(&MOCK_CONTRACT_ADDR.to_string(), coins(220, "uusd"))]);
+        let reply_msg = Reply {
+            id: MSG_REPLY_ID_EXECUTE,
+            result: ContractResult::Ok(SubMsgExecutionResponse {
+                events: vec![],
+                data: None,
+            }),
+        };
+        reply(deps.as_mut(), mock_env(), reply_msg).unwrap();
 
-        let info = mock_info(OWNER, &[]);
+        let msg = hook_msg(BONDER0, 100, Cw20HookMsg::EmergencyWithdraw {});
+        let info = mock_info(VAULTS_TOKEN_CONTRACT, &[]);
+        let res = execute(deps.as_mut(), mock_env(), info.clone(), msg.clone()).unwrap_err();
+        assert_eq!(res, ContractError::NotShutdown {});
 
-        let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        // an unwind left part of the position in another denom
+        deps.querier.with_balance(&[(
+            &MOCK_CONTRACT_ADDR.to_string(),
+            vec![coin(220, "uusd"), coin(50, "uluna")],
+        )]);
+        shutdown(deps.as_mut(), OWNER).unwrap();
 
+        let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        assert_eq!(
+            res.messages,
+            vec![
+                SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+                    contract_addr: VAULTS_TOKEN_CONTRACT.to_string(),
+                    funds: vec![],
+                    msg: to_binary(&Cw20ExecuteMsg::Burn {
+                        amount: Uint128::from(100u128),
+                    })
+                    .unwrap(),
+                })),
+                SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+                    to_address: BONDER0.to_string(),
+                    amount: coins(109, "uusd"),
+                })),
+                SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+                    to_address: BONDER0.to_string(),
+                    amount: coins(25, "uluna"),
+                })),
+            ]
+        );
         assert_eq!(
             res.attributes,
             vec![
-                attr("action", "claim"),
-                attr("claimer", OWNER.to_string()),
-                attr("asset", res_commission.asset.to_string()),
+                attr("action", "emergency_withdraw"),
+                attr("withdrawer", BONDER0),
+                attr("shares", "100"),
+                attr("assets", "109uusd,25uluna"),
             ]
         );
 
+        // the commission is left for the owner
         assert_eq!(
-            res.messages,
-            vec![SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
-                to_address: OWNER.to_string(),
-                amount: coins(1, "uusd"),
-            }))]
+            get_vaults(deps.as_ref()).unwrap(),
+            Decimal256::from_uint256(111u64)
+        );
+        assert_eq!(
+            get_commission(deps.as_ref()).unwrap(),
+            Decimal256::from_uint256(2u64)
         );
     }
 
     #[test]
-    fn unknown_owner_claim_will_err() {
+    fn pending_unbonds_can_be_withdrawn() {
         let mut deps = mock_dependencies(&[]);
-
+        deps.querier
+            .with_token_balances(&[(&VAULTS_TOKEN_CONTRACT.to_string(), &[])]);
         init(deps.as_mut());
 
-        let msg = ExecuteMsg::Claim {};
+        let mut config = get_config(deps.as_ref()).unwrap();
+        config.unbonding_period = 100;
+        set_config(deps.as_mut(), config).unwrap();
 
-        let info = mock_info(BONDER1, &[]);
+        let info = mock_info(BONDER0, &coins(100, "uusd"));
+        execute(deps.as_mut(), mock_env(), info, bond_msg(100)).unwrap();
+        deps.querier.with_token_balances(&[(
+            &VAULTS_TOKEN_CONTRACT.to_string(),
+            &[(&BONDER0.to_string(), &Uint128::from(100u128))],
+        )]);
+        deps.querier
+            .with_balance(&[(&MOCK_CONTRACT_ADDR.to_string(), coins(100, "uusd"))]);
+
+        let msg = hook_msg(
+            BONDER0,
+            40,
+            Cw20HookMsg::Unbond {
+                recipient: None,
+                min_assets_out: None,
+            },
+        );
+        let info = mock_info(VAULTS_TOKEN_CONTRACT, &[]);
+        execute(deps.as_mut(), mock_env(), info, msg).unwrap();
 
-        execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+        shutdown(deps.as_mut(), OWNER).unwrap();
+
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(100);
+        let msg = ExecuteMsg::WithdrawUnbonded {};
+        let res = execute(deps.as_mut(), env, mock_info(BONDER0, &[]), msg).unwrap();
+        assert_eq!(
+            res.messages,
+            vec![SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+                to_address: BONDER0.to_string(),
+                amount: coins(40, "uusd"),
+            }))]
+        );
+        assert_eq!(
+            get_total_unbonding(deps.as_ref()).unwrap(),
+            Decimal256::zero()
+        );
     }
 }
 
 #[cfg(test)]
-mod rate {
+mod strategy {
     use super::*;
+    use crate::contract::testing::mock_instantiate_msg;
     use cosmwasm_std::testing::{mock_env, mock_info, MOCK_CONTRACT_ADDR};
-    use cosmwasm_std::{coin, coins};
+    use cosmwasm_std::{coins, ContractResult, SubMsgExecutionResponse};
     use starflet_protocol::mock_querier::mock_dependencies;
     use terraswap::asset::AssetInfo::NativeToken;
 
-    use crate::response::MsgInstantiateContractResponse;
-
-    use std::str::FromStr;
-
     static OWNER: &str = "owner0000";
-    static COMMISSION_RATE: &str = "0.1";
-    static SYMBOL: &str = "TTN";
-    static CODE_ID: u64 = 123u64;
-
-    static BONDER0: &str = "bonder0000";
-    static BONDER1: &str = "bonder0001";
+    static BONDER: &str = "bonder0000";
 
     static VAULTS_TOKEN_CONTRACT: &str = "vaults0000";
 
-    fn init(mut deps: DepsMut) {
-        let asset_info = NativeToken {
-            denom: "uusd".to_string(),
-        };
-        let msg = InstantiateMsg {
-            commission_rate: Decimal256::from_str(COMMISSION_RATE).unwrap(),
-            asset_info,
-            token_code_id: CODE_ID,
-            symbol: SYMBOL.to_string(),
-        };
+    /// Holds the asset elsewhere and reports it at a fixed value
+    struct Elsewhere {
+        assets: u64,
+    }
 
-        let info = mock_info(OWNER, &[]);
+    impl Strategy for Elsewhere {
+        fn total_assets(&self, _deps: Deps, _env: &Env) -> Result<Decimal256, ContractError> {
+            Ok(Decimal256::from_uint256(self.assets))
+        }
 
-        instantiate(deps.branch(), mock_env(), info, msg).unwrap();
+        fn on_reply(
+            &self,
+            _deps: DepsMut,
+            _env: Env,
+            reply: Reply,
+        ) -> Result<Response<TerraMsgWrapper>, ContractError> {
+            Ok(Response::new().add_attribute("strategy_reply", reply.id.to_string()))
+        }
 
-        let mut res = MsgInstantiateContractResponse::new();
-        res.set_contract_address(MOCK_CONTRACT_ADDR.to_string());
+        fn extra_queries(
+            &self,
+            deps: Deps,
+            _env: &Env,
+            msg: &QueryMsg,
+        ) -> Option<StdResult<Binary>> {
+            match msg {
+                QueryMsg::TotalAssets {} => Some(to_binary(&TotalAssetsResponse {
+                    asset: Asset {
+                        info: load_config(deps).ok()?.asset_info,
+                        amount: Uint128::from(self.assets),
+                    },
+                })),
+                _ => None,
+            }
+        }
+    }
+
+    fn init(mut deps: DepsMut) {
+        let msg = mock_instantiate_msg();
+
+        instantiate(deps.branch(), mock_env(), mock_info(OWNER, &[]), msg).unwrap();
 
         let mut config = get_config(deps.as_ref()).unwrap();
         config.token_address = Some(Addr::unchecked(VAULTS_TOKEN_CONTRACT));
-        set_config(deps, config).unwrap();
+        set_config(deps.branch(), config).unwrap();
+
+        let msg = ExecuteMsg::Bond {
+            asset: Asset {
+                info: NativeToken {
+                    denom: "uusd".to_string(),
+                },
+                amount: Uint128::from(100u128),
+            },
+            proof: None,
+            recipient: None,
+            min_shares_out: None,
+        };
+        let info = mock_info(BONDER, &coins(100, "uusd"));
+        execute(deps, mock_env(), info, msg).unwrap();
     }
 
     #[test]
-    fn more_than_share_rate() {
-        let mut deps = mock_dependencies(&coins(110u128, "uusd"));
+    fn settles_against_total_assets() {
+        let mut deps = mock_dependencies(&[]);
         deps.querier
-            .with_balance(&[(&MOCK_CONTRACT_ADDR.to_string(), vec![coin(110u128, "uusd")])]);
-        deps.querier.with_token_balances(&[(
-            &VAULTS_TOKEN_CONTRACT.to_string(),
-            &[
-                (&BONDER0.to_string(), &Uint128::from(1u128)),
-                (&BONDER1.to_string(), &Uint128::from(9u128)),
-            ],
-        )]);
-
+            .with_token_balances(&[(&VAULTS_TOKEN_CONTRACT.to_string(), &[])]);
         init(deps.as_mut());
 
-        set_vaults(
-            deps.as_mut(),
-            Decimal256::from_uint256(Uint256::from(110u128)),
-        )
-        .unwrap();
-        add_commission(
+        // the planet balance is ignored, the strategy values the vaults
+        deps.querier
+            .with_balance(&[(&MOCK_CONTRACT_ADDR.to_string(), coins(100, "uusd"))]);
+        let strategy = Elsewhere { assets: 120 };
+
+        assert_eq!(
+            query_health(&strategy, deps.as_ref(), mock_env()).unwrap(),
+            HealthResponse {
+                tracked: Decimal256::from_uint256(100u64),
+                actual: Decimal256::from_uint256(120u64),
+                surplus: Decimal256::from_uint256(20u64),
+                shortfall: Decimal256::zero(),
+                synced_revenue: Decimal256::zero(),
+                synced_loss: Decimal256::zero(),
+            }
+        );
+
+        let msg = ExecuteMsg::Sync {
+            absorb_shortfall: None,
+        };
+        execute_with(
+            &strategy,
             deps.as_mut(),
-            Decimal256::from_uint256(Uint256::from(10u128)),
+            mock_env(),
+            mock_info(OWNER, &[]),
+            msg,
         )
         .unwrap();
-
-        let bonder0_balance = query_stake_info(deps.as_ref(), BONDER0.to_string());
-        assert_eq!("10uusd", bonder0_balance.asset.to_string(),);
-
-        let bonder1_balance = query_stake_info(deps.as_ref(), BONDER1.to_string());
-        assert_eq!("90uusd", bonder1_balance.asset.to_string());
+        assert_eq!(
+            get_vaults(deps.as_ref()).unwrap(),
+            Decimal256::from_uint256(120u64)
+        );
+        assert_eq!(
+            get_commission(deps.as_ref()).unwrap(),
+            Decimal256::from_uint256(2u64)
+        );
     }
 
     #[test]
-    fn less_than_share_rate() {
-        let mut deps = mock_dependencies(&coins(110u128, "uusd"));
+    fn strategy_reply_ids() {
+        let mut deps = mock_dependencies(&[]);
         deps.querier
-            .with_balance(&[(&MOCK_CONTRACT_ADDR.to_string(), vec![coin(110u128, "uusd")])]);
+            .with_token_balances(&[(&VAULTS_TOKEN_CONTRACT.to_string(), &[])]);
+        init(deps.as_mut());
 
-        deps.querier.with_token_balances(&[(
-            &VAULTS_TOKEN_CONTRACT.to_string(),
-            &[
-                (&BONDER0.to_string(), &Uint128::from(10000u128)),
-                (&BONDER1.to_string(), &Uint128::from(90000u128)),
-            ],
-        )]);
+        let reply = |id: u64| Reply {
+            id,
+            result: ContractResult::Ok(SubMsgExecutionResponse {
+                events: vec![],
+                data: None,
+            }),
+        };
 
-        init(deps.as_mut());
+        let strategy = Elsewhere { assets: 100 };
+        for id in [*STRATEGY_REPLY_IDS.start(), *STRATEGY_REPLY_IDS.end()] {
+            let res = reply_with(&strategy, deps.as_mut(), mock_env(), reply(id)).unwrap();
+            assert_eq!(res.attributes, vec![attr("strategy_reply", id.to_string())]);
+        }
 
-        set_vaults(
+        // the plain planet has no replies of its own there
+        let res = reply_with(
+            &Hold,
             deps.as_mut(),
-            Decimal256::from_uint256(Uint256::from(110u128)),
+            mock_env(),
+            reply(*STRATEGY_REPLY_IDS.start()),
+        );
+        match res {
+            Err(ContractError::InvalidReplyId {}) => {}
+            _ => panic!("Must return invalid reply id error"),
+        }
+
+        // outside both ranges nobody answers
+        for id in [0, *STRATEGY_REPLY_IDS.end() + 1] {
+            let res = reply_with(&strategy, deps.as_mut(), mock_env(), reply(id));
+            assert_eq!(res.unwrap_err(), ContractError::InvalidReplyId {});
+        }
+    }
+
+    #[test]
+    fn extra_queries_take_precedence() {
+        let mut deps = mock_dependencies(&[]);
+        deps.querier
+            .with_token_balances(&[(&VAULTS_TOKEN_CONTRACT.to_string(), &[])]);
+        init(deps.as_mut());
+
+        let strategy = Elsewhere { assets: 150 };
+        let res: TotalAssetsResponse = from_binary(
+            &query_with(
+                &strategy,
+                deps.as_ref(),
+                mock_env(),
+                QueryMsg::TotalAssets {},
+            )
+            .unwrap(),
         )
         .unwrap();
-        add_commission(
-            deps.as_mut(),
-            Decimal256::from_uint256(Uint256::from(10u128)),
+        assert_eq!(res.asset.amount, Uint128::from(150u128));
+
+        // the plain planet answers from the tracked vaults
+        let res: TotalAssetsResponse = from_binary(
+            &query_with(&Hold, deps.as_ref(), mock_env(), QueryMsg::TotalAssets {}).unwrap(),
         )
         .unwrap();
+        assert_eq!(res.asset.amount, Uint128::from(100u128));
+    }
+}
 
-        let bonder0_balance = query_stake_info(deps.as_ref(), BONDER0.to_string());
-        assert_eq!("10uusd", bonder0_balance.asset.to_string(),);
+#[cfg(test)]
+mod migration {
+    use super::*;
+    use crate::state::{COMMISSION, VAULTS};
+    use cosmwasm_std::testing::{mock_dependencies, mock_env};
+    use cosmwasm_std::Storage;
+    use terraswap::asset::AssetInfo::NativeToken;
 
-        let bonder1_balance = query_stake_info(deps.as_ref(), BONDER1.to_string());
-        assert_eq!("90uusd", bonder1_balance.asset.to_string());
+    #[test]
+    fn baseline_config() {
+        let mut deps = mock_dependencies(&[]);
+
+        // a config and vaults stored by the first release
+        deps.storage.set(
+            b"config",
+            br#"{"owner":"owner0000","commission_rate":"0.1","asset_info":{"native_token":{"denom":"uusd"}},"token_code_id":1,"token_address":"vaults0000"}"#,
+        );
+        VAULTS
+            .save(&mut deps.storage, &Decimal256::from_uint256(100u64))
+            .unwrap();
+        COMMISSION
+            .save(&mut deps.storage, &Decimal256::zero())
+            .unwrap();
+
+        migrate(deps.as_mut(), mock_env(), MigrateMsg {}).unwrap();
+
+        assert_eq!(
+            get_config(deps.as_ref()).unwrap(),
+            Config {
+                owner: Addr::unchecked("owner0000"),
+                commission_rate: Decimal256::percent(10),
+                asset_info: NativeToken {
+                    denom: "uusd".to_string(),
+                },
+                token_code_id: 1,
+                token_address: Some(Addr::unchecked("vaults0000")),
+                unbonding_period: 0,
+                max_total_deposit: None,
+                max_deposit_per_user: None,
+                access_mode: AccessMode::Open,
+                guardian: None,
+                executors: vec![],
+                max_rate_change: None,
+                config_delay: 0,
+            }
+        );
+        assert_eq!(
+            get_total_unbonding(deps.as_ref()).unwrap(),
+            Decimal256::zero()
+        );
+        assert_eq!(
+            get_vaults(deps.as_ref()).unwrap(),
+            Decimal256::from_uint256(100u64)
+        );
+        assert_eq!(
+            get_pause_state(deps.as_ref()).unwrap(),
+            PauseState::default()
+        );
+        assert_eq!(query_stats(deps.as_ref()).unwrap().execute_count, 0);
     }
 }
 
-pub fn migrate(_deps: DepsMut, _env: Env, _msg: MigrateMsg) -> StdResult<Response> {
+pub fn migrate(deps: DepsMut, _env: Env, _msg: MigrateMsg) -> StdResult<Response> {
+    migrate_state(deps)?;
+
     Ok(Response::default())
 }
@@ -1,4 +1,5 @@
-use cosmwasm_std::StdError;
+use cosmwasm_bignumber::Decimal256;
+use cosmwasm_std::{StdError, Uint128};
 use thiserror::Error;
 
 #[derive(Error, Debug, PartialEq)]
@@ -9,12 +10,27 @@ pub enum ContractError {
     #[error("Unauthorized")]
     Unauthorized {},
 
+    #[error("Planet is not initialized")]
+    NotInitialized {},
+
+    #[error("Vaults token is not instantiated")]
+    TokenNotInstantiated {},
+
+    #[error("Failed to query {contract}: {reason}")]
+    QueryFailed { contract: String, reason: String },
+
+    #[error("Overflow: {0}")]
+    Overflow(String),
+
     #[error("Invalid reply ID")]
     InvalidReplyId {},
 
-    #[error("Invalid request: \"bond\", \"unbond\" message not included in request")]
+    #[error("Invalid request: \"bond\", \"unbond\", \"emergency_withdraw\" message not included in request")]
     InvalidHookMsg {},
 
+    #[error("Planet is not the minter of {0}")]
+    NotMinter(String),
+
     #[error("Already instantiate contract {0}")]
     AlreadyInstantiate(String),
 
@@ -23,4 +39,94 @@ pub enum ContractError {
 
     #[error("Fail to execute. before {0} after {1}")]
     FailedExecute(String, String),
+
+    #[error("No unbonded amount to withdraw")]
+    NothingToWithdraw {},
+
+    #[error("Deposit cap exceeded, remaining {remaining}")]
+    DepositCapExceeded { remaining: Uint128 },
+
+    #[error("{0} is not allowed to bond")]
+    NotAllowed(String),
+
+    #[error("Invalid merkle root {0}")]
+    InvalidMerkleRoot(String),
+
+    #[error("{operation} is paused")]
+    Paused { operation: String },
+
+    #[error("Commission rate {rate} is above the maximum {max}")]
+    InvalidCommissionRate { rate: Decimal256, max: Decimal256 },
+
+    #[error("No pending config change")]
+    NoPendingConfig {},
+
+    #[error("Config change is timelocked until {release_at}")]
+    ConfigTimelocked { release_at: u64 },
+
+    #[error("Planet is shut down")]
+    Shutdown {},
+
+    #[error("Planet is not shut down")]
+    NotShutdown {},
+
+    #[error("No adopted vaults token is waiting for its backing")]
+    NoPendingBacking {},
+
+    #[error("Backing {amount} is more than the unbooked {surplus}")]
+    BackingExceeded {
+        amount: Decimal256,
+        surplus: Decimal256,
+    },
+
+    #[error("{0} is not allowed for execute")]
+    ExecuteNotAllowed(String),
+
+    #[error("Bond would mint zero shares")]
+    ZeroShares {},
+
+    #[error("Slippage exceeded, {actual} is less than the minimum {minimum}")]
+    SlippageExceeded { minimum: Uint128, actual: Uint128 },
+
+    #[error("Nothing to execute")]
+    EmptyExecute {},
+
+    #[error("Execute earned {profit}, less than the min profit {min_profit}")]
+    ProfitBelowMinimum {
+        profit: Decimal256,
+        min_profit: Decimal256,
+    },
+
+    #[error("Share rate moved from {before} to {after}, more than {max_rate_change}")]
+    RateChangeExceeded {
+        before: Decimal256,
+        after: Decimal256,
+        max_rate_change: Decimal256,
+    },
+
+    #[error("Execute lost {loss}, more than the max loss {max_loss}")]
+    MaxLossExceeded {
+        loss: Decimal256,
+        max_loss: Decimal256,
+    },
+}
+
+impl ContractError {
+    /// Tags a failed query with the contract or denom it was sent to
+    pub fn query_failed(contract: impl ToString) -> impl FnOnce(StdError) -> ContractError {
+        move |err| ContractError::QueryFailed {
+            contract: contract.to_string(),
+            reason: err.to_string(),
+        }
+    }
+}
+
+/// Lets query handlers share the execute helpers
+impl From<ContractError> for StdError {
+    fn from(err: ContractError) -> Self {
+        match err {
+            ContractError::Std(err) => err,
+            err => StdError::generic_err(err.to_string()),
+        }
+    }
 }
@@ -1,4 +1,7 @@
+pub mod access;
 pub mod contract;
 pub mod error;
 pub mod response;
 pub mod state;
+pub mod strategy;
+pub mod vault_math;
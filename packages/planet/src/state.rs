@@ -2,12 +2,15 @@ use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 use cosmwasm_bignumber::Decimal256;
-use cosmwasm_std::{Addr, Deps, DepsMut, StdResult};
-use cw_storage_plus::Item;
+use cosmwasm_std::{Addr, Deps, DepsMut, Order, StdResult, Uint128};
+use cw_storage_plus::{Bound, Item, Map, U64Key};
 
-use std::ops::Sub;
+use starflet_protocol::planet::{AccessMode, ExecuteTarget, Execution};
 use terraswap::asset::AssetInfo;
 
+use crate::vault_math::{checked_add, checked_sub};
+
+/// Fields added after the first release default, so a config stored by an older planet still loads
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct Config {
     pub owner: Addr,
@@ -15,6 +18,24 @@ pub struct Config {
     pub asset_info: AssetInfo,
     pub token_code_id: u64,
     pub token_address: Option<Addr>,
+    #[serde(default)]
+    pub unbonding_period: u64,
+    #[serde(default)]
+    pub max_total_deposit: Option<Uint128>,
+    #[serde(default)]
+    pub max_deposit_per_user: Option<Uint128>,
+    #[serde(default)]
+    pub access_mode: AccessMode,
+    #[serde(default)]
+    pub guardian: Option<Addr>,
+    /// Besides the owner, may run execute but not change config or claim
+    #[serde(default)]
+    pub executors: Vec<Addr>,
+    #[serde(default)]
+    pub max_rate_change: Option<Decimal256>,
+    /// Seconds a timelocked config change waits before it can be applied
+    #[serde(default)]
+    pub config_delay: u64,
 }
 
 pub const CONFIG: Item<Config> = Item::new("config");
@@ -27,26 +48,63 @@ pub fn set_config(deps: DepsMut, config: Config) -> StdResult<()> {
     CONFIG.save(deps.storage, &config)
 }
 
+/// Timelocked part of an `UpdateConfig`, waiting for `ApplyConfig`
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, JsonSchema)]
+pub struct PendingConfig {
+    pub owner: Option<Addr>,
+    pub commission_rate: Option<Decimal256>,
+    pub config_delay: Option<u64>,
+    /// Added by `UpdateExecuteTargets`, with validated addresses
+    #[serde(default)]
+    pub execute_targets: Vec<ExecuteTarget>,
+    pub release_at: u64,
+}
+
+pub const PENDING_CONFIG: Item<PendingConfig> = Item::new("pending_config");
+
+pub fn get_pending_config(deps: Deps) -> StdResult<Option<PendingConfig>> {
+    PENDING_CONFIG.may_load(deps.storage)
+}
+
+pub fn set_pending_config(deps: DepsMut, pending_config: PendingConfig) -> StdResult<()> {
+    PENDING_CONFIG.save(deps.storage, &pending_config)
+}
+
+pub fn remove_pending_config(deps: DepsMut) {
+    PENDING_CONFIG.remove(deps.storage)
+}
+
 pub const VAULTS: Item<Decimal256> = Item::new("vaults");
 pub const COMMISSION: Item<Decimal256> = Item::new("commission");
 
 pub fn init(deps: DepsMut) -> StdResult<()> {
-    VAULTS.save(deps.storage, &Decimal256::zero()).unwrap();
+    VAULTS.save(deps.storage, &Decimal256::zero())?;
+    TOTAL_UNBONDING.save(deps.storage, &Decimal256::zero())?;
     COMMISSION.save(deps.storage, &Decimal256::zero())
 }
 
+/// Rewrites the config with its defaults and saves the items an older planet did not init
+pub fn migrate_state(deps: DepsMut) -> StdResult<()> {
+    let config = CONFIG.load(deps.storage)?;
+    CONFIG.save(deps.storage, &config)?;
+
+    if TOTAL_UNBONDING.may_load(deps.storage)?.is_none() {
+        TOTAL_UNBONDING.save(deps.storage, &Decimal256::zero())?;
+    }
+
+    Ok(())
+}
+
 pub fn add_vaults(deps: DepsMut, amount: Decimal256) -> StdResult<()> {
-    let mut valuts = VAULTS.load(deps.storage).unwrap();
-    valuts += amount;
+    let valuts = VAULTS.load(deps.storage)?;
 
-    VAULTS.save(deps.storage, &valuts)
+    VAULTS.save(deps.storage, &checked_add(valuts, amount)?)
 }
 
 pub fn sub_vaults(deps: DepsMut, amount: Decimal256) -> StdResult<()> {
-    let mut valuts = VAULTS.load(deps.storage).unwrap();
-    valuts = valuts.sub(amount);
+    let valuts = VAULTS.load(deps.storage)?;
 
-    VAULTS.save(deps.storage, &valuts)
+    VAULTS.save(deps.storage, &checked_sub(valuts, amount)?)
 }
 
 pub fn set_vaults(deps: DepsMut, amount: Decimal256) -> StdResult<()> {
@@ -58,22 +116,20 @@ pub fn get_vaults(deps: Deps) -> StdResult<Decimal256> {
 }
 
 pub fn add_commission(deps: DepsMut, amount: Decimal256) -> StdResult<()> {
-    let mut commission = COMMISSION.load(deps.storage).unwrap();
-    commission += amount;
+    let commission = COMMISSION.load(deps.storage)?;
 
-    COMMISSION.save(deps.storage, &commission)
+    COMMISSION.save(deps.storage, &checked_add(commission, amount)?)
 }
 
 pub fn sub_commission(deps: DepsMut, amount: Decimal256) -> StdResult<()> {
-    let mut commission = COMMISSION.load(deps.storage).unwrap();
-    commission = commission.sub(amount);
+    let commission = COMMISSION.load(deps.storage)?;
 
-    COMMISSION.save(deps.storage, &commission)
+    COMMISSION.save(deps.storage, &checked_sub(commission, amount)?)
 }
 
 pub fn sub_all_commission(deps: DepsMut) -> StdResult<Decimal256> {
-    let commission = get_commission(deps.as_ref()).unwrap();
-    COMMISSION.save(deps.storage, &Decimal256::zero()).unwrap();
+    let commission = get_commission(deps.as_ref())?;
+    COMMISSION.save(deps.storage, &Decimal256::zero())?;
 
     Ok(commission)
 }
@@ -82,6 +138,368 @@ pub fn get_commission(deps: Deps) -> StdResult<Decimal256> {
     COMMISSION.load(deps.storage)
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Unbonding {
+    pub amount: Uint128,
+    pub release_at: u64,
+}
+
+pub const UNBONDINGS: Map<&Addr, Vec<Unbonding>> = Map::new("unbondings");
+// assets already taken out of VAULTS but still held until withdrawn
+pub const TOTAL_UNBONDING: Item<Decimal256> = Item::new("total_unbonding");
+
+pub fn get_unbondings(deps: Deps, addr: &Addr) -> StdResult<Vec<Unbonding>> {
+    Ok(UNBONDINGS.may_load(deps.storage, addr)?.unwrap_or_default())
+}
+
+pub fn add_unbonding(deps: DepsMut, addr: &Addr, unbonding: Unbonding) -> StdResult<()> {
+    let total = TOTAL_UNBONDING.load(deps.storage)?;
+    let total = checked_add(total, Decimal256::from_uint256(unbonding.amount))?;
+    TOTAL_UNBONDING.save(deps.storage, &total)?;
+
+    let mut unbondings = get_unbondings(deps.as_ref(), addr)?;
+    unbondings.push(unbonding);
+
+    UNBONDINGS.save(deps.storage, addr, &unbondings)
+}
+
+pub fn withdraw_unbondings(deps: DepsMut, addr: &Addr, now: u64) -> StdResult<Uint128> {
+    let (released, pending): (Vec<Unbonding>, Vec<Unbonding>) =
+        get_unbondings(deps.as_ref(), addr)?
+            .into_iter()
+            .partition(|unbonding| unbonding.release_at <= now);
+
+    let amount = released
+        .iter()
+        .try_fold(Uint128::zero(), |acc, unbonding| {
+            acc.checked_add(unbonding.amount)
+        })?;
+
+    let total = TOTAL_UNBONDING.load(deps.storage)?;
+    let total = checked_sub(total, Decimal256::from_uint256(amount))?;
+    TOTAL_UNBONDING.save(deps.storage, &total)?;
+
+    if pending.is_empty() {
+        UNBONDINGS.remove(deps.storage, addr);
+    } else {
+        UNBONDINGS.save(deps.storage, addr, &pending)?;
+    }
+
+    Ok(amount)
+}
+
+pub fn get_total_unbonding(deps: Deps) -> StdResult<Decimal256> {
+    TOTAL_UNBONDING.load(deps.storage)
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, JsonSchema)]
+pub struct PauseState {
+    pub bond: bool,
+    pub unbond: bool,
+    pub execute: bool,
+    pub claim: bool,
+}
+
+pub const EXECUTIONS: Map<U64Key, Execution> = Map::new("executions");
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, JsonSchema)]
+pub struct ExecutionTotals {
+    pub count: u64,
+    pub revenue: Decimal256,
+    pub loss: Decimal256,
+    pub commission: Decimal256,
+}
+
+pub const EXECUTION_TOTALS: Item<ExecutionTotals> = Item::new("execution_totals");
+
+/// Stores the execution under the next sequence id and adds it to the totals
+pub fn add_execution(deps: DepsMut, mut execution: Execution) -> StdResult<u64> {
+    let mut totals = get_execution_totals(deps.as_ref())?;
+    totals.count += 1;
+    totals.revenue = checked_add(totals.revenue, execution.revenue)?;
+    totals.loss = checked_add(totals.loss, execution.loss)?;
+    totals.commission = checked_add(totals.commission, execution.commission)?;
+
+    execution.id = totals.count;
+    EXECUTIONS.save(deps.storage, U64Key::from(execution.id), &execution)?;
+    EXECUTION_TOTALS.save(deps.storage, &totals)?;
+
+    let mut stats = get_stats(deps.as_ref())?;
+    stats.first_execution_height = stats.first_execution_height.or(Some(execution.height));
+    stats.last_execution_height = Some(execution.height);
+    STATS.save(deps.storage, &stats)?;
+
+    Ok(execution.id)
+}
+
+pub fn get_execution_totals(deps: Deps) -> StdResult<ExecutionTotals> {
+    Ok(EXECUTION_TOTALS.may_load(deps.storage)?.unwrap_or_default())
+}
+
+/// What syncs booked, kept apart so the execution totals match the execution records
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, JsonSchema)]
+pub struct SyncTotals {
+    pub count: u64,
+    pub revenue: Decimal256,
+    pub loss: Decimal256,
+    pub commission: Decimal256,
+}
+
+pub const SYNC_TOTALS: Item<SyncTotals> = Item::new("sync_totals");
+
+pub fn add_synced(
+    deps: DepsMut,
+    revenue: Decimal256,
+    loss: Decimal256,
+    commission: Decimal256,
+) -> StdResult<()> {
+    let mut totals = get_sync_totals(deps.as_ref())?;
+    totals.count += 1;
+    totals.revenue = checked_add(totals.revenue, revenue)?;
+    totals.loss = checked_add(totals.loss, loss)?;
+    totals.commission = checked_add(totals.commission, commission)?;
+    SYNC_TOTALS.save(deps.storage, &totals)
+}
+
+pub fn get_sync_totals(deps: Deps) -> StdResult<SyncTotals> {
+    Ok(SYNC_TOTALS.may_load(deps.storage)?.unwrap_or_default())
+}
+
+const MAX_LIMIT: u32 = 30;
+const DEFAULT_LIMIT: u32 = 10;
+pub fn load_executions(
+    deps: Deps,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<Vec<Execution>> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+
+    let start = start_after.map(Bound::exclusive_int);
+
+    EXECUTIONS
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| {
+            let (_, v) = item?;
+            Ok(v)
+        })
+        .collect::<StdResult<Vec<Execution>>>()
+}
+
+/// Bounds of the execute in flight, read back by its reply
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, JsonSchema)]
+pub struct TmpExecute {
+    pub max_loss: Option<Decimal256>,
+    pub min_profit: Option<Decimal256>,
+}
+
+pub const TMP_EXECUTE: Item<TmpExecute> = Item::new("tmp_execute");
+
+pub fn set_tmp_execute(deps: DepsMut, tmp_execute: TmpExecute) -> StdResult<()> {
+    TMP_EXECUTE.save(deps.storage, &tmp_execute)
+}
+
+pub fn get_tmp_execute(deps: Deps) -> StdResult<TmpExecute> {
+    Ok(TMP_EXECUTE.may_load(deps.storage)?.unwrap_or_default())
+}
+
+pub fn remove_tmp_execute(deps: DepsMut) {
+    TMP_EXECUTE.remove(deps.storage)
+}
+
+pub const PAUSE_STATE: Item<PauseState> = Item::new("pause_state");
+
+pub fn get_pause_state(deps: Deps) -> StdResult<PauseState> {
+    Ok(PAUSE_STATE.may_load(deps.storage)?.unwrap_or_default())
+}
+
+pub fn set_pause_state(deps: DepsMut, pause_state: PauseState) -> StdResult<()> {
+    PAUSE_STATE.save(deps.storage, &pause_state)
+}
+
+/// Set once by a shutdown and never cleared
+pub const SHUTDOWN: Item<bool> = Item::new("shutdown");
+
+pub fn is_shutdown(deps: Deps) -> StdResult<bool> {
+    Ok(SHUTDOWN.may_load(deps.storage)?.unwrap_or(false))
+}
+
+pub fn set_shutdown(deps: DepsMut) -> StdResult<()> {
+    SHUTDOWN.save(deps.storage, &true)
+}
+
+/// Set when an adopted vaults token already has holders, cleared once their backing is booked
+pub const PENDING_BACKING: Item<bool> = Item::new("pending_backing");
+
+pub fn is_backing_pending(deps: Deps) -> StdResult<bool> {
+    Ok(PENDING_BACKING.may_load(deps.storage)?.unwrap_or(false))
+}
+
+pub fn set_backing_pending(deps: DepsMut, pending: bool) -> StdResult<()> {
+    PENDING_BACKING.save(deps.storage, &pending)
+}
+
+pub const ALLOWLIST: Map<&Addr, bool> = Map::new("allowlist");
+
+pub fn is_allowlisted(deps: Deps, addr: &Addr) -> StdResult<bool> {
+    Ok(ALLOWLIST.may_load(deps.storage, addr)?.unwrap_or(false))
+}
+
+pub fn add_allowlist(deps: DepsMut, addr: &Addr) -> StdResult<()> {
+    ALLOWLIST.save(deps.storage, addr, &true)
+}
+
+pub fn remove_allowlist(deps: DepsMut, addr: &Addr) {
+    ALLOWLIST.remove(deps.storage, addr)
+}
+
+/// Allowed top-level message names per execute target, empty allows any
+pub const EXECUTE_TARGETS: Map<&Addr, Vec<String>> = Map::new("execute_targets");
+
+pub fn get_execute_target(deps: Deps, addr: &Addr) -> StdResult<Option<Vec<String>>> {
+    EXECUTE_TARGETS.may_load(deps.storage, addr)
+}
+
+pub fn set_execute_target(deps: DepsMut, addr: &Addr, messages: Vec<String>) -> StdResult<()> {
+    EXECUTE_TARGETS.save(deps.storage, addr, &messages)
+}
+
+pub fn remove_execute_target(deps: DepsMut, addr: &Addr) {
+    EXECUTE_TARGETS.remove(deps.storage, addr);
+    EXECUTE_TARGET_FUNDS.remove(deps.storage, addr)
+}
+
+/// Execute targets that may receive coins with their messages
+pub const EXECUTE_TARGET_FUNDS: Map<&Addr, bool> = Map::new("execute_target_funds");
+
+pub fn allows_funds(deps: Deps, addr: &Addr) -> StdResult<bool> {
+    Ok(EXECUTE_TARGET_FUNDS
+        .may_load(deps.storage, addr)?
+        .unwrap_or(false))
+}
+
+pub fn set_allow_funds(deps: DepsMut, addr: &Addr, allow_funds: bool) -> StdResult<()> {
+    if allow_funds {
+        EXECUTE_TARGET_FUNDS.save(deps.storage, addr, &true)
+    } else {
+        EXECUTE_TARGET_FUNDS.remove(deps.storage, addr);
+        Ok(())
+    }
+}
+
+/// What a staker put in and took out through the planet. Shares moved by cw20 transfers are not
+/// seen, so `shares` only counts what the planet minted to and burned from the staker
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, JsonSchema)]
+pub struct StakerBasis {
+    pub deposited: Uint128,
+    pub withdrawn: Uint128,
+    pub shares: Uint128,
+    /// Cost of `shares`, on average cost
+    pub cost: Uint128,
+}
+
+impl StakerBasis {
+    /// Returns true on the first deposit
+    pub fn add_deposit(&mut self, amount: Uint128, shares: Uint128) -> StdResult<bool> {
+        let first_deposit = self.deposited.is_zero();
+        self.deposited = self.deposited.checked_add(amount)?;
+        self.shares = self.shares.checked_add(shares)?;
+        self.cost = self.cost.checked_add(amount)?;
+
+        Ok(first_deposit)
+    }
+
+    /// Burned shares beyond the tracked ones came in by transfer and take no cost with them
+    pub fn add_withdrawal(&mut self, amount: Uint128, shares: Uint128) -> StdResult<()> {
+        self.withdrawn = self.withdrawn.checked_add(amount)?;
+
+        let tracked = shares.min(self.shares);
+        if !tracked.is_zero() {
+            let cost = self.cost.multiply_ratio(tracked, self.shares);
+            self.cost = self.cost.checked_sub(cost)?;
+            self.shares = self.shares.checked_sub(tracked)?;
+        }
+
+        Ok(())
+    }
+}
+
+pub const STAKER_BASIS: Map<&Addr, StakerBasis> = Map::new("staker_basis");
+
+pub fn get_staker_basis(deps: Deps, addr: &Addr) -> StdResult<StakerBasis> {
+    Ok(STAKER_BASIS
+        .may_load(deps.storage, addr)?
+        .unwrap_or_default())
+}
+
+/// Returns true on the first deposit of `addr`
+pub fn add_staker_deposit(
+    deps: DepsMut,
+    addr: &Addr,
+    amount: Uint128,
+    shares: Uint128,
+) -> StdResult<bool> {
+    let mut basis = get_staker_basis(deps.as_ref(), addr)?;
+    let first_deposit = basis.add_deposit(amount, shares)?;
+
+    STAKER_BASIS.save(deps.storage, addr, &basis)?;
+
+    Ok(first_deposit)
+}
+
+pub fn add_staker_withdrawal(
+    deps: DepsMut,
+    addr: &Addr,
+    amount: Uint128,
+    shares: Uint128,
+) -> StdResult<()> {
+    let mut basis = get_staker_basis(deps.as_ref(), addr)?;
+    basis.add_withdrawal(amount, shares)?;
+
+    STAKER_BASIS.save(deps.storage, addr, &basis)
+}
+
+/// Lifetime counters not covered by the execution totals
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, JsonSchema)]
+pub struct Stats {
+    pub bonded: Uint128,
+    pub unbonded: Uint128,
+    pub commission_claimed: Uint128,
+    pub depositors: u64,
+    pub first_execution_height: Option<u64>,
+    pub last_execution_height: Option<u64>,
+}
+
+pub const STATS: Item<Stats> = Item::new("stats");
+
+pub fn get_stats(deps: Deps) -> StdResult<Stats> {
+    Ok(STATS.may_load(deps.storage)?.unwrap_or_default())
+}
+
+pub fn add_bonded(deps: DepsMut, amount: Uint128, new_depositor: bool) -> StdResult<()> {
+    let mut stats = get_stats(deps.as_ref())?;
+    stats.bonded = stats.bonded.checked_add(amount)?;
+    if new_depositor {
+        stats.depositors += 1;
+    }
+
+    STATS.save(deps.storage, &stats)
+}
+
+pub fn add_unbonded(deps: DepsMut, amount: Uint128) -> StdResult<()> {
+    let mut stats = get_stats(deps.as_ref())?;
+    stats.unbonded = stats.unbonded.checked_add(amount)?;
+
+    STATS.save(deps.storage, &stats)
+}
+
+pub fn add_commission_claimed(deps: DepsMut, amount: Uint128) -> StdResult<()> {
+    let mut stats = get_stats(deps.as_ref())?;
+    stats.commission_claimed = stats.commission_claimed.checked_add(amount)?;
+
+    STATS.save(deps.storage, &stats)
+}
+
 #[cfg(test)]
 mod vaults {
     use super::*;
@@ -127,6 +545,54 @@ mod vaults {
     }
 }
 
+#[cfg(test)]
+mod executions {
+    use super::*;
+    use cosmwasm_std::testing::mock_dependencies;
+
+    fn execution(revenue: u64, loss: u64) -> Execution {
+        Execution {
+            id: 0,
+            height: 12345,
+            time: 1_600_000_000,
+            revenue: Decimal256::from_uint256(revenue),
+            loss: Decimal256::from_uint256(loss),
+            commission: Decimal256::zero(),
+            rate_before: Decimal256::one(),
+            rate_after: Decimal256::one(),
+        }
+    }
+
+    #[test]
+    fn add_and_range_executions() {
+        let mut deps = mock_dependencies(&[]);
+
+        for i in 1..=12u64 {
+            let id = add_execution(deps.as_mut(), execution(i, 0)).unwrap();
+            assert_eq!(id, i);
+        }
+        add_execution(deps.as_mut(), execution(0, 5)).unwrap();
+
+        let totals = get_execution_totals(deps.as_ref()).unwrap();
+        assert_eq!(totals.count, 13);
+        assert_eq!(totals.revenue, Decimal256::from_uint256(78u64));
+        assert_eq!(totals.loss, Decimal256::from_uint256(5u64));
+
+        let res = load_executions(deps.as_ref(), None, None).unwrap();
+        assert_eq!(res.len() as u32, DEFAULT_LIMIT);
+        assert_eq!(res[0].id, 1);
+
+        let res = load_executions(deps.as_ref(), Some(10), Some(2)).unwrap();
+        assert_eq!(
+            res.iter().map(|e| e.id).collect::<Vec<u64>>(),
+            vec![11u64, 12u64]
+        );
+
+        let res = load_executions(deps.as_ref(), None, Some(50)).unwrap();
+        assert_eq!(res.len(), 13);
+    }
+}
+
 #[cfg(test)]
 mod commission {
     use super::*;
@@ -167,4 +633,84 @@ mod commission {
         let res = sub_all_commission(deps.as_mut());
         assert!(res.is_ok());
     }
+
+    #[test]
+    fn test_store_sub_commission_underflow() {
+        let mut deps = mock_dependencies(&[]);
+
+        let amount = Decimal256::one();
+
+        init(deps.as_mut()).unwrap();
+
+        let res = add_commission(deps.as_mut(), amount);
+        assert!(res.is_ok());
+
+        let res = sub_commission(deps.as_mut(), amount + amount);
+        assert!(res.is_err());
+
+        let res = sub_commission(deps.as_mut(), amount);
+        assert!(res.is_ok());
+
+        let res = get_commission(deps.as_ref());
+        assert_eq!(res.unwrap(), Decimal256::zero());
+    }
+}
+
+#[cfg(test)]
+mod unbondings {
+    use super::*;
+    use cosmwasm_std::testing::mock_dependencies;
+
+    #[test]
+    fn test_store_withdraw_released_unbondings() {
+        let mut deps = mock_dependencies(&[]);
+        let addr = Addr::unchecked("addr0000");
+
+        init(deps.as_mut()).unwrap();
+
+        add_unbonding(
+            deps.as_mut(),
+            &addr,
+            Unbonding {
+                amount: Uint128::from(10u128),
+                release_at: 100,
+            },
+        )
+        .unwrap();
+        add_unbonding(
+            deps.as_mut(),
+            &addr,
+            Unbonding {
+                amount: Uint128::from(20u128),
+                release_at: 200,
+            },
+        )
+        .unwrap();
+
+        assert_eq!(
+            get_total_unbonding(deps.as_ref()).unwrap(),
+            Decimal256::from_uint256(30u128)
+        );
+
+        let res = withdraw_unbondings(deps.as_mut(), &addr, 150);
+        assert_eq!(res.unwrap(), Uint128::from(10u128));
+
+        let res = get_unbondings(deps.as_ref(), &addr).unwrap();
+        assert_eq!(
+            res,
+            vec![Unbonding {
+                amount: Uint128::from(20u128),
+                release_at: 200,
+            }]
+        );
+
+        let res = withdraw_unbondings(deps.as_mut(), &addr, 200);
+        assert_eq!(res.unwrap(), Uint128::from(20u128));
+
+        assert!(get_unbondings(deps.as_ref(), &addr).unwrap().is_empty());
+        assert_eq!(
+            get_total_unbonding(deps.as_ref()).unwrap(),
+            Decimal256::zero()
+        );
+    }
 }
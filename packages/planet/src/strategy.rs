@@ -0,0 +1,100 @@
+use std::ops::RangeInclusive;
+
+use cosmwasm_bignumber::Decimal256;
+use cosmwasm_std::{Addr, Binary, Deps, DepsMut, Env, Reply, Response, StdResult, Uint128};
+use starflet_protocol::planet::{Execution, QueryMsg};
+use terra_cosmwasm::TerraMsgWrapper;
+use terraswap::asset::Asset;
+
+use crate::contract::{load_config, query_vaults_balance, try_bond, try_unbond};
+use crate::error::ContractError;
+
+/// Reply ids the planet uses itself, `reply_with` rejects ids outside these and the strategy ones
+pub const PLANET_REPLY_IDS: RangeInclusive<u64> = 1..=9;
+
+/// Reply ids handed to `Strategy::on_reply`. A strategy that ends in an execute replies with
+/// `MSG_REPLY_ID_EXECUTE` or `MSG_REPLY_ID_MUST_EXECUTE` instead, so the planet settles it
+pub const STRATEGY_REPLY_IDS: RangeInclusive<u64> = 10..=99;
+
+/// What a planet does with the bonded asset. The generic planet in `contract` runs access,
+/// pause, caps, accounting and settlement, and calls these hooks for the rest.
+/// Every hook defaults to holding the planet asset as is
+pub trait Strategy {
+    /// Assets held for the vaults in the planet asset, accrued commission included.
+    /// Executes are settled and syncs reconciled against it
+    fn total_assets(&self, deps: Deps, env: &Env) -> Result<Decimal256, ContractError> {
+        let config = load_config(deps)?;
+        query_vaults_balance(deps, env.contract.address.clone(), &config)
+    }
+
+    /// Takes a bond that passed the access and pause checks. A strategy that has to convert the
+    /// asset first mints from its own reply with `try_bond`
+    fn on_bond(
+        &self,
+        deps: DepsMut,
+        _env: Env,
+        sender: Addr,
+        recipient: Addr,
+        asset: Asset,
+        min_shares_out: Option<Uint128>,
+    ) -> Result<Response<TerraMsgWrapper>, ContractError> {
+        try_bond(deps, sender, recipient, asset, min_shares_out)
+    }
+
+    /// Pays out `shares` the vaults token sent to the planet, burning them is left to the hook
+    #[allow(clippy::too_many_arguments)]
+    fn on_unbond(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        vaults_contract: Addr,
+        sender: Addr,
+        recipient: Addr,
+        shares: Uint128,
+        min_assets_out: Option<Uint128>,
+    ) -> Result<Response<TerraMsgWrapper>, ContractError> {
+        let config = load_config(deps.as_ref())?;
+        try_unbond(
+            deps,
+            env,
+            vaults_contract,
+            config.asset_info,
+            sender,
+            recipient,
+            shares,
+            min_assets_out,
+        )
+    }
+
+    /// Runs once an execute is settled, with the booked execution and the settlement response
+    fn on_execute_reply(
+        &self,
+        _deps: DepsMut,
+        _env: &Env,
+        _execution: &Execution,
+        res: Response<TerraMsgWrapper>,
+    ) -> Result<Response<TerraMsgWrapper>, ContractError> {
+        Ok(res)
+    }
+
+    /// Replies with an id in `STRATEGY_REPLY_IDS`
+    fn on_reply(
+        &self,
+        _deps: DepsMut,
+        _env: Env,
+        _reply: Reply,
+    ) -> Result<Response<TerraMsgWrapper>, ContractError> {
+        Err(ContractError::InvalidReplyId {})
+    }
+
+    /// Answers `msg` in place of the planet, e.g. to value it in another asset.
+    /// `None` leaves the query to the planet
+    fn extra_queries(&self, _deps: Deps, _env: &Env, _msg: &QueryMsg) -> Option<StdResult<Binary>> {
+        None
+    }
+}
+
+/// Keeps the bonded asset as is, the strategy of the plain planet
+pub struct Hold;
+
+impl Strategy for Hold {}
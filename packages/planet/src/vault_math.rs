@@ -0,0 +1,300 @@
+use bigint::{U256, U512};
+use cosmwasm_bignumber::{Decimal256, Uint256};
+use cosmwasm_std::{OverflowError, OverflowOperation, StdError, StdResult};
+
+/// Virtual shares backed by as many virtual asset units, so the rate starts at one. A donation
+/// to a near-empty vault is mostly taken by the virtual shares, so inflating the share rate
+/// costs the attacker about a thousand times what the victim loses.
+/// The virtual assets do not exist, so below a rate of one shares only redeem their plain
+/// part of the total assets
+pub const VIRTUAL_SHARES: u128 = 1_000;
+pub const VIRTUAL_ASSETS: u128 = 1_000;
+
+fn overflow(operation: OverflowOperation, a: impl ToString, b: impl ToString) -> StdError {
+    StdError::overflow(OverflowError::new(operation, a, b))
+}
+
+pub fn checked_add(a: Decimal256, b: Decimal256) -> StdResult<Decimal256> {
+    match a.0.overflowing_add(b.0) {
+        (value, false) => Ok(Decimal256(value)),
+        (_, true) => Err(overflow(OverflowOperation::Add, a, b)),
+    }
+}
+
+pub fn checked_sub(a: Decimal256, b: Decimal256) -> StdResult<Decimal256> {
+    match a.0.overflowing_sub(b.0) {
+        (value, false) => Ok(Decimal256(value)),
+        (_, true) => Err(overflow(OverflowOperation::Sub, a, b)),
+    }
+}
+
+/// Assets owned by stakers: vaults minus the accrued commission
+pub fn total_assets(vaults: Decimal256, commission: Decimal256) -> StdResult<Decimal256> {
+    checked_sub(vaults, commission)
+}
+
+/// Raw `numerator * multiplier / denominator`, rounded down. The product is kept in 512 bits,
+/// and denominators always include the virtual offset, so they are never zero
+fn mul_div_floor(
+    numerator: Uint256,
+    multiplier: Uint256,
+    denominator: Uint256,
+) -> StdResult<Uint256> {
+    let quotient = numerator.0.full_mul(multiplier.0) / U512::from(denominator.0);
+    if quotient > U512::from(U256::max_value()) {
+        return Err(overflow(OverflowOperation::Mul, numerator, multiplier));
+    }
+
+    Ok(Uint256(U256::from(quotient)))
+}
+
+/// Total assets plus the virtual assets, in raw decimal units
+fn virtual_assets(total_assets: Decimal256) -> StdResult<Uint256> {
+    let assets = checked_add(total_assets, Decimal256::from_uint256(VIRTUAL_ASSETS))?;
+    Ok(Uint256(assets.0))
+}
+
+/// Supply plus the virtual shares, in raw decimal units
+fn virtual_supply(supply: Uint256) -> StdResult<Uint256> {
+    let (shares, overflowed) = supply.0.overflowing_add(Uint256::from(VIRTUAL_SHARES).0);
+    if overflowed {
+        return Err(overflow(OverflowOperation::Add, supply, VIRTUAL_SHARES));
+    }
+
+    Ok(Uint256(Decimal256::from_uint256(Uint256(shares)).0))
+}
+
+/// Plain `shares` part of the total assets, in asset units. Zero without supply
+fn plain_assets(shares: Uint256, total_assets: Decimal256, supply: Uint256) -> StdResult<Uint256> {
+    if supply.is_zero() {
+        return Ok(Uint256::zero());
+    }
+
+    mul_div_floor(
+        shares,
+        Uint256(total_assets.0),
+        Uint256(Decimal256::from_uint256(supply).0),
+    )
+}
+
+/// Assets per share. Without supply, assets left in the vaults are priced in, so they are not
+/// handed to the first depositor
+pub fn share_rate(total_assets: Decimal256, supply: Uint256) -> StdResult<Decimal256> {
+    let rate = mul_div_floor(
+        virtual_assets(total_assets)?,
+        Uint256(Decimal256::one().0),
+        virtual_supply(supply)?,
+    )?;
+    if supply.is_zero() {
+        return Ok(Decimal256(rate.0));
+    }
+
+    let plain_rate = mul_div_floor(Uint256(total_assets.0), Uint256::one(), supply)?;
+
+    Ok(Decimal256(rate.min(plain_rate).0))
+}
+
+/// Shares minted for `assets`, rounded down
+pub fn shares_to_mint(
+    assets: Uint256,
+    total_assets: Decimal256,
+    supply: Uint256,
+) -> StdResult<Uint256> {
+    mul_div_floor(
+        assets,
+        virtual_supply(supply)?,
+        virtual_assets(total_assets)?,
+    )
+}
+
+/// Assets paid out for `shares`, rounded down
+pub fn assets_to_redeem(
+    shares: Uint256,
+    total_assets: Decimal256,
+    supply: Uint256,
+) -> StdResult<Uint256> {
+    let assets = mul_div_floor(
+        shares,
+        virtual_assets(total_assets)?,
+        virtual_supply(supply)?,
+    )?;
+
+    Ok(assets.min(plain_assets(shares, total_assets, supply)?))
+}
+
+/// `amount * shares / supply`, rounded down. Zero without supply
+pub fn pro_rata(amount: Uint256, shares: Uint256, supply: Uint256) -> StdResult<Uint256> {
+    if supply.is_zero() {
+        return Ok(Uint256::zero());
+    }
+
+    mul_div_floor(amount, shares, supply)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Small xorshift generator so the property runs are reproducible
+    struct Rng(u64);
+
+    impl Rng {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+
+        /// Up to 1e24, spread over every order of magnitude
+        fn amount(&mut self) -> u128 {
+            let scale = 10u128.pow((self.next() % 13) as u32);
+            (self.next() as u128 % scale) * (self.next() as u128 % scale + 1)
+        }
+    }
+
+    fn bond_then_unbond(deposit: u128, total_assets: Decimal256, supply: u128) -> Option<u128> {
+        let deposit = Uint256::from(deposit);
+        let supply = Uint256::from(supply);
+
+        let shares = shares_to_mint(deposit, total_assets, supply).unwrap();
+        if shares.is_zero() {
+            return None;
+        }
+
+        let total_assets = checked_add(total_assets, Decimal256::from_uint256(deposit)).unwrap();
+        let supply = supply + shares;
+        let redeemed = assets_to_redeem(shares, total_assets, supply).unwrap();
+
+        Some(redeemed.into())
+    }
+
+    #[test]
+    fn checked_ops() {
+        let one = Decimal256::one();
+
+        assert_eq!(
+            checked_add(one, one).unwrap(),
+            Decimal256::from_uint256(2u64)
+        );
+        assert_eq!(checked_sub(one, one).unwrap(), Decimal256::zero());
+        assert!(checked_sub(Decimal256::zero(), one).is_err());
+    }
+
+    #[test]
+    fn rounds_in_vault_favour() {
+        // 3001 assets across 1000 shares, 4001 across 2000 with the virtual ones
+        let total_assets = Decimal256::from_uint256(3001u64);
+        let supply = Uint256::from(1000u64);
+
+        // 5 * 2000 / 4001 = 2.49...
+        assert_eq!(
+            shares_to_mint(Uint256::from(5u64), total_assets, supply).unwrap(),
+            Uint256::from(2u64)
+        );
+        // 3 * 4001 / 2000 = 6.0015
+        assert_eq!(
+            assets_to_redeem(Uint256::from(3u64), total_assets, supply).unwrap(),
+            Uint256::from(6u64)
+        );
+    }
+
+    #[test]
+    fn below_one_redeems_no_virtual_assets() {
+        // 100 assets across 100000 shares, the virtual formula would pay 10000 * 1100 / 101000
+        let total_assets = Decimal256::from_uint256(100u64);
+        let supply = Uint256::from(100_000u64);
+
+        assert_eq!(
+            assets_to_redeem(Uint256::from(10_000u64), total_assets, supply).unwrap(),
+            Uint256::from(10u64)
+        );
+        assert_eq!(
+            share_rate(total_assets, supply).unwrap(),
+            Decimal256::from_ratio(1, 1000)
+        );
+    }
+
+    #[test]
+    fn empty_vault_is_one_to_one() {
+        let amount = Uint256::from(1234u64);
+
+        assert_eq!(
+            shares_to_mint(amount, Decimal256::zero(), Uint256::zero()).unwrap(),
+            amount
+        );
+        // nothing to redeem without shares
+        assert_eq!(
+            assets_to_redeem(amount, Decimal256::zero(), Uint256::zero()).unwrap(),
+            Uint256::zero()
+        );
+        assert_eq!(
+            share_rate(Decimal256::zero(), Uint256::zero()).unwrap(),
+            Decimal256::one()
+        );
+    }
+
+    #[test]
+    fn pro_rata_rounds_down() {
+        // 100 * 1 / 3 = 33.3...
+        assert_eq!(
+            pro_rata(Uint256::from(100u64), Uint256::one(), Uint256::from(3u64)).unwrap(),
+            Uint256::from(33u64)
+        );
+        assert_eq!(
+            pro_rata(Uint256::from(100u64), Uint256::one(), Uint256::zero()).unwrap(),
+            Uint256::zero()
+        );
+
+        // the product does not overflow before the division
+        let max = Uint256(!Uint256::zero().0);
+        assert_eq!(pro_rata(max, max, max).unwrap(), max);
+    }
+
+    #[test]
+    fn overflow_is_an_error() {
+        let max = Decimal256(!Decimal256::zero().0);
+
+        assert!(checked_add(max, Decimal256::one()).is_err());
+        assert!(shares_to_mint(Uint256(max.0), Decimal256::one(), Uint256::from(10u64)).is_err());
+    }
+
+    #[test]
+    fn bond_then_unbond_never_returns_more() {
+        let mut rng = Rng(0x5eed_1234_abcd_0001);
+
+        for _ in 0..20_000 {
+            let deposit = rng.amount();
+            let supply = rng.amount() + 1;
+            let total_assets =
+                Decimal256(Uint256::from(rng.amount()).0 * Uint256::from(rng.next()).0);
+
+            if let Some(redeemed) = bond_then_unbond(deposit, total_assets, supply) {
+                assert!(
+                    redeemed <= deposit,
+                    "deposit {} redeemed {} assets {} supply {}",
+                    deposit,
+                    redeemed,
+                    total_assets,
+                    supply
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn bond_then_unbond_after_donation_never_returns_more() {
+        let mut rng = Rng(0x0dd_c0ffee);
+
+        for _ in 0..20_000 {
+            let supply = rng.next() as u128 % 1_000 + 1;
+            // assets inflated far above supply by a donation
+            let total_assets = Decimal256::from_uint256(supply * (rng.next() as u128 % 1_000_000));
+            let deposit = rng.next() as u128 % 10_000_000;
+
+            if let Some(redeemed) = bond_then_unbond(deposit, total_assets, supply) {
+                assert!(redeemed <= deposit);
+            }
+        }
+    }
+}
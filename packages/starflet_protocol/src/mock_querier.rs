@@ -5,7 +5,9 @@ use cosmwasm_std::{
 };
 use std::collections::HashMap;
 
-use cw20::{BalanceResponse as Cw20BalanceResponse, Cw20QueryMsg, TokenInfoResponse};
+use cw20::{
+    BalanceResponse as Cw20BalanceResponse, Cw20QueryMsg, MinterResponse, TokenInfoResponse,
+};
 use terra_cosmwasm::{TaxCapResponse, TaxRateResponse, TerraQuery, TerraQueryWrapper, TerraRoute};
 use terraswap::asset::PairInfo;
 use terraswap::factory::QueryMsg as FactoryQueryMsg;
@@ -36,12 +38,14 @@ pub struct WasmMockQuerier {
 pub struct TokenQuerier {
     // this lets us iterate over all pairs that match the first string
     balances: HashMap<String, HashMap<String, Uint128>>,
+    minters: HashMap<String, String>,
 }
 
 impl TokenQuerier {
     pub fn new(balances: &[(&String, &[(&String, &Uint128)])]) -> Self {
         TokenQuerier {
             balances: balances_to_map(balances),
+            minters: HashMap::new(),
         }
     }
 }
@@ -224,6 +228,16 @@ impl WasmMockQuerier {
                             to_binary(&Cw20BalanceResponse { balance }).unwrap(),
                         ))
                     }
+                    Cw20QueryMsg::Minter {} => {
+                        let res = self.token_querier.minters.get(contract_addr).map(|minter| {
+                            MinterResponse {
+                                minter: minter.to_string(),
+                                cap: None,
+                            }
+                        });
+
+                        SystemResult::Ok(ContractResult::Ok(to_binary(&res).unwrap()))
+                    }
                     _ => panic!("DO NOT ENTER HERE"),
                 },
             },
@@ -247,6 +261,15 @@ impl WasmMockQuerier {
         self.token_querier = TokenQuerier::new(balances);
     }
 
+    // configure the token minters, balances are kept
+    pub fn with_token_minters(&mut self, minters: &[(&String, &String)]) {
+        for (contract_addr, minter) in minters {
+            self.token_querier
+                .minters
+                .insert(contract_addr.to_string(), minter.to_string());
+        }
+    }
+
     // configure the token owner mock querier
     pub fn with_tax(&mut self, rate: Decimal, caps: &[(&String, &Uint128)]) {
         self.tax_querier = TaxQuerier::new(rate, caps);
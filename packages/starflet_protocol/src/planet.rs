@@ -1,18 +1,64 @@
 use cosmwasm_bignumber::Decimal256;
-use cosmwasm_std::Binary;
+use cosmwasm_std::{Binary, Uint128};
 use cw20::Cw20ReceiveMsg;
 use schemars::JsonSchema;
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
 use terraswap::asset::{Asset, AssetInfo};
 
+/// Keeps an explicit `null` apart from a missing field: `Some(None)` clears, `None` leaves as is
+pub fn clearable<'de, T, D>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    T: Deserialize<'de>,
+    D: Deserializer<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct InstantiateMsg {
     pub commission_rate: Decimal256,
     pub asset_info: AssetInfo,
     pub symbol: String,
     pub token_code_id: u64,
+    /// Seconds an unbond waits before it can be withdrawn, 0 for instant
+    pub unbonding_period: Option<u64>,
+    pub max_total_deposit: Option<Uint128>,
+    pub max_deposit_per_user: Option<Uint128>,
+    pub access_mode: Option<AccessMode>,
+    /// May toggle the pause switches besides the owner
+    pub guardian: Option<String>,
+    /// Largest relative share rate move a single execute may cause
+    pub max_rate_change: Option<Decimal256>,
+    /// Seconds a fee increase or owner change waits before it can be applied, 0 for none
+    pub config_delay: Option<u64>,
+    pub marketing: Option<TokenMarketing>,
+    /// Wasm admin of the vaults token, the planet itself when not set
+    pub token_admin: Option<String>,
+    /// Existing cw20 to keep as the vaults token instead of instantiating one.
+    /// The planet must already be its minter
+    pub vaults_token: Option<String>,
+}
+
+/// Marketing info of the vaults token, the planet owner may update it afterwards
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct TokenMarketing {
+    pub project: Option<String>,
+    pub description: Option<String>,
+    pub logo_url: Option<String>,
 }
 
+/// Who may bond into the planet
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum AccessMode {
+    #[default]
+    Open,
+    Allowlist,
+    /// hex encoded sha256 root, leaves are sha256 of the depositor address
+    MerkleRoot {
+        root: String,
+    },
+}
 #[derive(Serialize, strum_macros::Display)]
 #[strum(serialize_all = "snake_case")]
 pub enum Action {
@@ -22,25 +68,127 @@ pub enum Action {
     Unbond,
     Execute,
     Claim,
+    WithdrawUnbonded,
+    UpdateAccessMode,
+    UpdateAllowlist,
+    SetPause,
+    UpdateExecutors,
+    UpdateExecuteTargets,
+    Sync,
+    Shutdown,
+    EmergencyWithdraw,
+    ApplyConfig,
+    CancelConfig,
+    BookBacking,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum ExecuteMsg {
     Receive(Cw20ReceiveMsg),
+    /// A new owner, a higher commission rate or a shorter config delay is scheduled behind the
+    /// config delay, the rest applies at once. The limits and the guardian are removed with `null`
     UpdateConfig {
         owner: Option<String>,
         commission_rate: Option<Decimal256>,
         code_id: Option<u64>,
+        unbonding_period: Option<u64>,
+        #[serde(
+            default,
+            deserialize_with = "clearable",
+            skip_serializing_if = "Option::is_none"
+        )]
+        max_total_deposit: Option<Option<Uint128>>,
+        #[serde(
+            default,
+            deserialize_with = "clearable",
+            skip_serializing_if = "Option::is_none"
+        )]
+        max_deposit_per_user: Option<Option<Uint128>>,
+        #[serde(
+            default,
+            deserialize_with = "clearable",
+            skip_serializing_if = "Option::is_none"
+        )]
+        guardian: Option<Option<String>>,
+        #[serde(
+            default,
+            deserialize_with = "clearable",
+            skip_serializing_if = "Option::is_none"
+        )]
+        max_rate_change: Option<Option<Decimal256>>,
+        config_delay: Option<u64>,
     },
+    /// Applies the scheduled config change once its delay has passed
+    ApplyConfig {},
+    /// Drops the scheduled config change
+    CancelConfig {},
     Bond {
         asset: Asset,
+        proof: Option<Vec<String>>,
+        /// Receives the minted shares, the sender when not set
+        recipient: Option<String>,
+        /// Fail if fewer shares would be minted
+        min_shares_out: Option<Uint128>,
     },
+    /// Runs `msgs` in order, settling revenue once after the last one
     Execute {
-        msg: Binary,
+        msgs: Vec<Binary>,
         is_distribute: bool,
+        /// Must-execute mode: accept a loss up to this amount instead of failing
+        max_loss: Option<Uint128>,
+        /// Fail unless the execute earns at least this much
+        min_profit: Option<Uint128>,
     },
     Claim {},
+    WithdrawUnbonded {},
+    UpdateAccessMode {
+        access_mode: AccessMode,
+    },
+    UpdateAllowlist {
+        add: Vec<String>,
+        remove: Vec<String>,
+    },
+    SetPause {
+        bond: Option<bool>,
+        unbond: Option<bool>,
+        execute: Option<bool>,
+        claim: Option<bool>,
+    },
+    UpdateExecutors {
+        add: Vec<String>,
+        remove: Vec<String>,
+    },
+    UpdateExecuteTargets {
+        add: Vec<ExecuteTarget>,
+        remove: Vec<String>,
+    },
+    /// Reconciles the vaults with the balance held by the planet.
+    /// A surplus is booked as revenue, a shortfall is only reported unless absorbed
+    Sync {
+        /// Write a shortfall off against the commission and the share rate, owner only
+        absorb_shortfall: Option<bool>,
+    },
+    /// Books assets moved in behind an adopted vaults token, owner only.
+    /// They back shares already held, so no commission is taken
+    BookBacking {
+        amount: Uint128,
+    },
+    /// Winds the planet down for good, owner or guardian only.
+    /// Bond, unbond and execute stay disabled and holders leave with `EmergencyWithdraw`
+    Shutdown {
+        /// Base64 `CosmosMsg`s that unwind the strategy positions, limited to the execute targets
+        unwind_msgs: Option<Vec<Binary>>,
+    },
+}
+
+/// Contract `execute` may call, optionally limited to some top-level message names
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ExecuteTarget {
+    pub contract_addr: String,
+    pub messages: Option<Vec<String>>,
+    /// Whether the messages may attach coins, false when not set
+    pub allow_funds: Option<bool>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -48,17 +196,81 @@ pub enum ExecuteMsg {
 pub enum Cw20HookMsg {
     /// Return stable coins to a user
     /// according to exchange rate
-    Bond {},
-    Unbond {},
+    Bond {
+        proof: Option<Vec<String>>,
+        /// Receives the minted shares, the cw20 sender when not set
+        recipient: Option<String>,
+        /// Fail if fewer shares would be minted
+        min_shares_out: Option<Uint128>,
+    },
+    Unbond {
+        /// Receives the unbonded asset, the cw20 sender when not set
+        recipient: Option<String>,
+        /// Fail if less asset would be paid out, after tax
+        min_assets_out: Option<Uint128>,
+    },
+    /// Burns the shares for a pro-rata part of every asset held, once shut down
+    EmergencyWithdraw {},
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum QueryMsg {
     Config {},
-    StakerInfo { staker_addr: String },
+    StakerInfo {
+        staker_addr: String,
+    },
     Commission {},
     Rate {},
+    /// Config change waiting for its delay, all fields empty when there is none
+    PendingConfig {},
+    PendingUnbonds {
+        address: String,
+    },
+    DepositCapacity {
+        address: Option<String>,
+    },
+    IsAllowed {
+        address: String,
+        proof: Option<Vec<String>>,
+    },
+    PauseState {},
+    ExecuteTarget {
+        contract_addr: String,
+    },
+    Executions {
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    /// Shares a bond of `asset` would mint right now, after tax
+    PreviewBond {
+        asset: Asset,
+    },
+    /// Asset an unbond of `shares` would pay out right now, after tax
+    PreviewUnbond {
+        shares: Uint128,
+    },
+    /// Shares worth `amount` of the asset at the current rate, ignoring tax
+    ConvertToShares {
+        amount: Uint128,
+    },
+    /// Asset worth `shares` at the current rate, ignoring tax
+    ConvertToAssets {
+        shares: Uint128,
+    },
+    TotalAssets {},
+    MaxBond {
+        address: String,
+        /// Required in merkle_root mode, as for a bond
+        proof: Option<Vec<String>>,
+    },
+    MaxUnbond {
+        address: String,
+    },
+    /// Lifetime counters, in the planet asset
+    Stats {},
+    /// Vaults as tracked against the balance actually held, to alert on drift
+    Health {},
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -68,11 +280,48 @@ pub struct ConfigResponse {
     pub asset_info: AssetInfo,
     pub token_code_id: u64,
     pub token_address: String,
+    pub unbonding_period: u64,
+    pub max_total_deposit: Option<Uint128>,
+    pub max_deposit_per_user: Option<Uint128>,
+    pub access_mode: AccessMode,
+    pub guardian: Option<String>,
+    pub executors: Vec<String>,
+    pub max_rate_change: Option<Decimal256>,
+    pub config_delay: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PendingConfigResponse {
+    pub owner: Option<String>,
+    pub commission_rate: Option<Decimal256>,
+    pub config_delay: Option<u64>,
+    pub execute_targets: Vec<ExecuteTarget>,
+    /// Time in seconds from which the change can be applied
+    pub release_at: Option<u64>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct StakerInfoResponse {
     pub asset: Asset,
+    /// Asset bonded for the staker, valued when bonded
+    pub deposited: Uint128,
+    /// Asset unbonded by the staker, valued when unbonded before tax
+    pub withdrawn: Uint128,
+    /// Cost of the held shares on average cost. Only shares the planet minted to the staker
+    /// carry a cost: shares received by cw20 transfer count at zero, and shares sent away take
+    /// their part of the cost with them without realizing anything
+    pub cost_basis: Uint128,
+    /// `withdrawn` minus the cost of the unbonded shares
+    pub realized_pnl: Pnl,
+    /// `asset` minus `cost_basis`
+    pub unrealized_pnl: Pnl,
+}
+
+/// Profit or loss, at most one of them is non-zero
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, JsonSchema)]
+pub struct Pnl {
+    pub profit: Uint128,
+    pub loss: Uint128,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -85,5 +334,144 @@ pub struct RateResponse {
     pub rate: Decimal256,
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PendingUnbond {
+    pub asset: Asset,
+    pub release_at: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PendingUnbondsResponse {
+    pub pending_unbonds: Vec<PendingUnbond>,
+    /// Sum of the pending unbonds whose release time has passed
+    pub withdrawable: Asset,
+}
+
+/// Remaining room before each cap, `None` when the cap is not set
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct DepositCapacityResponse {
+    pub total_remaining: Option<Uint128>,
+    pub user_remaining: Option<Uint128>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct IsAllowedResponse {
+    pub allowed: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PauseStateResponse {
+    pub bond: bool,
+    pub unbond: bool,
+    pub execute: bool,
+    pub claim: bool,
+    pub shutdown: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ExecuteTargetResponse {
+    pub allowed: bool,
+    pub messages: Option<Vec<String>>,
+    pub allow_funds: bool,
+}
+
+/// Settlement of one execute
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Execution {
+    pub id: u64,
+    pub height: u64,
+    pub time: u64,
+    pub revenue: Decimal256,
+    pub loss: Decimal256,
+    pub commission: Decimal256,
+    pub rate_before: Decimal256,
+    pub rate_after: Decimal256,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ExecutionsResponse {
+    pub executions: Vec<Execution>,
+    pub total_count: u64,
+    pub total_revenue: Decimal256,
+    pub total_loss: Decimal256,
+    pub total_commission: Decimal256,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct StatsResponse {
+    pub total_bonded: Uint128,
+    /// Valued when unbonded, before tax
+    pub total_unbonded: Uint128,
+    /// Settled by executes, syncs are counted apart
+    pub total_revenue: Decimal256,
+    pub total_commission: Decimal256,
+    pub total_commission_claimed: Uint128,
+    /// Losses absorbed by the vaults in must-execute mode
+    pub total_loss: Decimal256,
+    pub execute_count: u64,
+    /// Syncs that booked a surplus or absorbed a shortfall
+    pub sync_count: u64,
+    pub synced_revenue: Decimal256,
+    pub synced_commission: Decimal256,
+    pub synced_loss: Decimal256,
+    /// Addresses that were bonded for at least once
+    pub depositor_count: u64,
+    pub first_execution_height: Option<u64>,
+    pub last_execution_height: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct HealthResponse {
+    pub tracked: Decimal256,
+    /// Balance held by the planet, pending unbonds excluded
+    pub actual: Decimal256,
+    pub surplus: Decimal256,
+    pub shortfall: Decimal256,
+    /// Surpluses and shortfalls settled by syncs so far
+    pub synced_revenue: Decimal256,
+    pub synced_loss: Decimal256,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PreviewBondResponse {
+    pub shares: Uint128,
+    /// Tax taken from the bond before it reaches the vaults
+    pub tax: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PreviewUnbondResponse {
+    /// Amount received, tax already deducted
+    pub asset: Asset,
+    pub tax: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ConvertToSharesResponse {
+    pub shares: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ConvertToAssetsResponse {
+    pub asset: Asset,
+}
+
+/// Vaults owned by the stakers, commission excluded
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct TotalAssetsResponse {
+    pub asset: Asset,
+}
+
+/// Largest bond accepted now, `None` when no cap applies
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct MaxBondResponse {
+    pub amount: Option<Uint128>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct MaxUnbondResponse {
+    pub shares: Uint128,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct MigrateMsg {}
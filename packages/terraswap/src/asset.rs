@@ -63,7 +63,10 @@ impl Asset {
         }
     }
 
-    pub fn into_msg(self, querier: &QuerierWrapper, recipient: Addr) -> StdResult<CosmosMsg> {
+    pub fn into_msg<T>(self, querier: &QuerierWrapper, recipient: Addr) -> StdResult<CosmosMsg<T>>
+    where
+        T: Clone + fmt::Debug + PartialEq + JsonSchema,
+    {
         let amount = self.amount;
 
         match &self.info {
@@ -82,7 +85,10 @@ impl Asset {
         }
     }
 
-    pub fn into_submsg(self, querier: &QuerierWrapper, recipient: Addr) -> StdResult<SubMsg> {
+    pub fn into_submsg<T>(self, querier: &QuerierWrapper, recipient: Addr) -> StdResult<SubMsg<T>>
+    where
+        T: Clone + fmt::Debug + PartialEq + JsonSchema,
+    {
         Ok(SubMsg::new(self.into_msg(querier, recipient)?))
     }
 
@@ -5,7 +5,7 @@ use crate::querier::{
 };
 
 use cosmwasm_std::testing::MOCK_CONTRACT_ADDR;
-use cosmwasm_std::{to_binary, Addr, BankMsg, Coin, CosmosMsg, Decimal, Uint128, WasmMsg};
+use cosmwasm_std::{to_binary, Addr, BankMsg, Coin, CosmosMsg, Decimal, Empty, Uint128, WasmMsg};
 use cw20::Cw20ExecuteMsg;
 
 #[test]
@@ -211,7 +211,7 @@ fn test_asset() {
 
     assert_eq!(
         token_asset
-            .into_msg(&deps.as_ref().querier, Addr::unchecked("addr0000"))
+            .into_msg::<Empty>(&deps.as_ref().querier, Addr::unchecked("addr0000"))
             .unwrap(),
         CosmosMsg::Wasm(WasmMsg::Execute {
             contract_addr: "asset0000".to_string(),
@@ -226,7 +226,7 @@ fn test_asset() {
 
     assert_eq!(
         native_token_asset
-            .into_msg(&deps.as_ref().querier, Addr::unchecked("addr0000"))
+            .into_msg::<Empty>(&deps.as_ref().querier, Addr::unchecked("addr0000"))
             .unwrap(),
         CosmosMsg::Bank(BankMsg::Send {
             to_address: "addr0000".to_string(),
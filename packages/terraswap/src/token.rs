@@ -2,7 +2,7 @@ use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 use cosmwasm_std::{StdError, StdResult, Uint128};
-use cw20::{Cw20Coin, MinterResponse};
+use cw20::{Cw20Coin, Logo, MinterResponse};
 
 /// TokenContract InstantiateMsg
 #[derive(Serialize, Deserialize, JsonSchema)]
@@ -12,6 +12,18 @@ pub struct InstantiateMsg {
     pub decimals: u8,
     pub initial_balances: Vec<Cw20Coin>,
     pub mint: Option<MinterResponse>,
+    /// Only understood by token codes with the cw20 marketing extension
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub marketing: Option<InstantiateMarketingInfo>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct InstantiateMarketingInfo {
+    pub project: Option<String>,
+    pub description: Option<String>,
+    /// Address allowed to update the marketing info
+    pub marketing: Option<String>,
+    pub logo: Option<Logo>,
 }
 
 impl InstantiateMsg {